@@ -3,10 +3,18 @@ use strum::Display;
 
 use crate::app;
 use crate::components::code;
+use crate::components::copy_string;
 use crate::components::gdbmi;
 use crate::components::gdbtty;
+use crate::components::help;
 use crate::components::home;
+use crate::components::hover;
+use crate::components::key_select;
+use crate::components::macros;
 use crate::components::mouse_select;
+use crate::components::palette;
+use crate::components::transcript;
+use crate::tool::keys;
 #[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
 pub enum Action {
     Tick,
@@ -16,7 +24,22 @@ pub enum Action {
     Resume,
     Quit,
     SwapHV,
+    /// 改变源码窗口占的百分比，按键绑定用
+    ResizeSplit(i16),
+    /// 设置源码窗口占的百分比，所有组件据此重新布局
+    SetSplit(u16),
+    /// 拖动分界线时跟着鼠标改变分割比例，重新布局并显示比例，松开鼠标时再发 SetSplit 保存
+    DragSplit(u16),
+    /// 切换到指定名字的布局预设
+    SetLayout(String),
+    NextLayout,
+    /// 焦点移到布局中的下一个窗口
+    FocusNext,
+    /// 焦点窗口占满整个屏幕，再次触发时还原
+    ToggleZoom,
     ClearScreen,
+    /// 重新读取配置文件并发给所有组件，不用重启
+    ReloadConfig,
     Error(String),
     Help,
     Home(home::Action),
@@ -25,5 +48,163 @@ pub enum Action {
     Code(code::Action),
     Mode(app::Mode),
     CopyStr(String),
+    CopyString(copy_string::Action),
+    Notify(String),
     MouseSelect(mouse_select::Action),
+    KeySelect(key_select::Action),
+    Palette(palette::Action),
+    Transcript(transcript::Action),
+    HelpOverlay(help::Action),
+    /// 执行配置中指定名字的宏
+    Macro(String),
+    Macros(macros::Action),
+    /// 源码窗口中表达式的求值弹窗
+    Hover(hover::Action),
+    /// 按键序列输入了一部分，状态栏据此显示可以接着按的键，为空时清除
+    PendingKeys(keys::PendingHint),
+}
+
+impl Action {
+    /// 帮助和命令面板中按它分组
+    pub fn category(&self) -> &'static str {
+        match self {
+            Action::SwapHV
+            | Action::ResizeSplit(_)
+            | Action::SetSplit(_)
+            | Action::DragSplit(_)
+            | Action::SetLayout(_)
+            | Action::NextLayout
+            | Action::FocusNext
+            | Action::ToggleZoom => "Layout",
+            Action::Home(_) | Action::Gdbtty(_) | Action::Gdbmi(_) => "Console",
+            Action::Code(_) | Action::Hover(_) => "Source",
+            Action::KeySelect(_)
+            | Action::MouseSelect(_)
+            | Action::CopyStr(_)
+            | Action::CopyString(_) => "Selection",
+            Action::Transcript(_) => "Transcript",
+            Action::Macro(_) | Action::Macros(_) => "Macros",
+            Action::Palette(_) | Action::HelpOverlay(_) | Action::Help => "Help",
+            _ => "General",
+        }
+    }
+
+    /// 给人看的说明，没有专门说明的 action 用它的 Debug 输出
+    pub fn description(&self) -> String {
+        use app::Mode;
+        let lower = |v: &dyn std::fmt::Debug| format!("{:?}", v).to_lowercase();
+        let lines = |n: &usize| match n {
+            1 => "1 line".to_string(),
+            n => format!("{} lines", n),
+        };
+        let text = match self {
+            Action::Quit => "Quit",
+            Action::Suspend => "Suspend",
+            Action::ClearScreen => "Clear screen",
+            Action::ReloadConfig => "Reload config",
+            Action::Help => "Show keybindings",
+            Action::Mode(Mode::Code) => "Switch to code window",
+            Action::Mode(Mode::Gdb) => "Switch to gdb console",
+            Action::Mode(mode) => return format!("Switch to {} mode", lower(mode)),
+            Action::SwapHV => "Swap horizontal/vertical split",
+            Action::ResizeSplit(n) if *n >= 0 => return format!("Grow source pane by {}%", n),
+            Action::ResizeSplit(n) => return format!("Shrink source pane by {}%", -n),
+            Action::SetSplit(n) => return format!("Set source pane to {}%", n),
+            Action::SetLayout(name) => return format!("Layout: {}", name),
+            Action::Macro(name) => return format!("Macro: {}", name),
+            Action::NextLayout => "Next layout",
+            Action::FocusNext => "Focus next pane",
+            Action::ToggleZoom => "Zoom focused pane",
+            Action::Home(home::Action::Up(n)) => return format!("Scroll console up {}", lines(n)),
+            Action::Home(home::Action::Down(n)) => {
+                return format!("Scroll console down {}", lines(n))
+            }
+            Action::Home(home::Action::SearchStart) => "Search gdb console",
+            Action::Home(home::Action::SearchNext) => "Next match",
+            Action::Home(home::Action::SearchPrev) => "Previous match",
+            Action::Home(home::Action::SearchCopy) => "Copy matched line",
+            Action::Code(code::Action::Up(n)) => return format!("Scroll code up {}", lines(n)),
+            Action::Code(code::Action::Down(n)) => return format!("Scroll code down {}", lines(n)),
+            Action::Code(code::Action::Left(n)) => return format!("Scroll code left {}", n),
+            Action::Code(code::Action::Right(n)) => return format!("Scroll code right {}", n),
+            Action::Code(code::Action::PageUP) => "Page up",
+            Action::Code(code::Action::PagegDown) => "Page down",
+            Action::Code(code::Action::Top) => "Go to file start",
+            Action::Code(code::Action::Bottom) => "Go to file end",
+            Action::Code(code::Action::LocateStart) => "Locate missing source file",
+            Action::Code(code::Action::LocateComplete) => "Complete path",
+            Action::Code(code::Action::LocateUp) => "Previous suggestion",
+            Action::Code(code::Action::LocateDown) => "Next suggestion",
+            Action::Code(code::Action::LocateTogglePersist) => "Remember the located path",
+            Action::Code(code::Action::LocateAccept) => "Open located file",
+            Action::Code(code::Action::LocateCancel) => "Cancel locating",
+            Action::Code(code::Action::Evaluate) => "Evaluate expression under cursor",
+            Action::Hover(hover::Action::Up) => "Previous member",
+            Action::Hover(hover::Action::Down) => "Next member",
+            Action::Hover(hover::Action::Expand) => "Expand member",
+            Action::Hover(hover::Action::Collapse) => "Collapse member",
+            Action::Hover(hover::Action::Watch) => "Watch expression",
+            Action::Hover(hover::Action::Print) => "Print in console",
+            Action::Hover(hover::Action::Close) => "Close value popup",
+            Action::KeySelect(key_select::Action::Start((pane, mode))) => {
+                let pane = match pane {
+                    mouse_select::SelectionRangeType::SrcWindow => "source",
+                    mouse_select::SelectionRangeType::GdbtTTYWindeow => "gdb console",
+                };
+                return format!("Select text in {} by {}", pane, lower(mode));
+            }
+            Action::KeySelect(key_select::Action::SetMode(mode)) => {
+                return format!("Select by {}", lower(mode))
+            }
+            Action::KeySelect(key_select::Action::SwitchPane) => "Switch pane",
+            Action::KeySelect(key_select::Action::Left(_)) => "Move cursor left",
+            Action::KeySelect(key_select::Action::Right(_)) => "Move cursor right",
+            Action::KeySelect(key_select::Action::Up(_)) => "Move cursor up",
+            Action::KeySelect(key_select::Action::Down(_)) => "Move cursor down",
+            Action::KeySelect(key_select::Action::LineStart) => "Go to line start",
+            Action::KeySelect(key_select::Action::LineEnd) => "Go to line end",
+            Action::KeySelect(key_select::Action::Yank) => "Copy selection",
+            Action::KeySelect(key_select::Action::Cancel) => "Cancel selection",
+            Action::Transcript(transcript::Action::Save) => "Save transcript",
+            Action::Palette(palette::Action::Open(input)) if input.is_empty() => "Command palette",
+            Action::Palette(palette::Action::Open(_)) => "Command palette for a gdb command",
+            Action::Palette(palette::Action::Up) => "Previous command",
+            Action::Palette(palette::Action::Down) => "Next command",
+            Action::Palette(palette::Action::Accept) => "Run selected command",
+            Action::Palette(palette::Action::Cancel) => "Close palette",
+            Action::HelpOverlay(help::Action::Up(_)) => "Scroll help up",
+            Action::HelpOverlay(help::Action::Down(_)) => "Scroll help down",
+            Action::HelpOverlay(help::Action::Close) => "Close help",
+            _ => return format!("{:?}", self),
+        };
+        text.to_string()
+    }
+
+    /// 按键前输入了次数时，带行数的 action 乘上次数，其它的不变
+    pub fn with_count(self, count: usize) -> Action {
+        use code::Action as C;
+        use key_select::Action as K;
+        let n = |v: usize| v.saturating_mul(count);
+        let n16 = |v: u16| v.saturating_mul(u16::try_from(count).unwrap_or(u16::MAX));
+        match self {
+            Action::Code(C::Up(v)) => Action::Code(C::Up(n(v))),
+            Action::Code(C::Down(v)) => Action::Code(C::Down(n(v))),
+            Action::Code(C::Left(v)) => Action::Code(C::Left(n(v))),
+            Action::Code(C::Right(v)) => Action::Code(C::Right(n(v))),
+            Action::Home(home::Action::Up(v)) => Action::Home(home::Action::Up(n(v))),
+            Action::Home(home::Action::Down(v)) => Action::Home(home::Action::Down(n(v))),
+            Action::KeySelect(K::Left(v)) => Action::KeySelect(K::Left(n16(v))),
+            Action::KeySelect(K::Right(v)) => Action::KeySelect(K::Right(n16(v))),
+            Action::KeySelect(K::Up(v)) => Action::KeySelect(K::Up(n16(v))),
+            Action::KeySelect(K::Down(v)) => Action::KeySelect(K::Down(n16(v))),
+            Action::HelpOverlay(help::Action::Up(v)) => Action::HelpOverlay(help::Action::Up(n(v))),
+            Action::HelpOverlay(help::Action::Down(v)) => {
+                Action::HelpOverlay(help::Action::Down(n(v)))
+            }
+            Action::ResizeSplit(v) => {
+                Action::ResizeSplit(v.saturating_mul(i16::try_from(count).unwrap_or(i16::MAX)))
+            }
+            action => action,
+        }
+    }
 }
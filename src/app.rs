@@ -4,18 +4,28 @@ use crate::components::gdbtty::Action as GdbttyAction;
 use crate::{
     action,
     components::{
-        code::Code, fps::FpsCounter, gdbmi::Gdbmi, gdbtty::Gdbtty, home::Home,
-        mouse_select::MouseSelectComponent, startpage::StartPage, statusbar::StatusBar, Component,
+        code::Code, fps::FpsCounter, gdbmi::Gdbmi, gdbtty::Gdbtty, help::HelpOverlay, home::Home,
+        hover::Hover, key_select::KeySelectComponent, macros::Macros,
+        mouse_select::MouseSelectComponent, palette::Palette, startpage::StartPage,
+        statusbar::StatusBar, tabs::TabBars, transcript::Transcript, Component,
+    },
+    config::{self, ColorDepth, Config},
+    tool::{
+        keys::{KeySequence, PendingHint},
+        layout::{self, LayoutService, SavedLayout, SharedLayout},
     },
-    config::Config,
     tui::{Event, Tui},
 };
 use color_eyre::Result;
 use crossterm::event::KeyEvent;
 use ratatui::prelude::Rect;
 use serde::{Deserialize, Serialize};
+use std::{
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
 use tokio::sync::mpsc;
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 
 pub struct App {
     config: Config,
@@ -25,11 +35,16 @@ pub struct App {
     should_quit: bool,
     should_suspend: bool,
     mode: Mode,
-    last_tick_key_events: Vec<KeyEvent>,
+    keys: KeySequence,
     action_tx: mpsc::UnboundedSender<action::Action>,
     action_rx: mpsc::UnboundedReceiver<action::Action>,
     gdb_path: String,
     gdb_args: Vec<String>,
+    layout: SharedLayout,
+    /// 命令行指定的颜色深度，重新加载配置时仍然用它
+    color: Option<ColorDepth>,
+    /// 配置文件的修改时间，每个 tick 比较一次
+    config_stamp: Vec<Option<SystemTime>>,
 }
 
 #[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -37,15 +52,25 @@ pub enum Mode {
     #[default]
     Gdb,
     Code,
+    Search,
+    Visual,
+    Locate,
+    Palette,
+    Help,
+    Hover,
 }
 
 impl App {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         tick_rate: f64,
         frame_rate: f64,
         gdb_path: String,
         args: Vec<String>,
         gdb_args: Vec<String>,
+        transcript: Option<PathBuf>,
+        transcript_timestamp: bool,
+        color: Option<ColorDepth>,
     ) -> Result<Self> {
         let gdb_args = match args.is_empty() {
             true => gdb_args,
@@ -55,6 +80,19 @@ impl App {
                 .chain(args)
                 .collect::<Vec<_>>(),
         };
+        let mut config = Config::new()?;
+        config.styles.colors = color.unwrap_or(config.styles.colors).resolve();
+        let saved = SavedLayout::load(&SavedLayout::path());
+        if let Some(split) = saved.split {
+            config.layout.split = split;
+        }
+        if let Some(preset) = saved
+            .preset
+            .filter(|p| config.layout.presets.contains_key(p))
+        {
+            config.layout.preset = preset;
+        }
+        let layout = LayoutService::new(&config.layout).shared();
         let (action_tx, action_rx) = mpsc::unbounded_channel();
         Ok(Self {
             tick_rate,
@@ -67,18 +105,28 @@ impl App {
                 Box::new(Gdbtty::new()),
                 Box::new(StartPage::new()),
                 Box::new(StatusBar::new()),
+                Box::new(TabBars::new()),
                 Box::new(CopyString::new()),
                 Box::new(MouseSelectComponent::new()),
+                Box::new(KeySelectComponent::new()),
+                Box::new(Hover::new()),
+                Box::new(Palette::new()),
+                Box::new(HelpOverlay::new()),
+                Box::new(Macros::new()),
+                Box::new(Transcript::new(transcript, transcript_timestamp)),
             ],
             should_quit: false,
             should_suspend: false,
-            config: Config::new()?,
+            config,
             mode: Mode::Gdb,
-            last_tick_key_events: Vec::new(),
+            keys: KeySequence::new(),
             action_tx,
             action_rx,
             gdb_path,
             gdb_args,
+            layout,
+            color,
+            config_stamp: config::config_files_stamp(),
         })
     }
 
@@ -100,6 +148,16 @@ impl App {
         for component in self.components.iter_mut() {
             component.register_config_handler(self.config.clone())?;
         }
+        let size = tui.size()?;
+        self.layout
+            .borrow_mut()
+            .set_area(Rect::new(0, 0, size.width, size.height));
+        if let Some(pane) = layout::pane_of_mode(self.mode) {
+            self.layout.borrow_mut().set_focus(pane);
+        }
+        for component in self.components.iter_mut() {
+            component.register_layout_handler(self.layout.clone())?;
+        }
         for component in self.components.iter_mut() {
             component.init(tui.size()?)?;
         }
@@ -107,6 +165,12 @@ impl App {
         self.init()?;
 
         let action_tx = self.action_tx.clone();
+        if !self.config.issues.is_empty() {
+            action_tx.send(action::Action::Notify(format!(
+                "{} problems in the config, see rgdb --check-config",
+                self.config.issues.len()
+            )))?;
+        }
         loop {
             self.handle_events(&mut tui).await?;
             self.handle_actions(&mut tui)?;
@@ -147,30 +211,40 @@ impl App {
     }
 
     fn handle_key_event(&mut self, key: KeyEvent) -> Result<()> {
-        let action_tx = self.action_tx.clone();
         let Some(keymap) = self.config.keybindings.get(&self.mode) else {
             return Ok(());
         };
-        match keymap.get(&vec![key]) {
-            Some(action) => {
-                info!("Got action: {action:?}");
-                action_tx.send(action.clone())?;
-            }
-            _ => {
-                // If the key was not handled as a single key action,
-                // then consider it for multi-key combinations.
-                self.last_tick_key_events.push(key);
-
-                // Check for multi-key combinations
-                if let Some(action) = keymap.get(&self.last_tick_key_events) {
-                    info!("Got action: {action:?}");
-                    action_tx.send(action.clone())?;
-                }
-            }
+        let was_pending = !self.keys.is_empty();
+        // 会把按键当作文字输入的模式不接受次数
+        let counts = matches!(self.mode, Mode::Code | Mode::Visual);
+        for action in self.keys.push(keymap, key, counts) {
+            info!("Got action: {action:?}");
+            self.action_tx.send(action)?;
+        }
+        if was_pending || !self.keys.is_empty() {
+            let hint = self.keys.hint(keymap);
+            self.action_tx.send(action::Action::PendingKeys(hint))?;
         }
         Ok(())
     }
 
+    /// 按键序列超时后执行已经完整的绑定，清除状态栏上的提示
+    fn expire_keys(&mut self) -> Vec<action::Action> {
+        if self.keys.is_empty() {
+            return vec![];
+        }
+        let Some(keymap) = self.config.keybindings.get(&self.mode) else {
+            self.keys.clear();
+            return vec![];
+        };
+        let timeout = Duration::from_millis(self.config.input.sequence_timeout_ms);
+        let mut actions = self.keys.expire(keymap, timeout);
+        if self.keys.is_empty() {
+            actions.push(action::Action::PendingKeys(PendingHint::default()));
+        }
+        actions
+    }
+
     fn handle_actions(&mut self, tui: &mut Tui) -> Result<()> {
         while let Ok(action) = self.action_rx.try_recv() {
             let mut add_actions: Vec<_> = vec![];
@@ -179,18 +253,87 @@ impl App {
             }
             match action {
                 action::Action::Tick => {
-                    self.last_tick_key_events.drain(..);
+                    add_actions.extend(self.expire_keys());
+                    let stamp = config::config_files_stamp();
+                    if stamp != self.config_stamp {
+                        self.config_stamp = stamp;
+                        add_actions.push(action::Action::ReloadConfig);
+                    }
                 }
                 action::Action::Quit => self.should_quit = true,
                 action::Action::Suspend => self.should_suspend = true,
                 action::Action::Resume => self.should_suspend = false,
                 action::Action::ClearScreen => tui.terminal.clear()?,
-                action::Action::Resize(w, h) => self.handle_resize(tui, w, h)?,
+                action::Action::Resize(w, h) => {
+                    self.layout.borrow_mut().set_area(Rect::new(0, 0, w, h));
+                    self.handle_resize(tui, w, h)?
+                }
                 action::Action::Render => self.render(tui)?,
-                action::Action::Mode(mode) => self.set_mode(mode),
+                action::Action::Mode(mode) => {
+                    if !self.keys.is_empty() {
+                        self.keys.clear();
+                        add_actions.push(action::Action::PendingKeys(PendingHint::default()));
+                    }
+                    self.set_mode(mode);
+                    let pane = layout::pane_of_mode(mode);
+                    if pane.is_some_and(|pane| self.layout.borrow_mut().set_focus(pane)) {
+                        add_actions.push(self.resize_action(tui)?);
+                    }
+                }
                 action::Action::SwapHV => {
-                    let size: ratatui::prelude::Size = tui.size()?;
-                    add_actions.push(action::Action::Resize(size.width, size.height));
+                    self.layout.borrow_mut().swap();
+                    add_actions.push(self.resize_action(tui)?);
+                }
+                action::Action::ResizeSplit(delta) => {
+                    let split =
+                        i32::from(self.layout.borrow().split()).saturating_add(delta.into());
+                    add_actions.push(action::Action::SetSplit(layout::clamp_split(split)));
+                }
+                action::Action::SetSplit(split) => {
+                    self.layout.borrow_mut().set_split(split);
+                    self.save_layout();
+                    add_actions.push(self.resize_action(tui)?);
+                    add_actions.push(action::Action::Notify(format!("Split {}%", split)));
+                }
+                action::Action::DragSplit(split) => {
+                    self.layout.borrow_mut().set_split(split);
+                    add_actions.push(self.resize_action(tui)?);
+                    add_actions.push(action::Action::Notify(format!("Split {}%", split)));
+                }
+                action::Action::SetLayout(ref name) => {
+                    let msg = match self.layout.borrow_mut().set_preset(name) {
+                        true => format!("Layout {}", name),
+                        false => format!("No layout named {}", name),
+                    };
+                    add_actions.push(action::Action::Notify(msg));
+                    self.save_layout();
+                    add_actions.push(self.resize_action(tui)?);
+                }
+                action::Action::NextLayout => {
+                    let name = self.layout.borrow_mut().next_preset();
+                    self.save_layout();
+                    add_actions.push(self.resize_action(tui)?);
+                    add_actions.push(action::Action::Notify(format!("Layout {}", name)));
+                }
+                action::Action::ToggleZoom => {
+                    let zoomed = self.layout.borrow_mut().toggle_zoom();
+                    add_actions.push(self.resize_action(tui)?);
+                    let msg = match (zoomed, self.key_hint(&action::Action::ToggleZoom)) {
+                        (true, Some(key)) => format!("Zoom in, {} to restore", key),
+                        (true, None) => "Zoom in".to_string(),
+                        (false, _) => "Zoom out".to_string(),
+                    };
+                    add_actions.push(action::Action::Notify(msg));
+                }
+                action::Action::ReloadConfig => {
+                    add_actions.push(self.reload_config()?);
+                    add_actions.push(self.resize_action(tui)?);
+                }
+                action::Action::FocusNext => {
+                    let pane = self.layout.borrow_mut().focus_next();
+                    if let Some(pane) = pane {
+                        add_actions.push(action::Action::Mode(pane.into()));
+                    }
                 }
                 _ => {}
             }
@@ -206,6 +349,62 @@ impl App {
         Ok(())
     }
 
+    /// 布局改变后让各组件按新的区域重新调整，和终端大小改变时一样
+    fn resize_action(&self, tui: &Tui) -> Result<action::Action> {
+        let size: ratatui::prelude::Size = tui.size()?;
+        Ok(action::Action::Resize(size.width, size.height))
+    }
+
+    /// 重新读取配置发给所有组件，返回要显示的提示。出错的条目和启动时一样用默认值，
+    /// 运行中调整过的分割比例和布局保留，已经高亮过的文件不会按新主题重新高亮
+    fn reload_config(&mut self) -> Result<action::Action> {
+        let mut config = match Config::new() {
+            Ok(config) => config,
+            Err(e) => {
+                error!("reload config fail: {}", e);
+                return Ok(action::Action::Notify(format!("Reload config fail: {}", e)));
+            }
+        };
+        config.styles.colors = self.color.unwrap_or(config.styles.colors).resolve();
+        self.layout.borrow_mut().set_presets(&config.layout);
+        for component in self.components.iter_mut() {
+            component.register_config_handler(config.clone())?;
+        }
+        let msg = match config.issues.len() {
+            0 => "Config reloaded".to_string(),
+            n => format!("Config reloaded, {} problems, see rgdb --check-config", n),
+        };
+        self.config = config;
+        if !self.keys.is_empty() {
+            self.keys.clear();
+            self.action_tx
+                .send(action::Action::PendingKeys(PendingHint::default()))?;
+        }
+        Ok(action::Action::Notify(msg))
+    }
+
+    /// 当前模式中绑定到 `action` 的最短按键，用在提示里
+    fn key_hint(&self, action: &action::Action) -> Option<String> {
+        self.config
+            .keybindings
+            .get(&self.mode)?
+            .iter()
+            .filter(|(_, bound)| *bound == action)
+            .map(|(keys, _)| config::key_sequence_to_string(keys))
+            .min_by(|a, b| a.len().cmp(&b.len()).then_with(|| a.cmp(b)))
+    }
+
+    fn save_layout(&self) {
+        let layout = self.layout.borrow();
+        let saved = SavedLayout {
+            split: Some(layout.split()),
+            preset: Some(layout.preset().to_string()),
+        };
+        if let Err(e) = saved.save(&SavedLayout::path()) {
+            error!("save layout fail: {}", e);
+        }
+    }
+
     fn handle_resize(&mut self, tui: &mut Tui, w: u16, h: u16) -> Result<()> {
         tui.resize(Rect::new(0, 0, w, h))?;
         self.render(tui)?;
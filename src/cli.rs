@@ -1,7 +1,8 @@
 use clap::builder::styling::{self, AnsiColor};
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
-use crate::config::{get_config_dir, get_data_dir};
+use crate::config::{get_config_dir, get_data_dir, ColorDepth, Config};
 const STYLES: styling::Styles = styling::Styles::styled()
     .header(AnsiColor::Yellow.on_default())
     .usage(AnsiColor::Green.on_default())
@@ -12,7 +13,7 @@ const STYLES: styling::Styles = styling::Styles::styled()
 #[command(author, version = version(), about, styles=STYLES)]
 pub struct Cli {
     /// Set which gdb debugger to use.
-    #[arg(short('d'), long, value_name = "PATH_TO_GDB", default_value_t = String::from("gdb"), value_parser = gdb_check)]
+    #[arg(short('d'), long, value_name = "PATH_TO_GDB", default_value_t = String::from("gdb"))]
     pub gdb: String,
 
     /// Tick rate, i.e. number of ticks per second
@@ -27,9 +28,101 @@ pub struct Cli {
     #[arg(long, value_name = "ARGS", num_args(1..), allow_hyphen_values(true))]
     pub args: Vec<String>,
 
+    /// Write the gdb console output to this file as plain text while rgdb runs
+    #[arg(long, value_name = "PATH")]
+    pub transcript: Option<PathBuf>,
+
+    /// Prefix every transcript line with the seconds elapsed since rgdb started
+    #[arg(long)]
+    pub transcript_timestamp: bool,
+
+    /// Colors of the terminal, syntax highlight and ui colors are mapped to the nearest ones it has [default: the "colors" in the config, auto]
+    #[arg(long, value_name = "WHEN", value_enum)]
+    pub color: Option<ColorDepth>,
+
+    /// Check the config files, print the problems found and exit
+    #[arg(long)]
+    pub check_config: bool,
+
     /// Args pass to gdb which not change
     #[arg(value_name = "GDB_ARGS", last(true), allow_hyphen_values(true))]
     pub gdb_args: Vec<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+impl Cli {
+    /// 启动 gdb 前才查找 gdb 的路径，`list` 和 `--check-config` 这样不用 gdb 的命令在没有 gdb 的机器上也能用
+    pub fn gdb_path(&self) -> Result<String, clap::Error> {
+        gdb_check(&self.gdb).map_err(|e| {
+            use clap::CommandFactory;
+            Self::command().error(
+                clap::error::ErrorKind::ValueValidation,
+                format!(
+                    "invalid value '{}' for '--gdb <PATH_TO_GDB>': {}",
+                    self.gdb, e
+                ),
+            )
+        })
+    }
+}
+
+#[derive(Subcommand, Debug, PartialEq, Eq)]
+pub enum Command {
+    /// List the highlight themes and syntaxes, including the ones in the config directory
+    List {
+        #[arg(value_enum)]
+        kind: Option<ListKind>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ListKind {
+    Themes,
+    Syntaxes,
+}
+
+/// 打印可用的主题和语法，语法后面列出它的扩展名
+pub fn list(kind: Option<ListKind>) {
+    if kind != Some(ListKind::Syntaxes) {
+        println!("Themes:");
+        crate::tool::theme_names()
+            .into_iter()
+            .for_each(|name| println!("    {}", name));
+    }
+    if kind != Some(ListKind::Themes) {
+        println!("Syntaxes:");
+        let mut syntaxes = crate::tool::syntaxes().iter().collect::<Vec<_>>();
+        syntaxes.sort_by_key(|s| s.name.to_lowercase());
+        syntaxes
+            .into_iter()
+            .filter(|s| !s.hidden)
+            .for_each(|s| println!("    {} [{}]", s.name, s.file_extensions.join(", ")));
+    }
+}
+
+/// 打印配置中的问题，有问题时返回非零的退出码
+pub fn check_config() -> i32 {
+    println!("Config directory: {}", get_config_dir().display());
+    let config = match Config::new() {
+        Ok(config) => config,
+        Err(e) => {
+            println!("{}", e);
+            return 1;
+        }
+    };
+    config.issues.iter().for_each(|issue| println!("{}", issue));
+    match config.issues.len() {
+        0 => {
+            println!("No problems found");
+            0
+        }
+        n => {
+            println!("{} problems found, the defaults are used instead", n);
+            1
+        }
+    }
 }
 
 const VERSION_MESSAGE: &str = concat!(
@@ -70,7 +163,8 @@ fn gdb_check(s: &str) -> Result<String, String> {
 
 #[cfg(test)]
 mod tests {
-    use crate::cli::Cli;
+    use crate::cli::{Cli, Command, ListKind};
+    use crate::config::ColorDepth;
     use clap::Parser;
     #[test]
     fn test_args() {
@@ -114,4 +208,44 @@ mod tests {
         println!("{:?}", cli.args);
         assert!(cli.args == vec!["./a.out", "-h", "--", "--args"]);
     }
+
+    #[test]
+    fn test_list_command() {
+        let cli = Cli::try_parse_from(["rgdb", "-d", "sh", "list", "themes"]).unwrap();
+        assert_eq!(
+            cli.command,
+            Some(Command::List {
+                kind: Some(ListKind::Themes)
+            })
+        );
+        let cli = Cli::try_parse_from(["rgdb", "-d", "sh", "--", "list"]).unwrap();
+        assert_eq!(cli.command, None);
+        assert_eq!(cli.gdb_args, vec!["list"]);
+        // 不启动 gdb，找不到 gdb 也可以列出
+        let cli = Cli::try_parse_from(["rgdb", "-d", "no-such-gdb", "list"]).unwrap();
+        assert!(cli.command.is_some());
+        assert!(cli.gdb_path().is_err());
+    }
+
+    #[test]
+    fn test_color() {
+        let cli = Cli::try_parse_from(["rgdb", "-d", "sh", "--color", "256"]).unwrap();
+        assert_eq!(cli.color, Some(ColorDepth::Ansi256));
+        let cli = Cli::try_parse_from(["rgdb", "-d", "sh", "--color=never"]).unwrap();
+        assert_eq!(cli.color, Some(ColorDepth::Never));
+        let cli = Cli::try_parse_from(["rgdb", "-d", "sh"]).unwrap();
+        assert_eq!(cli.color, None);
+        assert!(Cli::try_parse_from(["rgdb", "-d", "sh", "--color", "8"]).is_err());
+    }
+
+    #[test]
+    fn test_check_config() {
+        let cli = Cli::try_parse_from(["rgdb", "-d", "sh", "--check-config"]).unwrap();
+        assert!(cli.check_config);
+        let cli = Cli::try_parse_from(["rgdb", "-d", "sh"]).unwrap();
+        assert!(!cli.check_config);
+        // 只检查配置，没有 gdb 也可以
+        let cli = Cli::try_parse_from(["rgdb", "-d", "no-such-gdb", "--check-config"]).unwrap();
+        assert!(cli.check_config);
+    }
 }
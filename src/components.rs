@@ -7,17 +7,24 @@ use ratatui::{
 use tokio::sync::mpsc::UnboundedSender;
 // use tracing::debug;
 
-use crate::{action::Action, config::Config, tui::Event};
+use crate::{action::Action, config::Config, tool::layout::SharedLayout, tui::Event};
 
 pub mod code;
+pub mod copy_string;
 pub mod fps;
 pub mod gdbmi;
 pub mod gdbtty;
+pub mod help;
 pub mod home;
+pub mod hover;
+pub mod key_select;
+pub mod macros;
+pub mod mouse_select;
+pub mod palette;
 pub mod startpage;
 pub mod statusbar;
-pub mod mouse_select;
-pub mod copy_string;
+pub mod tabs;
+pub mod transcript;
 
 /// `Component` is a trait that represents a visual and interactive element of the user interface.
 ///
@@ -50,6 +57,19 @@ pub trait Component {
         let _ = config; // to appease clippy
         Ok(())
     }
+    /// Register the layout shared by all components, which tells where each pane is.
+    ///
+    /// # Arguments
+    ///
+    /// * `layout` - The shared layout, updated by the app when the layout changes.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - An Ok result or an error.
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        let _ = layout; // to appease clippy
+        Ok(())
+    }
     /// Initialize the component with a specified area if necessary.
     ///
     /// # Arguments
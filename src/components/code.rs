@@ -1,13 +1,22 @@
 use super::{mouse_select, Component};
+use crate::action;
+use crate::app::Mode;
 use crate::components::code::asmfuncdata::AsmFuncData;
 use crate::components::code::breakpoint::BreakPointData;
+use crate::components::code::cache::LruCache;
+use crate::components::code::highlight::{HighlightLine, Highlighter};
+use crate::components::code::locate::LocatePrompt;
+use crate::components::code::pathmap::PathMapper;
 use crate::components::code::srcfiledata::SrcFileData;
 use crate::components::gdbmi;
-use crate::components::mouse_select::{MouseSelect, SelectionRange, TextSelection};
+use crate::components::hover;
+use crate::components::macros::{self, MacroContext};
+use crate::components::mouse_select::{MouseSelect, SelectMode, SelectionRange, TextSelection};
+use crate::config::{Config, StyleKey};
 use crate::mi::frame::Frame as FrameMi;
+use crate::tool::layout::SharedLayout;
 use crate::tool::{self, get_str_by_display_range};
-use crate::tool::{FileData, HashSelf, HighlightFileData, TextFileData};
-use crate::{action, config::Config};
+use crate::tool::{FileData, HashSelf, TextFileData};
 use color_eyre::{eyre::Ok, Result};
 use ratatui::{prelude::*, widgets::*};
 use serde::{Deserialize, Serialize};
@@ -21,6 +30,10 @@ use tokio::sync::mpsc::UnboundedSender;
 use tracing::{debug, error, info};
 mod asmfuncdata;
 pub mod breakpoint;
+mod cache;
+mod highlight;
+mod locate;
+mod pathmap;
 mod srcfiledata;
 mod test;
 
@@ -29,15 +42,34 @@ pub struct Code {
     command_tx: Option<UnboundedSender<action::Action>>,
     config: Config,
 
-    files_set: HashMap<Rc<String>, SrcFileData>,
+    files_set: LruCache<SrcFileData>,
     read_fail_files_set: HashSet<String>,
-    asm_func_set: HashMap<Rc<String>, AsmFuncData>,
+    asm_func_set: LruCache<AsmFuncData>,
+    highlighters: HashMap<Rc<String>, UnboundedSender<(usize, usize)>>,
     breakpoint_set: HashMap<Rc<String>, BreakPointData>,
+    path_mapper: PathMapper,
+    last_show_file: Option<(String, u64, FrameMi)>,
+    missing_source: Option<String>,
+    executable_mtime: Option<std::time::SystemTime>,
+    locate: Option<LocatePrompt>,
+    mode: Mode,
     file_need_show: FileNeedShow,
     vertical_scroll: usize,
     horizontial_scroll: usize,
+    /// 鼠标或可视模式光标在源码窗口中的屏幕位置 (row, column)
+    cursor: Option<(u16, u16)>,
+    /// 鼠标停留的表达式，停够时间后弹窗显示它的值
+    mouse_hover: Option<MouseHover>,
     area: Rect,
-    is_horizontal: bool,
+    layout: SharedLayout,
+}
+
+struct MouseHover {
+    expr: String,
+    pos: (u16, u16),
+    since: std::time::Instant,
+    /// 已经发出了弹窗
+    sent: bool,
 }
 
 #[derive(Default)]
@@ -68,14 +100,30 @@ pub enum Action {
     FileReadEnd(String),
     FileReadFail((String, FrameMi)),
     AsmFileEnd,
-    FilehighlightLine((String, Vec<(ratatui::style::Color, String)>)),
-    FilehighlightEnd(String),
+    FilehighlightChunk((String, usize, Vec<HighlightLine>)),
     Up(usize),
     Down(usize),
     Left(usize),
     Right(usize),
     PageUP,
     PagegDown,
+    /// 滚到文件开头
+    Top,
+    /// 滚到文件末尾
+    Bottom,
+    LocateStart,
+    LocateComplete,
+    LocateUp,
+    LocateDown,
+    LocateTogglePersist,
+    LocateAccept,
+    LocateCancel,
+    /// 后台按文件名找到的候选 (找不到的文件, 候选)
+    LocateFound((String, Vec<std::path::PathBuf>)),
+    /// 可视模式的光标移到了源码窗口中的屏幕位置 (row, column)
+    SetCursor((u16, u16)),
+    /// 在当前栈帧中对光标处的表达式求值并弹窗显示
+    Evaluate,
 }
 
 #[derive(Default)]
@@ -103,6 +151,8 @@ impl Code {
     pub fn new() -> Self {
         let mut a = Self::default();
         a.vertical_scroll = 1;
+        a.files_set = LruCache::new("source cache", usize::MAX);
+        a.asm_func_set = LruCache::new("asm cache", usize::MAX);
         a
     }
 
@@ -139,7 +189,7 @@ impl Code {
             },
             FileNeedShow::AsmFile(func) => {
                 let name = &func.name;
-                match self.asm_func_set.get(&Rc::new(name.clone())) {
+                match self.asm_func_set.get(name) {
                     Some(asm_file) => match asm_file.get_read_done() {
                         true => match asm_file.get_line_id(&func.addr) {
                             Some(id) => FileDataReal::AsmFile((asm_file, id)),
@@ -167,9 +217,8 @@ impl Code {
     fn get_breakpoints(&self) -> Vec<&BreakPointData> {
         self.breakpoint_set.values().collect()
     }
-    fn set_area(&mut self, area: &layout::Size) {
-        let area = Rect::new(0, 0, area.width, area.height);
-        tool::Layouts { src: self.area, .. } = (area, self.is_horizontal).into();
+    fn set_area(&mut self) {
+        self.area = self.layout.borrow().layouts().src;
     }
     fn file_down(&mut self, n: usize) {
         self.vertical_scroll = self.vertical_scroll.saturating_add(n);
@@ -284,13 +333,13 @@ impl Code {
         });
         info
     }
-    fn get_file_show_info(&self, area: Rect) -> Option<(&dyn FileData, LineInfo, Areas)> {
+    fn get_file_show_info(&self) -> Option<(&dyn FileData, LineInfo, Areas)> {
         // 获取布局区域
         let tool::Layouts {
             src: src_area,
             src_status: status_area,
             ..
-        } = tool::Layouts::from((area, self.is_horizontal));
+        } = self.layout.borrow().layouts();
         let draw_info = self.get_file_show_areas_and_len(src_area).and_then(
             |(file, lineinfo, area_no_status)| {
                 Some((
@@ -370,17 +419,14 @@ impl Code {
             };
 
             if let Some(n) = pointer_size {
+                let style = self.config.styles.get(StyleKey::Pointer);
                 let text_pointer = Line::from_iter(
-                    std::iter::once(
-                        Span::raw('├'.to_string()).style(Style::default().light_green()),
-                    )
-                    .chain(std::iter::repeat_n(
-                        Span::raw('─'.to_string()).style(Style::default().light_green()),
-                        n.saturating_sub(1),
-                    ))
-                    .chain(std::iter::once(
-                        Span::raw('>'.to_string()).style(Style::default().light_green()),
-                    )),
+                    std::iter::once(Span::raw('├'.to_string()).style(style))
+                        .chain(std::iter::repeat_n(
+                            Span::raw('─'.to_string()).style(style),
+                            n.saturating_sub(1),
+                        ))
+                        .chain(std::iter::once(Span::raw('>'.to_string()).style(style))),
                 );
 
                 let paragraph_pointer = Paragraph::new(text_pointer);
@@ -396,15 +442,15 @@ impl Code {
         end_line: usize,
         area_src: Rect,
     ) {
-        let src = match (file.get_read_done(), file.get_highlight_done()) {
-            (true, true) => file.get_highlight_lines_range(start_line, end_line).0,
-            (true, false) => file
-                .get_lines_range(start_line, end_line)
-                .0
-                .iter()
-                .map(|s| vec![(ratatui::style::Color::White, s.to_string())])
-                .collect(),
-            _ => vec![],
+        let text = self
+            .config
+            .styles
+            .get(StyleKey::Text)
+            .fg
+            .unwrap_or(Color::Reset);
+        let src = match file.get_read_done() {
+            true => file.get_highlight_lines_range(start_line, end_line, text).0,
+            false => vec![],
         };
         let text_src = Text::from_iter(
             src.iter()
@@ -413,12 +459,56 @@ impl Code {
         let paragraph_src = Paragraph::new(text_src).scroll((0, self.horizontial_scroll as u16));
         frame.render_widget(paragraph_src, area_src);
     }
+    fn draw_locate(&self, frame: &mut Frame, prompt: &LocatePrompt, area: Rect) {
+        let area = area.inner(Margin {
+            vertical: 1,
+            horizontal: 2,
+        });
+        let persist = match prompt.persist() {
+            true => "[x]",
+            false => "[ ]",
+        };
+        let searching = match prompt.searching() {
+            true => "(searching…) ",
+            false => "",
+        };
+        let block = Block::bordered()
+            .title(format!(" Locate {} {}", prompt.missing(), searching))
+            .title_bottom(format!(
+                " <Tab> Complete <Enter> Open <Ctrl-s> {} Remember <Esc> Cancel ",
+                persist
+            ));
+        let input = Line::from(format!("> {}", prompt.input()))
+            .style(self.config.styles.get(StyleKey::PromptInput));
+        let suggestions = prompt.suggestions().iter().enumerate().map(|(id, path)| {
+            let line = Line::from(path.to_string_lossy().into_owned());
+            match id == prompt.selected() {
+                true => line.style(self.config.styles.get(StyleKey::ListSelected)),
+                false => line,
+            }
+        });
+        // 选中的候选保持在可见范围内
+        let height = area.height.saturating_sub(3) as usize;
+        let skip = prompt.selected().saturating_add(1).saturating_sub(height);
+        let text = Text::from_iter(std::iter::once(input).chain(suggestions.skip(skip)));
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
     fn draw_status(&self, frame: &mut Frame, file: &dyn FileData, area_status: Rect) {
-        let title = file.get_status();
+        let title = match (&self.file_need_show, &self.missing_source) {
+            (FileNeedShow::AsmFile(_), Some(missing)) => format!(
+                "{} | {} not found, <Ctrl-o> locate",
+                file.get_status(),
+                missing
+            ),
+            _ if self.is_source_newer_than_executable() => {
+                format!("{} | source is newer than executable", file.get_status())
+            }
+            _ => file.get_status(),
+        };
         let scroll_x = title.len().saturating_sub(area_status.width as usize) as u16;
         let paragraph_status = Paragraph::new(title)
-            .fg(Color::Black)
-            .bg(Color::Gray)
+            .style(self.config.styles.get(StyleKey::StatusBar))
             .scroll((0, scroll_x));
         frame.render_widget(paragraph_status, area_status);
     }
@@ -435,9 +525,9 @@ impl Code {
         let text_ids = Text::from_iter(ids.iter().map(|s| {
             let line = Line::from_iter(s.to_string().chars().map(|c| Span::raw(c.to_string())));
             if *s == line_id {
-                line.style(Style::default().light_green())
+                line.style(self.config.styles.get(StyleKey::LineNumberCurrent))
             } else {
-                line
+                line.style(self.config.styles.get(StyleKey::LineNumber))
             }
         }));
 
@@ -459,8 +549,8 @@ impl Code {
             if let Some(enable) = bp.get(&(*s as u64)) {
                 let line = Line::from_iter(s.to_string().chars().map(|c| Span::raw(c.to_string())));
                 match enable {
-                    true => line.style(Style::default().fg(Color::Rgb(255, 0, 0))),
-                    false => line.style(Style::default().fg(Color::Rgb(255, 128, 0))), //orange
+                    true => line.style(self.config.styles.get(StyleKey::Breakpoint)),
+                    false => line.style(self.config.styles.get(StyleKey::BreakpointDisabled)),
                 }
             } else {
                 Line::from("")
@@ -484,7 +574,7 @@ impl Code {
         let up_half = hight.div_euclid(2);
         let scrollbar = Scrollbar::new(ScrollbarOrientation::VerticalRight)
             .symbols(scrollbar::VERTICAL)
-            .style(Style::default().fg(Color::White));
+            .style(self.config.styles.get(StyleKey::Scrollbar));
 
         let mut state = ScrollbarState::new(text_len.saturating_sub(hight))
             .position(self.vertical_scroll.saturating_sub(up_half));
@@ -504,25 +594,155 @@ impl Code {
             }
         };
     }
+    /// 丢弃磁盘上已经改变的文件，下次显示时重新读取和高亮
+    fn drop_changed_files(&mut self) {
+        let changed = self
+            .files_set
+            .iter()
+            .filter(|(_, file)| file.need_reload())
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+        changed.into_iter().for_each(|name| {
+            info!("file {} changed on disk, reload", &name);
+            self.files_set.remove(&name);
+            self.highlighters.remove(&name);
+        });
+    }
+    /// 超出内存预算时淘汰缓存的源码和反汇编，当前显示的和有断点的保留
+    fn shrink_cache(&mut self) {
+        let (show_file, show_func) = match &self.file_need_show {
+            FileNeedShow::None => (None, None),
+            FileNeedShow::SrcFile(file) => (Some(&file.name), None),
+            FileNeedShow::AsmFile(func) => (None, Some(&func.name)),
+        };
+        let breakpoints = self.breakpoint_set.values().collect::<Vec<_>>();
+        let has_breakpoint = |file: &dyn FileData| {
+            !file
+                .get_breakpoint_need_show_in_range(breakpoints.clone(), 0, usize::MAX)
+                .is_empty()
+        };
+        self.files_set
+            .shrink(|name, file| Some(name) == show_file || has_breakpoint(file))
+            .iter()
+            .for_each(|name| {
+                self.highlighters.remove(name);
+            });
+        self.asm_func_set
+            .shrink(|name, func| Some(name) == show_func || has_breakpoint(func));
+    }
+    /// 请求高亮当前可见的行，已经请求过的块不再重复请求
+    fn request_highlight(&mut self) {
+        let FileNeedShow::SrcFile(file) = &self.file_need_show else {
+            return;
+        };
+        let tool::Layouts { src, .. } = self.layout.borrow().layouts();
+        let (start, end) = self.get_windows_show_file_range(src.height as usize);
+        let Some(tx) = self.highlighters.get(&file.name) else {
+            return;
+        };
+        let requested = self.files_set.update(&file.name, |file_data| {
+            file_data.take_highlight_request(start, end)
+        });
+        if requested == Some(true) && tx.send((start, end)).is_err() {
+            debug!("highlight file {} already stop", &file.name);
+        }
+    }
+    /// 当前源码比可执行文件新时，显示的行可能和程序对不上
+    fn is_source_newer_than_executable(&self) -> bool {
+        let FileNeedShow::SrcFile(file) = &self.file_need_show else {
+            return false;
+        };
+        let src_mtime = self
+            .files_set
+            .get(&file.name)
+            .and_then(|f| f.stamp())
+            .map(|s| s.mtime);
+        match (src_mtime, self.executable_mtime) {
+            (Some(src), Some(exe)) => src > exe,
+            _ => false,
+        }
+    }
+    /// 按文件名查找丢失源码的根目录：配置的搜索目录和当前目录
+    /// 在后台线程中按文件名查找，大目录树或网络挂载上也不会卡住界面
+    fn locate_search(&self, missing: String) {
+        let Some(send) = self.command_tx.clone() else {
+            error!("locate search not start");
+            return;
+        };
+        let roots = self.locate_roots();
+        tokio::task::spawn_blocking(move || {
+            let found = locate::search(&missing, &roots);
+            tool::send_action(
+                &send,
+                action::Action::Code(Action::LocateFound((missing, found))),
+            );
+        });
+    }
+    fn locate_roots(&self) -> Vec<std::path::PathBuf> {
+        self.config
+            .source
+            .search_paths
+            .iter()
+            .cloned()
+            .chain(std::env::current_dir().ok())
+            .collect()
+    }
+    /// 记住找到的文件对应的目录映射，然后重新打开当前位置
+    fn locate_accept(&mut self) -> Option<action::Action> {
+        let prompt = self.locate.as_ref()?;
+        let Some(found) = prompt.chosen() else {
+            return Some(action::Action::Notify("No such source file".to_string()));
+        };
+        let mapping = locate::directory_mapping(prompt.missing(), &found);
+        let persist = prompt.persist();
+        self.locate = None;
+        if let Some((from, to)) = mapping {
+            info!("map source dir {} to {}", &from, &to);
+            if persist {
+                if let Err(e) =
+                    pathmap::save_rule(&pathmap::saved_rules_path(), from.clone(), to.clone())
+                {
+                    error!("save source map fail: {}", e);
+                }
+            }
+            self.path_mapper.add_rule(from, to);
+        }
+        if let Some(send) = self.command_tx.clone() {
+            tool::send_action(&send, action::Action::Mode(Mode::Code));
+        }
+        self.remap_source_paths()
+    }
+    /// 路径规则变化后重新映射断点，并重试之前没能打开的文件
+    fn remap_source_paths(&mut self) -> Option<action::Action> {
+        let mapper = &self.path_mapper;
+        self.breakpoint_set
+            .values_mut()
+            .for_each(|bp| bp.map_fullname(|f| mapper.resolve(f)));
+        self.read_fail_files_set.clear();
+        match (&self.file_need_show, &self.last_show_file) {
+            (FileNeedShow::SrcFile(_), _) | (_, None) => None,
+            (_, Some(show)) => Some(action::Action::Gdbmi(gdbmi::Action::ShowFile(show.clone()))),
+        }
+    }
     fn show_file(&mut self, file: String, line_id: u64, frame: FrameMi) -> Option<action::Action> {
         let mut ret = None;
         match self.read_fail_files_set.contains(&file) {
-            true => match &frame.func {
-                Some(func) => {
+            true => {
+                self.missing_source = Some(frame.fullname.clone().unwrap_or(file));
+                if let Some(func) = &frame.func {
                     ret = Some(action::Action::Gdbmi(gdbmi::Action::ShowAsm((
                         func.clone(),
                         frame.addr.clone(),
                         frame,
                     ))));
                 }
-                _ => {}
-            },
+            }
             false => {
                 self.file_need_show = FileNeedShow::SrcFile(FileNeedShowSrcFile {
                     name: file.clone(),
                     line: line_id,
                 });
-                match self.files_set.contains_key(&file) {
+                match self.files_set.access(&file) {
                     false => {
                         if let Some(send) = self.command_tx.clone() {
                             let file_data = SrcFileData::new(file.clone());
@@ -547,6 +767,89 @@ impl Code {
         }
         ret
     }
+    /// 光标处由 pick 取出的单词和所在行，光标不在源码上时为 None
+    fn cursor_word_and_line(
+        &self,
+        pick: fn(&str, usize) -> Option<&str>,
+    ) -> Option<(Option<String>, usize)> {
+        let (row, column) = self.cursor?;
+        let (line, col) = self.change_tui_position_to_file_position(row, column)?;
+        let (file, ..) = self.get_file_show_areas_and_len(self.area)?;
+        let (lines, start, _) = file.get_lines_range(line, line + 1);
+        let word = match (start == line, lines.first()) {
+            (true, Some(s)) => pick(s, col).map(str::to_string),
+            _ => None,
+        };
+        Some((word, line))
+    }
+    /// 光标处的表达式，汇编窗口中没有可以求值的表达式
+    fn cursor_expression(&self) -> Option<String> {
+        match self.file_need_show {
+            FileNeedShow::SrcFile(_) => self.cursor_word_and_line(tool::expression_at)?.0,
+            _ => None,
+        }
+    }
+    fn evaluate(&self) -> action::Action {
+        match (self.cursor, self.cursor_expression()) {
+            (Some(pos), Some(expr)) => {
+                action::Action::Hover(hover::Action::Open((expr, pos, true)))
+            }
+            _ => action::Action::Notify("No identifier under the cursor".to_string()),
+        }
+    }
+    /// 鼠标移到了别的表达式上时重新计时，之前弹出的窗口关闭
+    fn mouse_moved(&mut self) {
+        let expr = self.cursor_expression();
+        if self.mouse_hover.as_ref().map(|h| &h.expr) == expr.as_ref() {
+            return;
+        }
+        if self.mouse_hover.take().is_some_and(|h| h.sent) {
+            if let Some(send) = self.command_tx.clone() {
+                tool::send_action(&send, action::Action::Hover(hover::Action::Leave));
+            }
+        }
+        self.mouse_hover = expr.zip(self.cursor).map(|(expr, pos)| MouseHover {
+            expr,
+            pos,
+            since: std::time::Instant::now(),
+            sent: false,
+        });
+    }
+    /// 鼠标停够时间后弹窗，只在没有其它弹窗的模式下
+    fn mouse_hover_tick(&mut self) -> Option<action::Action> {
+        let delay = self.config.hover.mouse_delay_ms;
+        if delay == 0 || !matches!(self.mode, Mode::Code | Mode::Gdb) {
+            return None;
+        }
+        let hover = self.mouse_hover.as_mut().filter(|h| !h.sent)?;
+        if hover.since.elapsed() < std::time::Duration::from_millis(delay) {
+            return None;
+        }
+        hover.sent = true;
+        Some(action::Action::Hover(hover::Action::Open((
+            hover.expr.clone(),
+            hover.pos,
+            false,
+        ))))
+    }
+    /// 宏占位符的值，行号优先用光标所在行，否则用停下的行，选择的文本由宏组件补上
+    fn macro_context(&self) -> MacroContext {
+        let cursor = self.cursor_word_and_line(tool::word_at);
+        let word_under_cursor = cursor.as_ref().and_then(|(word, _)| word.clone());
+        let (file, line) = match (&self.file_need_show, &self.last_show_file) {
+            (FileNeedShow::SrcFile(show), Some((file, ..))) => {
+                let line = cursor.map_or(show.line, |(_, line)| line as u64);
+                (Some(file.clone()), Some(line))
+            }
+            _ => (None, None),
+        };
+        MacroContext {
+            word_under_cursor,
+            file,
+            line,
+            selection: None,
+        }
+    }
     fn change_tui_position_to_file_position(
         &self,
         row: u16,
@@ -555,6 +858,14 @@ impl Code {
         if let Some((_, lineinfo, AreasNoStatus { src: src_area, .. })) =
             self.get_file_show_areas_and_len(self.area)
         {
+            // 落在行号栏上的位置按行首处理
+            let column = match self
+                .area
+                .contains(ratatui::layout::Position::new(column, row))
+            {
+                true => column.max(src_area.x),
+                false => column,
+            };
             if src_area.contains(ratatui::layout::Position::new(column, row)) {
                 let row = (row as usize)
                     .saturating_sub(src_area.y as usize)
@@ -571,6 +882,31 @@ impl Code {
         }
     }
 
+    /// 把选择的屏幕坐标转换为文件位置，整行选择时列扩展到行首和行尾
+    fn change_select_to_file_range(
+        &self,
+        select: &MouseSelect,
+    ) -> Option<((usize, usize), (usize, usize))> {
+        let MouseSelect {
+            start: (start_row, start_col),
+            end: (end_row, end_col),
+            mode,
+        } = select;
+        match mode {
+            SelectMode::Line => {
+                let (_, _, areas) = self.get_file_show_areas_and_len(self.area)?;
+                let (start, _) =
+                    self.change_tui_position_to_file_position(*start_row, areas.src.x)?;
+                let (end, _) = self.change_tui_position_to_file_position(*end_row, areas.src.x)?;
+                Some(((start, 0), (end, usize::MAX)))
+            }
+            _ => Some((
+                self.change_tui_position_to_file_position(*start_row, *start_col)?,
+                self.change_tui_position_to_file_position(*end_row, *end_col)?,
+            )),
+        }
+    }
+
     /// 从文件位置获取选择范围信息
     fn get_selection_ranges_and_text<'a>(
         &self,
@@ -579,6 +915,7 @@ impl Code {
         file_end: (usize, usize),
         area: Rect,
         start_row: u16,
+        block: bool,
     ) -> Option<Vec<(&'a str, SelectionRange)>> {
         let (lines_str, line_start_id, _) = file.get_lines_range(file_start.0, file_end.0 + 1);
 
@@ -598,39 +935,39 @@ impl Code {
             .map(|(id, s)| {
                 let id_file = id + line_start_id;
                 // 计算起始列
-                let start = if id_file == file_start.0 {
-                    file_start.1.max(0)
+                let start = if block || id_file == file_start.0 {
+                    file_start.1
                 } else {
                     0
                 };
                 // 计算结束列
                 let width = s.width();
-                let end = if id_file == file_end.0 {
+                let end = if block || id_file == file_end.0 {
                     file_end.1.min(width)
                 } else {
                     width
                 };
 
-                // 获取实际的字符范围
-                get_str_by_display_range(s, start, end).and_then(|(s, start, end)| {
-                    // 转换为屏幕坐标
-                    Some((
-                        s,
-                        SelectionRange {
-                            line_number: id.saturating_add(start_row.into()),
-                            start_column: start
-                                .saturating_sub(self.horizontial_scroll)
-                                .saturating_add(area.x.into()),
-                            end_column: end
-                                .saturating_sub(self.horizontial_scroll)
-                                .min(area.width.saturating_sub(1) as usize)
-                                .saturating_add(area.x.into()),
-                        },
-                    ))
-                })
+                // 获取实际的字符范围，比起始列短的行选中空串，块选择不会因此整个失败
+                let (s, start, end) =
+                    get_str_by_display_range(s, start, end).unwrap_or(("", start, start));
+                // 转换为屏幕坐标
+                (
+                    s,
+                    SelectionRange {
+                        line_number: id.saturating_add(start_row.into()),
+                        start_column: start
+                            .saturating_sub(self.horizontial_scroll)
+                            .saturating_add(area.x.into()),
+                        end_column: end
+                            .saturating_sub(self.horizontial_scroll)
+                            .min(area.width.saturating_sub(1) as usize)
+                            .saturating_add(area.x.into()),
+                    },
+                )
             })
-            .collect::<Option<Vec<_>>>();
-        selected
+            .collect::<Vec<_>>();
+        Some(selected)
     }
 
     /// 获取区域宽度和起始位置信息
@@ -648,20 +985,32 @@ impl TextSelection for Code {
         let (_, area) = self.get_area_info(self.area)?;
 
         // 2. 获取选择状态和文件信息
-        let MouseSelect {
-            start: (start_row, start_col),
-            end: (end_row, end_col),
-        } = select;
         let (file, ..) = self.get_file_show_areas_and_len(self.area)?;
 
         // 3. 转换为文件位置
-        let file_start = self.change_tui_position_to_file_position(*start_row, *start_col)?;
-        let file_end = self.change_tui_position_to_file_position(*end_row, *end_col)?;
+        let (file_start, file_end) = self.change_select_to_file_range(select)?;
 
         // 4. 生成选择范围
-        self.get_selection_ranges_and_text(file, file_start, file_end, area.src, *start_row)
-            .and_then(|v| Some(v.into_iter().map(|(s, _)| s).collect::<Vec<_>>().join("")))
-            .and_then(|s| if s.is_empty() { None } else { Some(s) })
+        let block = select.mode == SelectMode::Block;
+        self.get_selection_ranges_and_text(
+            file,
+            file_start,
+            file_end,
+            area.src,
+            select.start.0,
+            block,
+        )
+        .map(|v| {
+            let segs = v.into_iter().map(|(s, _)| s);
+            match block {
+                true => segs
+                    .map(|s| s.trim_end_matches('\n'))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                false => segs.collect::<Vec<_>>().join(""),
+            }
+        })
+        .and_then(|s| if s.is_empty() { None } else { Some(s) })
     }
 
     fn get_selected_area(&self, select: &MouseSelect) -> Option<Vec<SelectionRange>> {
@@ -669,38 +1018,67 @@ impl TextSelection for Code {
         let (_, area) = self.get_area_info(self.area)?;
 
         // 2. 获取选择状态和文件信息
-        let MouseSelect {
-            start: (start_row, start_col),
-            end: (end_row, end_col),
-        } = select;
         let (file, ..) = self.get_file_show_areas_and_len(self.area)?;
 
         // 3. 转换为文件位置
-        let file_start = self.change_tui_position_to_file_position(*start_row, *start_col)?;
-        let file_end = self.change_tui_position_to_file_position(*end_row, *end_col)?;
+        let (file_start, file_end) = self.change_select_to_file_range(select)?;
 
         // 4. 生成选择范围
-        self.get_selection_ranges_and_text(file, file_start, file_end, area.src, *start_row)
-            .and_then(|v| Some(v.into_iter().map(|(_, s)| s).collect::<Vec<_>>()))
+        self.get_selection_ranges_and_text(
+            file,
+            file_start,
+            file_end,
+            area.src,
+            select.start.0,
+            select.mode == SelectMode::Block,
+        )
+        .map(|v| v.into_iter().map(|(_, s)| s).collect::<Vec<_>>())
     }
 }
 
 impl Component for Code {
-    fn init(&mut self, area: Size) -> Result<()> {
+    fn init(&mut self, _area: Size) -> Result<()> {
         // let mut clipboard = Clipboard::new()?;
         // clipboard.set_text("Hello, clipboard!")?;
-        self.set_area(&area);
+        self.set_area();
         Ok(())
     }
     fn register_action_handler(&mut self, tx: UnboundedSender<action::Action>) -> Result<()> {
         self.command_tx = Some(tx);
         Ok(())
     }
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        // 重新加载配置时保留运行中得到的映射，保存的映射重复添加不会重复
+        self.path_mapper.set_config(&config.source);
+        self.files_set.set_budget(config.source.cache_bytes);
+        self.asm_func_set.set_budget(config.source.cache_bytes);
+        pathmap::load_saved_rules(&pathmap::saved_rules_path())
+            .into_iter()
+            .for_each(|(from, to)| self.path_mapper.add_rule(from, to));
         self.config = config;
         Ok(())
     }
+    fn handle_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<action::Action>> {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        if let (Mode::Locate, Some(prompt)) = (self.mode, self.locate.as_mut()) {
+            match key.code {
+                KeyCode::Char(c) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                    prompt.push_char(c);
+                }
+                KeyCode::Backspace => prompt.pop_char(),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
     fn handle_mouse_event(
         &mut self,
         mouse: crossterm::event::MouseEvent,
@@ -708,6 +1086,14 @@ impl Component for Code {
         let is_in = self
             .area
             .contains(ratatui::layout::Position::new(mouse.column, mouse.row));
+        if matches!(
+            mouse.kind,
+            crossterm::event::MouseEventKind::Moved
+                | crossterm::event::MouseEventKind::Down(crossterm::event::MouseButton::Left)
+        ) {
+            self.cursor = is_in.then_some((mouse.row, mouse.column));
+            self.mouse_moved();
+        }
 
         // 处理滚动事件
         let action = match mouse.kind {
@@ -726,13 +1112,13 @@ impl Component for Code {
         let mut ret = None;
         match action {
             action::Action::Tick => {
-                // add any logic here that should run on every tick
+                ret = self.mouse_hover_tick();
             }
             action::Action::Render => {
                 // add any logic here that should run on every render
             }
-            action::Action::Resize(x, y) => {
-                self.set_area(&layout::Size::new(x, y));
+            action::Action::Resize(_, _) => {
+                self.set_area();
             }
             action::Action::Code(Action::Up(p)) => {
                 self.file_up(p);
@@ -754,14 +1140,89 @@ impl Component for Code {
                 let n = self.area.height as usize;
                 ret = Some(action::Action::Code(Action::Down(n)));
             }
-            action::Action::SwapHV => {
-                self.is_horizontal = !self.is_horizontal;
+            action::Action::Code(Action::Top) => {
+                self.file_up(usize::MAX);
+            }
+            action::Action::Code(Action::Bottom) => {
+                self.file_down(usize::MAX);
+            }
+            action::Action::Mode(mode) => {
+                self.mode = mode;
+                if mode != Mode::Locate {
+                    self.locate = None;
+                }
+            }
+            action::Action::Code(Action::LocateStart) => {
+                if let Some(missing) = self.missing_source.clone() {
+                    self.locate = Some(LocatePrompt::new(missing.clone()));
+                    self.locate_search(missing);
+                    ret = Some(action::Action::Mode(Mode::Locate));
+                }
+            }
+            action::Action::Code(Action::LocateFound((missing, found))) => {
+                if let Some(prompt) = self.locate.as_mut() {
+                    prompt.set_found(&missing, found);
+                }
+            }
+            action::Action::Code(Action::LocateComplete) => {
+                if let Some(prompt) = self.locate.as_mut() {
+                    prompt.complete();
+                }
+            }
+            action::Action::Code(Action::LocateUp) => {
+                if let Some(prompt) = self.locate.as_mut() {
+                    prompt.up();
+                }
+            }
+            action::Action::Code(Action::LocateDown) => {
+                if let Some(prompt) = self.locate.as_mut() {
+                    prompt.down();
+                }
+            }
+            action::Action::Code(Action::LocateTogglePersist) => {
+                if let Some(prompt) = self.locate.as_mut() {
+                    prompt.toggle_persist();
+                }
+            }
+            action::Action::Code(Action::LocateAccept) => {
+                ret = self.locate_accept();
+            }
+            action::Action::Code(Action::LocateCancel) => {
+                ret = Some(action::Action::Mode(Mode::Code));
+            }
+            action::Action::Code(Action::SetCursor(pos)) => {
+                self.cursor = Some(pos);
+            }
+            action::Action::Code(Action::Evaluate) => {
+                ret = Some(self.evaluate());
+            }
+            action::Action::Macro(name) => {
+                ret = Some(action::Action::Macros(macros::Action::Run((
+                    name,
+                    self.macro_context(),
+                ))));
             }
             action::Action::Gdbmi(gdbmi::Action::ShowFile((file, line_id, frame))) => {
+                self.last_show_file = Some((file.clone(), line_id, frame.clone()));
+                self.missing_source = None;
+                self.drop_changed_files();
+                let file = self.path_mapper.resolve(&file);
                 ret = self.show_file(file, line_id, frame);
             }
+            action::Action::Gdbmi(gdbmi::Action::Executable(exe)) => {
+                self.executable_mtime = std::fs::metadata(&exe)
+                    .and_then(|meta| meta.modified())
+                    .ok();
+            }
+            action::Action::Gdbmi(gdbmi::Action::SubstitutePaths(rules)) => {
+                ret = match self.path_mapper.set_gdb_rules(rules) {
+                    true => self.remap_source_paths(),
+                    false => None,
+                };
+            }
             action::Action::Gdbmi(gdbmi::Action::Breakpoint(bkpt)) => {
-                let val = BreakPointData::from(&bkpt);
+                let mut val = BreakPointData::from(&bkpt);
+                val.map_fullname(|f| self.path_mapper.resolve(f));
                 let key = val.get_key();
                 self.breakpoint_set.remove(&key);
                 self.breakpoint_set.insert(key, val);
@@ -770,36 +1231,44 @@ impl Component for Code {
                 self.breakpoint_set.remove(&Rc::new(id.to_string()));
             }
             action::Action::Code(Action::FileReadOneLine((file_name, line))) => {
-                self.files_set.entry(file_name.into()).and_modify(|file| {
-                    file.add_line(line);
-                });
+                self.files_set
+                    .update(&file_name, |file| file.add_line(line));
             }
             action::Action::Code(Action::FileReadEnd(file)) => {
-                match self.files_set.remove_entry(&file) {
-                    Some((name, mut file_data)) => {
-                        if let Some(send) = self.command_tx.clone() {
-                            file_data.set_read_done();
-                            let lines = file_data.get_lines().clone();
-                            self.files_set.insert(name, file_data);
-                            let highlight_thread =
-                                SrcFileData::highlight_file(file.clone(), lines, send.clone());
-                            tokio::task::spawn_local(async {
-                                highlight_thread.await;
-                            });
-                            debug!("highlight file {} start", file);
-                        } else {
-                            let msg = format!("read file {} thread not start", &file);
-                            error!("{}", &msg);
-                        }
+                let read = self.files_set.update(&file, |file_data| {
+                    file_data.set_read_done();
+                    (file_data.get_key(), file_data.get_lines().clone())
+                });
+                match (read, self.command_tx.clone()) {
+                    (Some((key, lines)), Some(send)) => {
+                        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+                        self.highlighters.insert(key, tx);
+                        let name = file.clone();
+                        let config = self.config.highlight.clone();
+                        let styles = self.config.styles.clone();
+                        tokio::task::spawn_local(async move {
+                            if let Some(highlighter) =
+                                Highlighter::new(name, lines, &config, &styles)
+                            {
+                                highlighter.run(rx, send).await;
+                            }
+                        });
+                        debug!("highlight file {} start", file);
                     }
-                    _ => {
+                    (Some(_), None) => {
+                        let msg = format!("read file {} thread not start", &file);
+                        error!("{}", &msg);
+                    }
+                    (None, _) => {
                         error!("file {} not found", &file);
                     }
                 }
                 self.set_vertical_to_stop_point(&file);
+                self.shrink_cache();
             }
             action::Action::Code(Action::FileReadFail((file, frame))) => {
                 self.files_set.remove(&file);
+                self.missing_source = Some(frame.fullname.clone().unwrap_or(file.clone()));
                 self.read_fail_files_set.insert(file);
                 self.file_need_show = FileNeedShow::None;
                 match &frame.func {
@@ -813,41 +1282,33 @@ impl Component for Code {
                     _ => {}
                 }
             }
-            action::Action::Code(Action::FilehighlightLine((file_name, line))) => {
-                self.files_set.entry(file_name.into()).and_modify(|file| {
-                    file.add_highlight_line(line);
-                });
-            }
-            action::Action::Code(Action::FilehighlightEnd(file)) => {
-                match self.files_set.remove_entry(&file) {
-                    Some((name, mut file_data)) => {
-                        file_data.set_highlight_done();
-                        self.files_set.insert(name, file_data);
-                    }
-                    _ => {
-                        error!("file {} not found", &file);
-                    }
-                }
+            action::Action::Code(Action::FilehighlightChunk((file_name, start, lines))) => {
+                self.files_set
+                    .update(&file_name, |file| file.add_highlight_chunk(start, lines));
             }
             action::Action::Gdbmi(gdbmi::Action::ReadAsmFunc(func)) => {
-                debug!("asm_func_set{:?}", &self.asm_func_set.keys());
-                self.asm_func_set
-                    .entry(func.func.clone().into())
-                    .and_modify(|asm| {
-                        asm.add_lines(&func);
-                        asm.set_read_done();
-                        asm.add_highlight_lines(&func);
-                        asm.set_highlight_done();
-                        ret = Some(action::Action::Code(Action::AsmFileEnd));
-                    });
+                debug!(
+                    "asm_func_set{:?}",
+                    &self.asm_func_set.keys().collect::<Vec<_>>()
+                );
+                let (theme, styles) = (&self.config.highlight.theme, &self.config.styles);
+                let read = self.asm_func_set.update(&func.func, |asm| {
+                    asm.add_lines(&func);
+                    asm.set_read_done();
+                    asm.add_highlight_lines(&func, theme, styles);
+                });
+                if read.is_some() {
+                    ret = Some(action::Action::Code(Action::AsmFileEnd));
+                }
                 self.set_vertical_to_stop_point(&func.func);
+                self.shrink_cache();
             }
             action::Action::Gdbmi(gdbmi::Action::ShowAsm((func, addr, _))) => {
                 self.file_need_show = FileNeedShow::AsmFile(FileNeedShowAsmFunc {
                     name: func.clone(),
                     addr: addr.clone(),
                 });
-                match self.asm_func_set.contains_key(&func) {
+                match self.asm_func_set.access(&func) {
                     false => {
                         let file_data = AsmFuncData::new(func.clone());
                         self.asm_func_set.insert(file_data.get_key(), file_data);
@@ -902,14 +1363,22 @@ impl Component for Code {
     /// 绘制代码视图的主要函数
     /// # 参数
     /// * `frame` - 用于绘制UI的Frame
-    /// * `area` - 绘制区域的矩形范围
-    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+    /// * `_area` - 整个终端的区域，源码窗口的位置从共享的布局中取
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        if self.layout.borrow().layouts().src.is_empty() {
+            return Ok(());
+        }
+        self.request_highlight();
         // 准备绘制所需的所有信息
-        let draw_info = self.get_file_show_info(area);
+        let draw_info = self.get_file_show_info();
         // 执行实际的绘制操作
         if let Some((file, line_info, areas)) = draw_info {
             self.draw_all(frame, file, line_info, areas);
         }
+        if let Some(prompt) = &self.locate {
+            let tool::Layouts { src, .. } = self.layout.borrow().layouts();
+            self.draw_locate(frame, prompt, src);
+        }
         Ok(())
     }
 }
@@ -1,4 +1,6 @@
 use crate::components::code::breakpoint::BreakPointData;
+use crate::components::code::cache::{self, CacheEntry};
+use crate::config::{adapt_color, StyleKey, Styles};
 use crate::mi::disassemble::DisassembleFunction;
 use crate::tool;
 use crate::tool::{addr_to_u64, FileData, HashSelf, HighlightFileData, TextFileData};
@@ -14,7 +16,8 @@ pub struct AsmFuncData {
     pub lines: Vec<String>,
     pub lines_highlight: Vec<Vec<(ratatui::style::Color, String)>>,
     pub read_done: bool,
-    pub highlight_done: bool,
+    /// 添加行和高亮后算一次
+    mem_size: usize,
 }
 
 impl HashSelf<String> for AsmFuncData {
@@ -90,16 +93,11 @@ impl TextFileData for AsmFuncData {
 }
 
 impl HighlightFileData for AsmFuncData {
-    fn get_highlight_done(&self) -> bool {
-        self.highlight_done
-    }
-    fn set_highlight_done(&mut self) {
-        self.highlight_done = true;
-    }
     fn get_highlight_lines_range(
         &self,
         start: usize,
         end: usize,
+        _text: ratatui::style::Color,
     ) -> (Vec<Vec<(ratatui::style::Color, String)>>, usize, usize) {
         let n = self.lines_highlight.len().saturating_add(1);
         let end = n.min(end);
@@ -115,6 +113,15 @@ impl HighlightFileData for AsmFuncData {
         )
     }
 }
+impl CacheEntry for AsmFuncData {
+    fn mem_size(&self) -> usize {
+        self.mem_size
+    }
+    fn is_busy(&self) -> bool {
+        !self.read_done
+    }
+}
+
 impl AsmFuncData {
     pub fn new(func_name: String) -> Self {
         Self {
@@ -123,9 +130,13 @@ impl AsmFuncData {
             lines_highlight: vec![],
             addrs: vec![],
             read_done: false,
-            highlight_done: false,
+            mem_size: 0,
         }
     }
+    fn update_mem_size(&mut self) {
+        self.mem_size = cache::lines_mem_size(&self.lines, &self.lines_highlight)
+            .saturating_add(self.addrs.capacity() * std::mem::size_of::<(u64, u64)>());
+    }
     pub fn add_lines(&mut self, lines: &DisassembleFunction) {
         let len = lines
             .insts
@@ -151,10 +162,18 @@ impl AsmFuncData {
 
             self.create_addr_map(lines, 1_usize);
         }
+        self.update_mem_size();
     }
-    pub fn add_highlight_lines(&mut self, _func: &DisassembleFunction) {
+    pub fn add_highlight_lines(
+        &mut self,
+        _func: &DisassembleFunction,
+        theme: &str,
+        styles: &Styles,
+    ) {
         let ext = "asm";
-        let theme = tool::get_theme();
+        let text = styles.get(StyleKey::Text).fg.unwrap_or(Color::Reset);
+        let title = styles.get(StyleKey::Title).fg.unwrap_or(text);
+        let theme = tool::get_theme(theme);
         let ps = tool::get_syntax_set(ext);
         let lines = self.get_lines();
 
@@ -166,17 +185,14 @@ impl AsmFuncData {
                 .map(|(id, line)| {
                     if id == 0 {
                         vec![
-                            (
-                                Color::White,
-                                "Dump of assembler code for function ".to_string(),
-                            ),
-                            (Color::Blue, self.get_file_name()),
-                            (Color::White, ":\n".to_string()),
+                            (text, "Dump of assembler code for function ".to_string()),
+                            (title, self.get_file_name()),
+                            (text, ":\n".to_string()),
                         ]
                     } else if id == lines.len().saturating_sub(1) {
-                        vec![(Color::White, line.clone())]
+                        vec![(text, line.clone())]
                     } else {
-                        match h.highlight_line(line, &ps) {
+                        match h.highlight_line(line, ps) {
                             std::result::Result::Ok(ranges) => ranges
                                 .into_iter()
                                 .map(|(c, s)| {
@@ -197,7 +213,7 @@ impl AsmFuncData {
                                     &line,
                                     e
                                 );
-                                vec![(Color::White, line.clone())]
+                                vec![(text, line.clone())]
                             }
                         }
                     }
@@ -207,9 +223,14 @@ impl AsmFuncData {
             error!("file {} not have extension", &ext);
             self.lines_highlight = lines
                 .iter()
-                .map(|line| vec![(Color::White, line.clone())])
+                .map(|line| vec![(text, line.clone())])
                 .collect();
         }
+        self.lines_highlight
+            .iter_mut()
+            .flatten()
+            .for_each(|(c, _)| *c = adapt_color(*c, styles.colors));
+        self.update_mem_size();
     }
     pub fn get_line_id(&self, addr: &String) -> Option<u64> {
         match (addr.starts_with("0x"), addr.get(2..addr.len())) {
@@ -30,6 +30,24 @@ pub struct BreakPointSignalSrcData {
     pub line: u64,
 }
 
+impl BreakPointData {
+    /// 用 `f` 改写所有位置的源码路径
+    pub fn map_fullname(&mut self, f: impl Fn(&str) -> String) {
+        match self {
+            Self::Signal(p) => p.map_fullname(&f),
+            Self::Multiple(p) => p.bps.iter_mut().for_each(|bp| bp.map_fullname(&f)),
+        }
+    }
+}
+
+impl BreakPointSignalData {
+    fn map_fullname(&mut self, f: &impl Fn(&str) -> String) {
+        if let Some(src) = self.src.as_mut() {
+            src.fullname = f(&src.fullname);
+        }
+    }
+}
+
 impl From<&BreakPointSignalAction> for BreakPointSignalData {
     fn from(a: &BreakPointSignalAction) -> Self {
         a.src.as_ref().map_or(
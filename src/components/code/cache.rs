@@ -0,0 +1,240 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use tracing::{debug, info};
+
+/// 缓存中的一项需要估计自己占用的内存
+pub trait CacheEntry {
+    /// 在添加内容时累计，不要每次都遍历所有行
+    fn mem_size(&self) -> usize;
+    /// 还在读取或高亮中的项不能淘汰，否则后台任务的结果会丢失
+    fn is_busy(&self) -> bool;
+}
+
+/// 估计一行原始文本占用的堆内存
+pub fn line_mem_size(line: &String) -> usize {
+    line.capacity()
+        .saturating_add(std::mem::size_of::<String>())
+}
+
+/// 估计一行高亮文本占用的堆内存
+pub fn highlight_mem_size(line: &[(ratatui::style::Color, String)]) -> usize {
+    line.iter()
+        .map(|(_, s)| {
+            s.capacity()
+                .saturating_add(std::mem::size_of::<(ratatui::style::Color, String)>())
+        })
+        .fold(0, usize::saturating_add)
+}
+
+/// 估计原始文本和高亮文本占用的堆内存
+pub fn lines_mem_size<'a>(
+    lines: &[String],
+    highlight: impl IntoIterator<Item = &'a Vec<(ratatui::style::Color, String)>>,
+) -> usize {
+    let text = lines
+        .iter()
+        .map(line_mem_size)
+        .fold(0, usize::saturating_add);
+    let highlight = highlight
+        .into_iter()
+        .map(|line| highlight_mem_size(line))
+        .fold(0, usize::saturating_add);
+    text.saturating_add(highlight)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+/// 带内存预算的 LRU 缓存，保存已经读取的源码文件和反汇编函数
+///
+/// 超出预算时按最久未使用的顺序淘汰，被固定的项（当前显示的文件、有断点的文件）不淘汰。
+#[derive(Debug)]
+pub struct LruCache<V> {
+    name: &'static str,
+    budget: usize,
+    /// 每项带上最近使用的时间和加入或修改时记下的大小
+    entries: HashMap<Rc<String>, (V, u64, usize)>,
+    /// 所有项大小的和，项只能通过 `insert` 和 `update` 改变
+    size: usize,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl<V: CacheEntry> Default for LruCache<V> {
+    fn default() -> Self {
+        Self::new("cache", usize::MAX)
+    }
+}
+
+impl<V: CacheEntry> LruCache<V> {
+    pub fn new(name: &'static str, budget: usize) -> Self {
+        Self {
+            name,
+            budget,
+            entries: HashMap::new(),
+            size: 0,
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+    }
+    fn touch(&mut self) -> u64 {
+        self.tick = self.tick.saturating_add(1);
+        self.tick
+    }
+    /// 查看一项，不改变使用顺序，用于绘制等频繁的访问
+    pub fn get(&self, key: &String) -> Option<&V> {
+        self.entries.get(key).map(|(v, _, _)| v)
+    }
+    /// 修改一项，之后重新记下它的大小
+    pub fn update<R>(&mut self, key: &String, f: impl FnOnce(&mut V) -> R) -> Option<R> {
+        let (v, _, size) = self.entries.get_mut(key)?;
+        let ret = f(v);
+        let new_size = v.mem_size();
+        self.size = self.size.saturating_sub(*size).saturating_add(new_size);
+        *size = new_size;
+        Some(ret)
+    }
+    /// 要显示某一项时调用，记录命中情况并把它标记为最近使用
+    pub fn access(&mut self, key: &String) -> bool {
+        let tick = self.touch();
+        match self.entries.get_mut(key) {
+            Some((_, used, _)) => {
+                *used = tick;
+                self.stats.hits = self.stats.hits.saturating_add(1);
+                true
+            }
+            None => {
+                self.stats.misses = self.stats.misses.saturating_add(1);
+                false
+            }
+        }
+    }
+    pub fn insert(&mut self, key: Rc<String>, value: V) {
+        let tick = self.touch();
+        let size = value.mem_size();
+        self.size = self.size.saturating_add(size);
+        if let Some((_, _, old)) = self.entries.insert(key, (value, tick, size)) {
+            self.size = self.size.saturating_sub(old);
+        }
+    }
+    pub fn remove(&mut self, key: &String) -> Option<V> {
+        let (v, _, size) = self.entries.remove(key)?;
+        self.size = self.size.saturating_sub(size);
+        Some(v)
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&Rc<String>, &V)> {
+        self.entries.iter().map(|(k, (v, _, _))| (k, v))
+    }
+    pub fn keys(&self) -> impl Iterator<Item = &Rc<String>> {
+        self.entries.keys()
+    }
+
+    /// 淘汰最久未使用的项直到不超过预算，返回淘汰的 key
+    pub fn shrink(&mut self, pinned: impl Fn(&String, &V) -> bool) -> Vec<Rc<String>> {
+        if self.size <= self.budget {
+            return vec![];
+        }
+        let mut candidates = self
+            .entries
+            .iter()
+            .filter(|(k, (v, _, _))| !v.is_busy() && !pinned(k, v))
+            .map(|(k, (_, used, _))| (*used, k.clone()))
+            .collect::<Vec<_>>();
+        candidates.sort_unstable_by_key(|(used, _)| *used);
+        let mut evicted = vec![];
+        for (_, key) in candidates {
+            if self.size <= self.budget {
+                break;
+            }
+            self.remove(&key);
+            evicted.push(key);
+        }
+        self.stats.evictions = self.stats.evictions.saturating_add(evicted.len() as u64);
+        if self.size > self.budget {
+            debug!(
+                "{} still over budget {} > {} after evict, rest are pinned",
+                self.name, self.size, self.budget
+            );
+        }
+        info!(
+            "{} evict {:?}, {} entries {} bytes, budget {}, {:?}",
+            self.name,
+            &evicted,
+            self.entries.len(),
+            self.size,
+            self.budget,
+            self.stats
+        );
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Entry(usize, bool);
+
+    impl CacheEntry for Entry {
+        fn mem_size(&self) -> usize {
+            self.0
+        }
+        fn is_busy(&self) -> bool {
+            self.1
+        }
+    }
+
+    #[test]
+    fn f_shrink_lru_order() {
+        let mut cache = LruCache::new("test", 25);
+        cache.insert(Rc::new("a".to_string()), Entry(10, false));
+        cache.insert(Rc::new("b".to_string()), Entry(10, false));
+        cache.insert(Rc::new("c".to_string()), Entry(10, false));
+        assert!(cache.access(&"a".to_string()));
+        assert!(!cache.access(&"x".to_string()));
+        assert_eq!(cache.shrink(|_, _| false), vec![Rc::new("b".to_string())]);
+        assert!(cache.get(&"a".to_string()).is_some());
+        assert_eq!(cache.size, 20);
+        assert_eq!(
+            cache.stats,
+            CacheStats {
+                hits: 1,
+                misses: 1,
+                evictions: 1
+            }
+        );
+    }
+
+    #[test]
+    fn f_running_size() {
+        let mut cache = LruCache::new("test", 100);
+        cache.insert(Rc::new("a".to_string()), Entry(10, false));
+        cache.insert(Rc::new("b".to_string()), Entry(10, false));
+        cache.insert(Rc::new("a".to_string()), Entry(5, false));
+        assert_eq!(cache.size, 15);
+        assert_eq!(cache.update(&"b".to_string(), |e| e.0 = 30), Some(()));
+        assert_eq!(cache.update(&"x".to_string(), |e| e.0 = 30), None);
+        assert_eq!(cache.size, 35);
+        cache.remove(&"a".to_string());
+        assert_eq!(cache.size, 30);
+    }
+
+    #[test]
+    fn f_shrink_skip_pinned_and_busy() {
+        let mut cache = LruCache::new("test", 5);
+        cache.insert(Rc::new("a".to_string()), Entry(10, false));
+        cache.insert(Rc::new("b".to_string()), Entry(10, true));
+        cache.insert(Rc::new("c".to_string()), Entry(10, false));
+        let evicted = cache.shrink(|k, _| k == "a");
+        assert_eq!(evicted, vec![Rc::new("c".to_string())]);
+        assert_eq!(cache.entries.len(), 2);
+    }
+}
@@ -0,0 +1,271 @@
+use super::action;
+use crate::components::code;
+use crate::config::{adapt_color, ColorDepth, HighlightConfig, StyleKey, Styles};
+use crate::tool;
+use std::collections::VecDeque;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{HighlightState, Theme};
+use syntect::parsing::{ParseState, ScopeStack, SyntaxSet};
+use tokio::sync::mpsc::error::TryRecvError;
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use tracing::{debug, error};
+
+/// 每块的行数：按块高亮和发送，每块开头保存一个解析状态
+pub const CHUNK_LINES: usize = 500;
+
+pub type HighlightLine = Vec<(ratatui::style::Color, String)>;
+
+/// 一个源码文件的后台高亮任务
+///
+/// syntect 的解析状态依赖前面所有行，所以在每块开头保存解析状态作为检查点，
+/// 之后要高亮某一块时从它的检查点开始，不需要从文件头重新解析。
+/// 请求的块（可见窗口）优先处理，空闲时只解析不高亮，继续向后建立检查点。
+pub struct Highlighter {
+    file_name: String,
+    lines: Vec<String>,
+    syntax_set: &'static SyntaxSet,
+    theme: Theme,
+    colors: ColorDepth,
+    /// 高亮出错的行用的颜色
+    text: ratatui::style::Color,
+    /// 每块开头的作用域栈和解析状态，高亮状态由作用域栈重新算出
+    checkpoints: Vec<(ScopeStack, ParseState)>,
+    done: Vec<bool>,
+}
+
+impl Highlighter {
+    pub fn new(
+        file_name: String,
+        lines: Vec<String>,
+        config: &HighlightConfig,
+        styles: &Styles,
+    ) -> Option<Self> {
+        let syntax_set = tool::get_syntax_set("");
+        let Some(syntax) = tool::find_syntax(&file_name, &lines, config) else {
+            error!("file {} not have syntax", &file_name);
+            return None;
+        };
+        let theme = tool::get_theme(&config.theme);
+        let first = (ScopeStack::new(), ParseState::new(syntax));
+        let chunks = lines.len().div_ceil(CHUNK_LINES);
+        Some(Self {
+            file_name,
+            lines,
+            syntax_set,
+            theme,
+            colors: styles.colors,
+            text: styles
+                .get(StyleKey::Text)
+                .fg
+                .unwrap_or(ratatui::style::Color::Reset),
+            checkpoints: vec![first],
+            done: vec![false; chunks],
+        })
+    }
+
+    fn chunks(&self) -> usize {
+        self.done.len()
+    }
+
+    /// 高亮第 `chunk` 块并保存下一块的检查点，调用前需要已有这一块的检查点
+    fn highlight_chunk(&mut self, chunk: usize) -> Vec<HighlightLine> {
+        let (stack, parse_state) = self.checkpoints[chunk].clone();
+        let highlighter = syntect::highlighting::Highlighter::new(&self.theme);
+        let highlight_state = HighlightState::new(&highlighter, stack);
+        let mut h = HighlightLines::from_state(&self.theme, highlight_state, parse_state);
+        let start = chunk.saturating_mul(CHUNK_LINES);
+        let ans = self
+            .lines
+            .iter()
+            .skip(start)
+            .take(CHUNK_LINES)
+            .map(|s| match h.highlight_line(s, self.syntax_set) {
+                std::result::Result::Ok(ranges) => ranges
+                    .into_iter()
+                    .map(|(c, s)| {
+                        let fg = c.foreground;
+                        let color = ratatui::style::Color::Rgb(fg.r, fg.g, fg.b);
+                        (adapt_color(color, self.colors), s.to_string())
+                    })
+                    .collect(),
+                std::result::Result::Err(e) => {
+                    error!("file {} highlight fail {} {}", &self.file_name, s, e);
+                    vec![(self.text, s.clone())]
+                }
+            })
+            .collect();
+        if self.checkpoints.len() == chunk.saturating_add(1) {
+            let (highlight_state, parse_state) = h.state();
+            self.checkpoints.push((highlight_state.path, parse_state));
+        }
+        ans
+    }
+
+    /// 顺着文件向后多建立一个检查点，只解析不高亮
+    fn advance(&mut self) {
+        let chunk = self.checkpoints.len().saturating_sub(1);
+        let (mut stack, mut parse_state) = self.checkpoints[chunk].clone();
+        let start = chunk.saturating_mul(CHUNK_LINES);
+        for s in self.lines.iter().skip(start).take(CHUNK_LINES) {
+            let ops = match parse_state.parse_line(s, self.syntax_set) {
+                std::result::Result::Ok(ops) => ops,
+                std::result::Result::Err(e) => {
+                    error!("file {} parse fail {} {}", &self.file_name, s, e);
+                    continue;
+                }
+            };
+            for (_, op) in ops {
+                if let Err(e) = stack.apply(&op) {
+                    error!("file {} parse fail {} {}", &self.file_name, s, e);
+                }
+            }
+        }
+        self.checkpoints.push((stack, parse_state));
+    }
+
+    /// 处理高亮请求直到请求的发送端被丢弃（文件被移出缓存）
+    ///
+    /// 请求是按行号 `[start, end)` 给出的可见范围，新的请求排在还没处理完的旧请求前面。
+    /// 旧请求不能丢弃，文件数据中已经把它们记为请求过，不会再次请求。
+    pub async fn run(
+        mut self,
+        mut requests: UnboundedReceiver<(usize, usize)>,
+        send: UnboundedSender<action::Action>,
+    ) {
+        let mut queue = VecDeque::new();
+        loop {
+            let request = match queue.is_empty() && self.checkpoints.len() > self.chunks() {
+                // 没有请求且检查点已经建完，等待新的请求
+                true => requests.recv().await,
+                false => match requests.try_recv() {
+                    std::result::Result::Ok(r) => Some(r),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => break,
+                },
+            };
+            if let Some((start, end)) = request {
+                let first = start.saturating_sub(1) / CHUNK_LINES;
+                let last = end.saturating_sub(1) / CHUNK_LINES;
+                let pending = std::mem::take(&mut queue);
+                queue = (first..last.saturating_add(1).min(self.chunks()))
+                    .filter(|c| !self.done[*c])
+                    .collect();
+                for chunk in pending {
+                    if !queue.contains(&chunk) {
+                        queue.push_back(chunk);
+                    }
+                }
+            } else if requests.is_closed() && queue.is_empty() {
+                break;
+            }
+            match queue.front().copied() {
+                Some(chunk) if chunk < self.checkpoints.len() => {
+                    queue.pop_front();
+                    let lines = self.highlight_chunk(chunk);
+                    self.done[chunk] = true;
+                    tool::send_action(
+                        &send,
+                        action::Action::Code(code::Action::FilehighlightChunk((
+                            self.file_name.clone(),
+                            chunk.saturating_mul(CHUNK_LINES),
+                            lines,
+                        ))),
+                    );
+                }
+                _ if self.checkpoints.len() <= self.chunks() => self.advance(),
+                _ => {}
+            }
+            // 每处理一块就交出控制权
+            tokio::task::yield_now().await;
+        }
+        debug!("highlight file {} stop", &self.file_name);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_highlight_from_checkpoint() {
+        let lines = (0..CHUNK_LINES * 2 + 3)
+            .map(|i| match i {
+                0 => "/*\n".to_string(),
+                _ => format!("int a{} = {};\n", i, i),
+            })
+            .collect::<Vec<_>>();
+        let config = HighlightConfig::default();
+        let mut h = Highlighter::new(
+            "a.c".to_string(),
+            lines.clone(),
+            &config,
+            &Styles::default(),
+        )
+        .unwrap();
+        assert_eq!(h.chunks(), 3);
+        h.advance();
+        h.advance();
+        let last = h.highlight_chunk(2);
+        assert_eq!(last.len(), 3);
+        // 从文件头一直高亮的结果和从检查点开始的结果一样
+        let mut full =
+            Highlighter::new("a.c".to_string(), lines, &config, &Styles::default()).unwrap();
+        let whole = (0..3)
+            .flat_map(|c| full.highlight_chunk(c))
+            .collect::<Vec<_>>();
+        assert_eq!(last, whole[CHUNK_LINES * 2..]);
+        assert_eq!(
+            last[0].iter().map(|(_, s)| s.as_str()).collect::<String>(),
+            "int a1000 = 1000;\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn f_highlight_scroll_past_and_back() {
+        let lines = (0..CHUNK_LINES * 3)
+            .map(|i| format!("int a{} = {};\n", i, i))
+            .collect::<Vec<_>>();
+        let config = HighlightConfig::default();
+        let h = Highlighter::new("a.c".to_string(), lines, &config, &Styles::default()).unwrap();
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let (send, mut actions) = tokio::sync::mpsc::unbounded_channel();
+        // 滚到第三块后马上滚回开头，第三块也要高亮
+        tx.send((CHUNK_LINES * 2 + 1, CHUNK_LINES * 2 + 40))
+            .unwrap();
+        tx.send((1, 40)).unwrap();
+        let wait = async {
+            let mut starts = vec![];
+            while starts.len() < 2 {
+                match actions.recv().await {
+                    Some(action::Action::Code(code::Action::FilehighlightChunk((_, start, _)))) => {
+                        starts.push(start)
+                    }
+                    _ => break,
+                }
+            }
+            drop(tx);
+            starts
+        };
+        let (_, starts) = tokio::time::timeout(std::time::Duration::from_secs(30), async {
+            tokio::join!(h.run(rx, send), wait)
+        })
+        .await
+        .unwrap();
+        assert_eq!(starts, vec![0, CHUNK_LINES * 2]);
+    }
+
+    #[test]
+    fn f_highlight_downsample() {
+        let lines = vec!["int a = 1;\n".to_string()];
+        let config = HighlightConfig::default();
+        let styles = Styles {
+            colors: ColorDepth::Ansi256,
+            ..Styles::default()
+        };
+        let mut h = Highlighter::new("a.c".to_string(), lines, &config, &styles).unwrap();
+        let line = h.highlight_chunk(0).remove(0);
+        assert!(line
+            .iter()
+            .all(|(c, _)| matches!(c, ratatui::style::Color::Indexed(_))));
+    }
+}
@@ -0,0 +1,258 @@
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// 按文件名查找时最多访问的目录项数量，避免在很大的目录树上卡住
+const SEARCH_ENTRY_LIMIT: usize = 200_000;
+const SUGGESTION_LIMIT: usize = 50;
+
+/// 在代码窗口中定位找不到的源码文件
+#[derive(Debug, Default)]
+pub struct LocatePrompt {
+    missing: String,
+    input: String,
+    by_name: Vec<PathBuf>,
+    suggestions: Vec<PathBuf>,
+    selected: usize,
+    persist: bool,
+    /// 还在后台按文件名查找
+    searching: bool,
+}
+
+impl LocatePrompt {
+    /// 按文件名查找的候选由 `search` 在后台得到，之后用 `set_found` 填入
+    pub fn new(missing: String) -> Self {
+        Self {
+            missing,
+            searching: true,
+            ..Self::default()
+        }
+    }
+    pub fn set_found(&mut self, missing: &str, by_name: Vec<PathBuf>) {
+        if !self.searching || missing != self.missing {
+            return;
+        }
+        self.searching = false;
+        self.by_name = by_name;
+        if self.input.is_empty() {
+            self.refresh();
+        }
+    }
+    pub fn searching(&self) -> bool {
+        self.searching
+    }
+    pub fn missing(&self) -> &str {
+        &self.missing
+    }
+    pub fn input(&self) -> &str {
+        &self.input
+    }
+    pub fn suggestions(&self) -> &[PathBuf] {
+        &self.suggestions
+    }
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+    pub fn persist(&self) -> bool {
+        self.persist
+    }
+    pub fn toggle_persist(&mut self) {
+        self.persist = !self.persist;
+    }
+    pub fn push_char(&mut self, c: char) {
+        self.input.push(c);
+        self.refresh();
+    }
+    pub fn pop_char(&mut self) {
+        self.input.pop();
+        self.refresh();
+    }
+    pub fn up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+    pub fn down(&mut self) {
+        self.selected = self
+            .selected
+            .saturating_add(1)
+            .min(self.suggestions.len().saturating_sub(1));
+    }
+    /// 用选中的候选补全输入，目录后面补上 `/` 继续浏览
+    pub fn complete(&mut self) {
+        if let Some(path) = self.suggestions.get(self.selected) {
+            let mut input = path.to_string_lossy().into_owned();
+            if path.is_dir() && !input.ends_with('/') {
+                input.push('/');
+            }
+            self.input = input;
+            self.refresh();
+        }
+    }
+    /// 选定的文件：输入的是文件就用它，是目录就在其中找同名文件，否则用选中的候选
+    pub fn chosen(&self) -> Option<PathBuf> {
+        let input = Path::new(&self.input);
+        let name = Path::new(&self.missing).file_name()?;
+        if !self.input.is_empty() && input.is_file() {
+            return Some(input.to_path_buf());
+        }
+        if !self.input.is_empty() && input.is_dir() && input.join(name).is_file() {
+            return Some(input.join(name));
+        }
+        self.suggestions
+            .get(self.selected)
+            .filter(|p| p.is_file())
+            .cloned()
+    }
+    fn refresh(&mut self) {
+        self.suggestions = match self.input.is_empty() {
+            true => self.by_name.clone(),
+            false => list_completions(&self.input),
+        };
+        self.selected = 0;
+    }
+}
+
+/// 在搜索根目录下查找和找不到的文件同名的文件，会访问很多目录，不要在界面线程中调用
+pub fn search(missing: &str, roots: &[PathBuf]) -> Vec<PathBuf> {
+    match Path::new(missing).file_name() {
+        Some(name) => find_by_name(roots, name, SUGGESTION_LIMIT),
+        None => vec![],
+    }
+}
+
+/// 在各个搜索根目录下递归查找同名文件，跳过隐藏目录
+pub fn find_by_name(roots: &[PathBuf], name: &std::ffi::OsStr, limit: usize) -> Vec<PathBuf> {
+    let mut ans = vec![];
+    let mut visited = 0_usize;
+    let mut stack = roots.iter().rev().cloned().collect::<Vec<_>>();
+    while let Some(dir) = stack.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let mut dirs = vec![];
+        for entry in entries.flatten() {
+            visited = visited.saturating_add(1);
+            if visited > SEARCH_ENTRY_LIMIT || ans.len() >= limit {
+                return ans;
+            }
+            let file_name = entry.file_name();
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !file_name.to_string_lossy().starts_with('.') {
+                    dirs.push(entry.path());
+                }
+            } else if file_name == name {
+                ans.push(entry.path());
+            }
+        }
+        dirs.sort();
+        stack.extend(dirs.into_iter().rev());
+    }
+    ans
+}
+
+/// 列出输入路径所在目录中以输入的最后一段开头的条目
+pub fn list_completions(input: &str) -> Vec<PathBuf> {
+    let (dir, prefix) = match input.rfind('/') {
+        Some(pos) => (
+            &input[..pos.saturating_add(1)],
+            &input[pos.saturating_add(1)..],
+        ),
+        None => ("./", input),
+    };
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+    let mut ans = entries
+        .flatten()
+        .map(|entry| entry.file_name())
+        .filter(|name| {
+            let name = name.to_string_lossy();
+            name.starts_with(prefix) && (!name.starts_with('.') || prefix.starts_with('.'))
+        })
+        .map(|name: OsString| Path::new(dir).join(name))
+        .collect::<Vec<_>>();
+    ans.sort();
+    ans.truncate(SUGGESTION_LIMIT);
+    ans
+}
+
+/// 根据丢失路径和找到的文件推出目录映射：去掉相同的尾部路径后剩下的前缀
+///
+/// `/build/w17/src/a.c` 和 `/home/me/proj/src/a.c` 得到 `/build/w17` -> `/home/me/proj`
+pub fn directory_mapping(missing: &str, found: &Path) -> Option<(String, String)> {
+    let missing = Path::new(missing);
+    let common = missing
+        .components()
+        .rev()
+        .zip(found.components().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let strip = |p: &Path, n: usize| {
+        let mut p = p.to_path_buf();
+        (0..n).for_each(|_| {
+            p.pop();
+        });
+        p
+    };
+    let from = strip(missing, common);
+    let to = strip(found, common);
+    match from.as_os_str().is_empty() || from == to {
+        true => None,
+        false => Some((
+            from.to_string_lossy().into_owned(),
+            to.to_string_lossy().into_owned(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_tree() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("rgdb-locate-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("proj/src")).unwrap();
+        std::fs::create_dir_all(root.join(".git/src")).unwrap();
+        std::fs::write(root.join("proj/src/a.c"), "").unwrap();
+        std::fs::write(root.join(".git/src/a.c"), "").unwrap();
+        root
+    }
+
+    #[test]
+    fn f_directory_mapping() {
+        assert_eq!(
+            directory_mapping("/build/w17/src/a.c", Path::new("/home/me/proj/src/a.c")),
+            Some(("/build/w17".to_string(), "/home/me/proj".to_string()))
+        );
+        assert_eq!(directory_mapping("/src/a.c", Path::new("/src/a.c")), None);
+        assert_eq!(
+            directory_mapping("/a/x.c", Path::new("/b/y.c")),
+            Some(("/a/x.c".to_string(), "/b/y.c".to_string()))
+        );
+    }
+
+    #[test]
+    fn f_locate_prompt() {
+        let root = temp_tree();
+        let missing = "/build/w17/src/a.c";
+        let mut prompt = LocatePrompt::new(missing.to_string());
+        assert!(prompt.searching() && prompt.suggestions().is_empty());
+        prompt.set_found("/other/a.c", vec![root.clone()]);
+        assert!(prompt.searching());
+        prompt.set_found(missing, search(missing, std::slice::from_ref(&root)));
+        assert!(!prompt.searching());
+        assert_eq!(prompt.suggestions(), &[root.join("proj/src/a.c")]);
+        assert_eq!(prompt.chosen(), Some(root.join("proj/src/a.c")));
+
+        format!("{}/pr", root.display())
+            .chars()
+            .for_each(|c| prompt.push_char(c));
+        assert_eq!(prompt.suggestions(), &[root.join("proj")]);
+        prompt.complete();
+        assert!(prompt.input().ends_with("proj/"));
+        "src".chars().for_each(|c| prompt.push_char(c));
+        assert_eq!(prompt.chosen(), Some(root.join("proj/src/a.c")));
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
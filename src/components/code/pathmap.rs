@@ -0,0 +1,294 @@
+use crate::config::{self, PathRule, SourceConfig};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use tracing::{debug, error};
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Prefix { from: String, to: String },
+    Regex { regex: Regex, to: String },
+}
+
+impl Rule {
+    fn apply(&self, path: &str) -> Option<String> {
+        match self {
+            Rule::Prefix { from, to } => replace_prefix(path, from, to),
+            Rule::Regex { regex, to } => match regex.is_match(path) {
+                true => Some(regex.replace(path, to.as_str()).into_owned()),
+                false => None,
+            },
+        }
+    }
+}
+
+/// 把 gdb 给出的源码路径映射到本地存在的文件
+///
+/// 先按顺序尝试配置的规则和从 gdb 导入的规则，再在搜索目录中按路径后缀查找。
+#[derive(Debug, Clone, Default)]
+pub struct PathMapper {
+    rules: Vec<Rule>,
+    /// 交互定位得到的目录映射，最近的在前
+    found: Vec<(String, String)>,
+    gdb_rules: Vec<(String, String)>,
+    search_paths: Vec<PathBuf>,
+}
+
+impl PathMapper {
+    pub fn new(config: &SourceConfig) -> Self {
+        let rules = config
+            .substitute_path
+            .iter()
+            .filter_map(|rule| match rule {
+                PathRule::Prefix { from, to } => Some(Rule::Prefix {
+                    from: from.clone(),
+                    to: to.clone(),
+                }),
+                PathRule::Regex { regex, to } => match Regex::new(regex) {
+                    std::result::Result::Ok(regex) => Some(Rule::Regex {
+                        regex,
+                        to: to.clone(),
+                    }),
+                    Err(e) => {
+                        error!("bad substitute path regex {:?}: {}", regex, e);
+                        None
+                    }
+                },
+            })
+            .collect();
+        Self {
+            rules,
+            found: vec![],
+            gdb_rules: vec![],
+            search_paths: config.search_paths.clone(),
+        }
+    }
+
+    /// 换成新配置中的规则和搜索目录，保留交互定位和从 gdb 导入的规则
+    pub fn set_config(&mut self, config: &SourceConfig) {
+        *self = Self {
+            found: std::mem::take(&mut self.found),
+            gdb_rules: std::mem::take(&mut self.gdb_rules),
+            ..Self::new(config)
+        };
+    }
+
+    /// 交互中找到文件后得到的目录映射，优先级最高
+    pub fn add_rule(&mut self, from: String, to: String) {
+        let rule = (from, to);
+        self.found.retain(|r| *r != rule);
+        self.found.insert(0, rule);
+    }
+
+    /// 换成 `show substitute-path` 列出的全部规则，`unset` 掉的规则不再生效，返回规则是否变了
+    pub fn set_gdb_rules(&mut self, rules: Vec<(String, String)>) -> bool {
+        match self.gdb_rules == rules {
+            true => false,
+            false => {
+                self.gdb_rules = rules;
+                true
+            }
+        }
+    }
+
+    /// 所有匹配的规则改写出的候选路径，按优先级排列
+    fn candidates(&self, path: &str) -> Vec<String> {
+        let gdb_rules = self
+            .gdb_rules
+            .iter()
+            .filter_map(|(from, to)| replace_prefix(path, from, to));
+        self.found
+            .iter()
+            .filter_map(|(from, to)| replace_prefix(path, from, to))
+            .chain(self.rules.iter().filter_map(|rule| rule.apply(path)))
+            .chain(gdb_rules)
+            .collect()
+    }
+
+    /// 在搜索目录下依次尝试路径的后缀，`/a/b/c.c` 会尝试 `a/b/c.c`、`b/c.c`、`c.c`
+    fn search(&self, path: &str) -> Option<String> {
+        let components = Path::new(path)
+            .components()
+            .filter_map(|c| match c {
+                std::path::Component::Normal(s) => Some(s),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        self.search_paths.iter().find_map(|dir| {
+            (0..components.len()).find_map(|skip| {
+                let candidate = components[skip..]
+                    .iter()
+                    .fold(dir.clone(), |p, c| p.join(c));
+                match candidate.is_file() {
+                    true => Some(candidate.to_string_lossy().into_owned()),
+                    false => None,
+                }
+            })
+        })
+    }
+
+    pub fn resolve(&self, path: &str) -> String {
+        let candidates = self.candidates(path);
+        let ret = candidates
+            .iter()
+            .find(|p| Path::new(p).is_file())
+            .cloned()
+            .or_else(|| match Path::new(path).is_file() {
+                true => Some(path.to_string()),
+                false => None,
+            })
+            .or_else(|| self.search(path))
+            .or_else(|| candidates.into_iter().next())
+            .unwrap_or_else(|| path.to_string());
+        if ret != path {
+            debug!("map source path {} to {}", path, &ret);
+        }
+        ret
+    }
+}
+
+/// 保存交互定位得到的目录映射的文件
+pub fn saved_rules_path() -> PathBuf {
+    config::get_data_dir().join("source-map.json")
+}
+
+pub fn load_saved_rules(path: &Path) -> Vec<(String, String)> {
+    match std::fs::read_to_string(path) {
+        std::result::Result::Ok(s) => serde_json::from_str(&s).unwrap_or_else(|e| {
+            error!("read {:?} fail: {}", path, e);
+            vec![]
+        }),
+        Err(_) => vec![],
+    }
+}
+
+pub fn save_rule(path: &Path, from: String, to: String) -> color_eyre::Result<()> {
+    let mut rules = load_saved_rules(path);
+    rules.retain(|(f, _)| *f != from);
+    rules.push((from, to));
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&rules)?)?;
+    Ok(())
+}
+
+/// 只在完整的路径分量上匹配前缀，`/build/w` 不会匹配 `/build/worker`
+fn replace_prefix(path: &str, from: &str, to: &str) -> Option<String> {
+    let from = from.trim_end_matches('/');
+    let rest = path.strip_prefix(from)?;
+    match rest.is_empty() || rest.starts_with('/') || from.is_empty() {
+        true => Some(format!("{}{}", to.trim_end_matches('/'), rest)),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mapper(rules: Vec<PathRule>, search_paths: Vec<PathBuf>) -> PathMapper {
+        PathMapper::new(&SourceConfig {
+            substitute_path: rules,
+            search_paths,
+            ..SourceConfig::default()
+        })
+    }
+
+    fn temp_tree(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("rgdb-pathmap-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(root.join("proj/src")).unwrap();
+        std::fs::write(root.join("proj/src/a.c"), "int main(){}\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn f_replace_prefix() {
+        assert_eq!(
+            replace_prefix("/build/worker/a.c", "/build/worker", "/src/"),
+            Some("/src/a.c".to_string())
+        );
+        assert_eq!(
+            replace_prefix("/build/worker-17/a.c", "/build/worker", "/src"),
+            None
+        );
+    }
+
+    #[test]
+    fn f_resolve_rules() {
+        let root = temp_tree("rules");
+        let to = root.join("proj").to_string_lossy().into_owned();
+        let m = mapper(
+            vec![
+                PathRule::Prefix {
+                    from: "/nowhere".to_string(),
+                    to: "/also/nowhere".to_string(),
+                },
+                PathRule::Regex {
+                    regex: "^/build/worker-[0-9]+".to_string(),
+                    to: to.clone(),
+                },
+            ],
+            vec![],
+        );
+        assert_eq!(
+            m.resolve("/build/worker-17/src/a.c"),
+            format!("{}/src/a.c", to)
+        );
+        assert_eq!(m.resolve("/nowhere/b.c"), "/also/nowhere/b.c");
+        assert_eq!(m.resolve("/other/b.c"), "/other/b.c");
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn f_saved_rules() {
+        let root = temp_tree("saved");
+        let path = root.join("source-map.json");
+        assert!(load_saved_rules(&path).is_empty());
+        save_rule(&path, "/a".to_string(), "/b".to_string()).unwrap();
+        save_rule(&path, "/a".to_string(), "/c".to_string()).unwrap();
+        assert_eq!(
+            load_saved_rules(&path),
+            vec![("/a".to_string(), "/c".to_string())]
+        );
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn f_resolve_gdb_rule_and_search_path() {
+        let root = temp_tree("search");
+        let mut m = mapper(vec![], vec![root.join("proj")]);
+        assert_eq!(
+            m.resolve("/build/x/src/a.c"),
+            root.join("proj/src/a.c").to_string_lossy()
+        );
+        let rules = vec![("/ci".to_string(), root.to_string_lossy().into_owned())];
+        assert!(m.set_gdb_rules(rules.clone()));
+        assert!(!m.set_gdb_rules(rules));
+        assert_eq!(
+            m.resolve("/ci/proj/src/a.c"),
+            root.join("proj/src/a.c").to_string_lossy()
+        );
+        // `unset substitute-path` 之后列出的规则中没有它了
+        assert!(m.set_gdb_rules(vec![]));
+        assert!(m.candidates("/ci/proj/src/a.c").is_empty());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn f_set_config_keeps_found_rules() {
+        let mut m = mapper(vec![], vec![]);
+        m.add_rule("/a".to_string(), "/found".to_string());
+        m.add_rule("/a".to_string(), "/found".to_string());
+        m.set_gdb_rules(vec![("/g".to_string(), "/gdb".to_string())]);
+        m.set_config(&SourceConfig {
+            substitute_path: vec![PathRule::Prefix {
+                from: "/a".to_string(),
+                to: "/config".to_string(),
+            }],
+            ..SourceConfig::default()
+        });
+        assert_eq!(m.candidates("/a/x.c"), vec!["/found/x.c", "/config/x.c"]);
+        assert_eq!(m.candidates("/g/x.c"), vec!["/gdb/x.c"]);
+    }
+}
@@ -1,26 +1,46 @@
 use super::action;
 use super::breakpoint::*;
+use super::cache::{self, CacheEntry};
+use super::highlight::{self, HighlightLine};
 use crate::components::code;
 use crate::mi::frame::Frame;
 use crate::tool;
 use crate::tool::{FileData, HighlightFileData, TextFileData};
 use std::collections::HashMap;
-use std::ffi::OsStr;
-use std::path::Path;
 use std::rc::Rc;
-use syntect::easy::HighlightLines;
+use std::time::SystemTime;
 use tokio::fs::File;
 use tokio::io::AsyncBufReadExt;
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::error;
 
+/// 读取源码时文件的修改时间和大小，用来发现文件在磁盘上被改动
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FileStamp {
+    pub mtime: SystemTime,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn of(path: &str) -> Option<Self> {
+        let meta = std::fs::metadata(path).ok()?;
+        Some(Self {
+            mtime: meta.modified().ok()?,
+            size: meta.len(),
+        })
+    }
+}
+
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub struct SrcFileData {
     pub file_name: Rc<String>,
     lines: Vec<String>,
-    lines_highlight: Vec<Vec<(ratatui::style::Color, String)>>,
+    lines_highlight: Vec<Option<HighlightLine>>,
+    highlight_requested: Vec<bool>,
     read_done: bool,
-    highlight_done: bool,
+    stamp: Option<FileStamp>,
+    /// 读入的行和收到的高亮占用的内存，添加时累计
+    mem_size: usize,
 }
 
 impl TextFileData for SrcFileData {
@@ -91,25 +111,25 @@ impl TextFileData for SrcFileData {
 }
 
 impl HighlightFileData for SrcFileData {
-    fn get_highlight_done(&self) -> bool {
-        self.highlight_done
-    }
-    fn set_highlight_done(&mut self) {
-        self.highlight_done = true;
-    }
     fn get_highlight_lines_range(
         &self,
         start: usize,
         end: usize,
+        text: ratatui::style::Color,
     ) -> (Vec<Vec<(ratatui::style::Color, String)>>, usize, usize) {
-        let n = self.lines_highlight.len().saturating_add(1);
+        let n = self.lines.len().saturating_add(1);
         let end = n.min(end);
+        // 还没高亮到的行先按原文显示
         (
-            self.lines_highlight
+            self.lines
                 .iter()
+                .enumerate()
                 .skip(start.saturating_sub(1))
                 .take(end.saturating_sub(start))
-                .cloned()
+                .map(|(id, line)| match self.lines_highlight.get(id) {
+                    Some(Some(h)) => h.clone(),
+                    _ => vec![(text, line.clone())],
+                })
                 .collect::<Vec<Vec<_>>>(),
             start,
             end,
@@ -117,82 +137,77 @@ impl HighlightFileData for SrcFileData {
     }
 }
 
+impl CacheEntry for SrcFileData {
+    fn mem_size(&self) -> usize {
+        self.mem_size
+    }
+    fn is_busy(&self) -> bool {
+        !self.read_done
+    }
+}
+
 impl SrcFileData {
     pub fn new(file_name: String) -> Self {
         Self {
+            stamp: FileStamp::of(&file_name),
             file_name: Rc::new(file_name),
             lines: vec![],
             lines_highlight: vec![],
+            highlight_requested: vec![],
             read_done: false,
-            highlight_done: false,
+            mem_size: 0,
         }
     }
+    pub fn stamp(&self) -> Option<FileStamp> {
+        self.stamp
+    }
+    /// 已经读完且磁盘上的文件已经变化，可以丢弃后重新读取
+    pub fn need_reload(&self) -> bool {
+        self.read_done && self.stamp != FileStamp::of(&self.file_name)
+    }
     pub fn add_line(&mut self, line: String) {
+        self.mem_size = self.mem_size.saturating_add(cache::line_mem_size(&line));
         self.lines.push(line);
     }
-    pub fn add_highlight_line(&mut self, line: Vec<(ratatui::style::Color, String)>) {
-        self.lines_highlight.push(line);
+    /// 收到从第 `start` 行（从 0 开始）起的一块高亮结果
+    pub fn add_highlight_chunk(&mut self, start: usize, chunk: Vec<HighlightLine>) {
+        if self.lines_highlight.len() < self.lines.len() {
+            self.lines_highlight.resize(self.lines.len(), None);
+        }
+        self.lines_highlight
+            .iter_mut()
+            .skip(start)
+            .zip(chunk)
+            .for_each(|(old, new)| {
+                let old_size = old.as_deref().map_or(0, cache::highlight_mem_size);
+                self.mem_size = self
+                    .mem_size
+                    .saturating_sub(old_size)
+                    .saturating_add(cache::highlight_mem_size(&new));
+                *old = Some(new);
+            });
     }
-
-    pub async fn highlight_file(
-        file_name: String,
-        lines: Vec<String>,
-        send: UnboundedSender<action::Action>,
-    ) {
-        let theme = tool::get_theme();
-        let ext = Path::new(&file_name).extension().and_then(OsStr::to_str);
-        if let Some(ext) = ext {
-            let ps = tool::get_syntax_set(ext);
-            if let Some(syntax) = ps.find_syntax_by_extension(ext) {
-                let mut h = HighlightLines::new(syntax, &theme);
-                
-                // 每处理几行就主动交出控制权
-                const YIELD_INTERVAL: usize = 200;
-                for (i, s) in lines.iter().enumerate() {
-                    // 每处理YIELD_INTERVAL行就交出控制权
-                    if i % YIELD_INTERVAL == 0 && i > 0 {
-                        tokio::task::yield_now().await;
-                    }
-                    
-                    match h.highlight_line(s, &ps) {
-                        std::result::Result::Ok(ranges) => {
-                            let e = ranges
-                                .into_iter()
-                                .map(|(c, s)| {
-                                    (
-                                        ratatui::style::Color::Rgb(
-                                            c.foreground.r,
-                                            c.foreground.g,
-                                            c.foreground.b,
-                                        ),
-                                        s.to_string(),
-                                    )
-                                })
-                                .collect();
-                            tool::send_action(
-                                &send,
-                                action::Action::Code(code::Action::FilehighlightLine((
-                                    file_name.clone(),
-                                    e,
-                                ))),
-                            );
-                        }
-                        std::result::Result::Err(e) => {
-                            error!("file {} highlight fail {} {}", &file_name, &s, e);
-                        }
-                    }
-                }
-                tool::send_action(
-                    &send,
-                    action::Action::Code(code::Action::FilehighlightEnd(file_name)),
-                );
-            } else {
-                error!("file {} not have extension", &ext);
-            }
-        } else {
-            error!("file {} not have extension", &file_name);
+    /// 可见范围 `[start, end)` 中有还没请求过高亮的块时返回 true，并把它们记为已请求
+    pub fn take_highlight_request(&mut self, start: usize, end: usize) -> bool {
+        let chunks = self.lines.len().div_ceil(highlight::CHUNK_LINES);
+        if self.highlight_requested.len() < chunks {
+            self.highlight_requested.resize(chunks, false);
         }
+        let first = start.saturating_sub(1) / highlight::CHUNK_LINES;
+        let last = end.saturating_sub(1) / highlight::CHUNK_LINES;
+        let mut ans = false;
+        self.highlight_requested
+            .iter_mut()
+            .skip(first)
+            .take(last.saturating_sub(first).saturating_add(1))
+            .filter(|requested| !**requested)
+            .for_each(|requested| {
+                *requested = true;
+                ans = true;
+            });
+        ans
     }
+
     pub fn read_file_filter(line: String) -> String {
         line.replace("\u{0}", r##"\{NUL}"##)
             .replace("\u{1}", r##"\{SOH}"##)
@@ -387,14 +387,9 @@ mod tests {
 
     #[test]
     fn f_get_line_id() {
-        let asm = AsmFuncData {
-            func_name: std::rc::Rc::new("main".to_string()),
-            addrs: vec![(0x01a_u64, 2), (0x02b_u64, 3), (0x12b_u64, 5)],
-            lines: vec![],
-            lines_highlight: vec![],
-            read_done: true,
-            highlight_done: true,
-        };
+        let mut asm = AsmFuncData::new("main".to_string());
+        asm.addrs = vec![(0x01a_u64, 2), (0x02b_u64, 3), (0x12b_u64, 5)];
+        asm.read_done = true;
         let id = asm.get_line_id(&"0x000001a".to_string());
         println!("{:?}", &id);
         assert!(id == Some(2));
@@ -435,6 +430,20 @@ mod tests {
         let status = ans.get_status();
         println!("{:?}", &status);
         assert!(status == "** Dump of assembler code for function main: (0x1 - 0x3b) **");
+        // 标题和结尾用配置的样式，不是写死的颜色
+        use crate::config::{StyleKey, Styles};
+        use crate::tool::HighlightFileData;
+        let styles = Styles::default();
+        ans.add_highlight_lines(&disassemble, "Nord", &styles);
+        let (lines, _, _) = ans.get_highlight_lines_range(1, 6, ratatui::style::Color::Reset);
+        let title = styles.get(StyleKey::Title).fg.unwrap();
+        let text = styles
+            .get(StyleKey::Text)
+            .fg
+            .unwrap_or(ratatui::style::Color::Reset);
+        assert_eq!(lines[0][0].0, text);
+        assert_eq!(lines[0][1], (title, "main".to_string()));
+        assert_eq!(lines[4][0].0, text);
     }
     #[test]
     fn test_utf8_iter() {
@@ -481,5 +490,69 @@ mod tests {
         assert_eq!(hello.width(), 2);
 
     }
+    #[test]
+    fn test_src_file_need_reload() {
+        let path = std::env::temp_dir().join(format!("rgdb-reload-{}.c", std::process::id()));
+        std::fs::write(&path, "int main(){}\n").unwrap();
+        let mut file = SrcFileData::new(path.to_string_lossy().into_owned());
+        assert!(file.stamp().is_some());
+        file.set_read_done();
+        assert!(!file.need_reload());
+        std::fs::write(&path, "int main(){ return 0; }\n").unwrap();
+        assert!(file.need_reload());
+        std::fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_src_file_highlight_chunk() {
+        use crate::tool::HighlightFileData;
+        let mut file = SrcFileData::new("/nowhere/a.c".to_string());
+        (0..3).for_each(|i| file.add_line(format!("int a{};\n", i)));
+        assert!(file.take_highlight_request(1, 3));
+        assert!(!file.take_highlight_request(2, 3));
+        file.add_highlight_chunk(
+            1,
+            vec![vec![(ratatui::style::Color::Red, "int a1;\n".to_string())]],
+        );
+        let (lines, _, _) = file.get_highlight_lines_range(1, 4, ratatui::style::Color::Gray);
+        assert_eq!(
+            lines[0],
+            vec![(ratatui::style::Color::Gray, "int a0;\n".to_string())]
+        );
+        assert_eq!(
+            lines[1],
+            vec![(ratatui::style::Color::Red, "int a1;\n".to_string())]
+        );
+    }
+    #[test]
+    fn test_src_file_mem_size() {
+        use crate::components::code::cache::{self, CacheEntry};
+        let mut file = SrcFileData::new("/nowhere/a.c".to_string());
+        (0..3).for_each(|i| file.add_line(format!("int a{};\n", i)));
+        let red = |s: &str| vec![(ratatui::style::Color::Red, s.to_string())];
+        file.add_highlight_chunk(0, vec![red("int a0;\n"), red("int a1;\n")]);
+        // 同一行再次收到高亮时替换，不重复累计
+        file.add_highlight_chunk(1, vec![red("int a1;\n"), red("int a2;\n")]);
+        let highlight = [red("int a0;\n"), red("int a1;\n"), red("int a2;\n")];
+        let expected = cache::lines_mem_size(file.get_lines(), &highlight);
+        assert_eq!(file.mem_size(), expected);
+    }
+    #[test]
+    fn f_block_selection_ragged() {
+        let mut file = SrcFileData::new("/nowhere/a.c".to_string());
+        ["abcdef\n", "\n", "ab\n", "abcdef\n"]
+            .iter()
+            .for_each(|line| file.add_line(line.to_string()));
+        let code = Code::new();
+        let area = ratatui::layout::Rect::new(0, 0, 80, 10);
+        let selected = code
+            .get_selection_ranges_and_text(&file, (1, 2), (4, 4), area, 0, true)
+            .unwrap();
+        let texts = selected
+            .iter()
+            .map(|(s, _)| s.trim_end_matches('\n'))
+            .collect::<Vec<_>>();
+        assert_eq!(texts, vec!["cd", "", "", "cd"]);
+        assert_eq!(selected[1].1.line_number, 1);
+    }
 
 }
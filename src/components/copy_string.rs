@@ -1,22 +1,108 @@
 use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
 
 use arboard::Clipboard;
 use base64::{engine::general_purpose::STANDARD, Engine as _};
 use color_eyre::{eyre, Result};
 use ratatui::{layout::Rect, Frame};
-use tracing::debug;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error};
 
 use super::Component;
-use crate::action::Action;
+use crate::action;
+use crate::config::{self, ClipboardBackend, ClipboardConfig, Config};
+use crate::tool;
+
+/// screen 对 DCS 的长度有限制，需要分段发送
+const SCREEN_DCS_CHUNK: usize = 76;
+
+/// 外部复制命令最多等这么久，没有图形界面时 xclip、wl-copy 可能一直不退出
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Action {
+    /// 外部复制命令在后台跑完，`true` 表示复制成功，否则接着尝试后面的后端
+    CommandDone((CopyJob, bool)),
+}
+
+/// 一次复制，按配置顺序尝试各个后端
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CopyJob {
+    text: String,
+    /// 下一个要尝试的后端
+    next: usize,
+    /// 已经失败的后端的出错信息
+    errors: Vec<String>,
+}
+
+impl CopyJob {
+    fn new(text: String) -> Self {
+        Self {
+            text,
+            next: 0,
+            errors: vec![],
+        }
+    }
+    fn copied(&self, target: &str) -> action::Action {
+        action::Action::Notify(format!(
+            "Copied {} chars to {}",
+            self.text.chars().count(),
+            target
+        ))
+    }
+}
+
+/// OSC 52 需要穿透的终端复用器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Multiplexer {
+    None,
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    fn detect() -> Self {
+        if std::env::var("TMUX").is_ok() {
+            Multiplexer::Tmux
+        } else if std::env::var("STY").is_ok()
+            || std::env::var("TERM").is_ok_and(|term| term.starts_with("screen"))
+        {
+            Multiplexer::Screen
+        } else {
+            Multiplexer::None
+        }
+    }
+}
+
+/// 生成 OSC 52 序列，在 tmux/screen 中用 DCS 包裹
+fn osc52_sequence(s: &str, mux: Multiplexer) -> String {
+    let osc52 = format!("\x1B]52;c;{}\x07", STANDARD.encode(s));
+    match mux {
+        Multiplexer::None => osc52,
+        Multiplexer::Tmux => format!("\x1BPtmux;{}\x1B\\", osc52.replace('\x1B', "\x1B\x1B")),
+        Multiplexer::Screen => osc52
+            .as_bytes()
+            .chunks(SCREEN_DCS_CHUNK)
+            .map(|chunk| format!("\x1BP{}\x1B\\", String::from_utf8_lossy(chunk)))
+            .collect(),
+    }
+}
 
 pub struct CopyString {
     board: Option<Clipboard>,
+    config: ClipboardConfig,
+    command_tx: Option<UnboundedSender<action::Action>>,
 }
 
 impl CopyString {
     pub fn new() -> Self {
         CopyString {
             board: Clipboard::new().ok(),
+            config: ClipboardConfig::default(),
+            command_tx: None,
         }
     }
     fn send_to_clipboard(&mut self, s: &String) -> Result<()> {
@@ -35,17 +121,16 @@ impl CopyString {
         }
         Ok(())
     }
-    fn send_to_ssh_clipboard(&self, s: &String) -> Result<()> {
+    fn send_to_ssh_clipboard(&self, s: &str) -> Result<()> {
         // 检查是否在SSH会话中
-        if std::env::var("SSH_TTY").is_err() && std::env::var("SSH_CLIENT").is_err() {
+        if self.config.osc52_only_ssh
+            && std::env::var("SSH_TTY").is_err()
+            && std::env::var("SSH_CLIENT").is_err()
+        {
             return Err(eyre::eyre!("Not in SSH session"));
         }
-        // Base64编码内容
-        let encoded = STANDARD.encode(s);
-        debug!("Base64 encoded string: {:?} {:?}", &encoded, &s);
-
         // 构建OSC 52序列
-        let osc52 = format!("\x1B]52;c;{}\x07", encoded);
+        let osc52 = osc52_sequence(s, Multiplexer::detect());
         debug!("OSC 52 sequence: {:?}", &osc52);
 
         // 写入到标准输出
@@ -60,33 +145,219 @@ impl CopyString {
 
         Ok(())
     }
+    fn send_to_file(&self, s: &str) -> Result<String> {
+        let directory = config::get_data_dir();
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join("clipboard.txt");
+        std::fs::write(&path, s)?;
+        Ok(path.display().to_string())
+    }
+    /// 在后台线程跑外部命令，结果通过 `Action::CommandDone` 发回来
+    fn spawn_command(&self, mut job: CopyJob) -> Result<()> {
+        let Some(send) = self.command_tx.clone() else {
+            return Err(eyre::eyre!("No action sender"));
+        };
+        let commands = self.config.commands.clone();
+        tokio::task::spawn_blocking(move || {
+            let ok = match send_to_command(&commands, &job.text) {
+                Ok(_) => true,
+                Err(e) => {
+                    job.errors.push(e.to_string());
+                    false
+                }
+            };
+            let action = action::Action::CopyString(Action::CommandDone((job, ok)));
+            tool::send_action(&send, action);
+        });
+        Ok(())
+    }
+    /// 从 `job.next` 开始按配置顺序尝试各个后端，返回给用户的提示。
+    /// 轮到外部命令时放到后台跑，返回 `None`，跑完后再接着试
+    fn copy(&mut self, mut job: CopyJob) -> Option<action::Action> {
+        while let Some(backend) = self.config.backends.get(job.next).copied() {
+            job.next = job.next.saturating_add(1);
+            let result = match backend {
+                ClipboardBackend::Arboard => self
+                    .send_to_clipboard(&job.text)
+                    .map(|_| "clipboard".into()),
+                ClipboardBackend::Osc52 => self
+                    .send_to_ssh_clipboard(&job.text)
+                    .map(|_| "OSC 52".into()),
+                ClipboardBackend::Command => match self.spawn_command(job.clone()) {
+                    Ok(_) => return None,
+                    Err(e) => Err(e),
+                },
+                ClipboardBackend::File => self.send_to_file(&job.text),
+            };
+            match result {
+                Ok(target) => return Some(job.copied(&target)),
+                Err(e) => {
+                    debug!("copy with {:?} fail: {}", backend, e);
+                    job.errors.push(e.to_string());
+                }
+            }
+        }
+        error!("Failed to copy: {}", job.errors.join("; "));
+        Some(action::Action::Notify("Copy failed".to_string()))
+    }
+}
+
+/// 依次尝试配置的外部命令，例如 xclip、wl-copy、pbcopy
+fn send_to_command(commands: &[Vec<String>], s: &str) -> Result<()> {
+    let mut errors = vec![];
+    for cmd in commands.iter() {
+        let Some((program, args)) = cmd.split_first() else {
+            continue;
+        };
+        match run_copy_command(program, args, s, COMMAND_TIMEOUT) {
+            Ok(_) => {
+                debug!("copied with {:?}", cmd);
+                return Ok(());
+            }
+            Err(e) => errors.push(format!("{}: {}", program, e)),
+        }
+    }
+    Err(eyre::eyre!("No copy command works: {}", errors.join(", ")))
+}
+
+/// 超时后杀掉命令。命令不读输入时写入会阻塞，所以在单独的线程里写
+fn run_copy_command(program: &str, args: &[String], s: &str, timeout: Duration) -> Result<()> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let writer = child.stdin.take().map(|mut stdin| {
+        let s = s.to_string();
+        std::thread::spawn(move || stdin.write_all(s.as_bytes()))
+    });
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(eyre::eyre!("no exit after {:?}", timeout));
+        }
+        std::thread::sleep(Duration::from_millis(10));
+    };
+    if let Some(writer) = writer {
+        writer
+            .join()
+            .map_err(|_| eyre::eyre!("write thread panic"))??;
+    }
+    match status.success() {
+        true => Ok(()),
+        false => Err(eyre::eyre!("exit with {}", status)),
+    }
 }
 
 impl Component for CopyString {
-    fn update(&mut self, action: Action) -> Result<Option<Action>> {
-        match action {
-            Action::CopyStr(s) => {
-                // 尝试写入本地剪贴板
-                let local_result = self.send_to_clipboard(&s);
-
-                // 尝试写入SSH剪贴板
-                let ssh_result = self.send_to_ssh_clipboard(&s);
-
-                // 如果两者都失败，返回错误
-                if local_result.is_err() && ssh_result.is_err() {
-                    return Err(eyre::eyre!(
-                        "Failed to copy: local error: {:?}, ssh error: {:?}",
-                        local_result.err().unwrap(),
-                        ssh_result.err().unwrap()
-                    ));
-                }
+    fn register_action_handler(&mut self, tx: UnboundedSender<action::Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config.clipboard;
+        Ok(())
+    }
+
+    fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
+        let ret = match action {
+            action::Action::CopyStr(s) => self.copy(CopyJob::new(s)),
+            action::Action::CopyString(Action::CommandDone((job, true))) => {
+                Some(job.copied("command"))
             }
-            _ => {}
+            action::Action::CopyString(Action::CommandDone((job, false))) => self.copy(job),
+            _ => None,
         };
-        Ok(None)
+        Ok(ret)
     }
 
     fn draw(&mut self, _frame: &mut Frame, _area: Rect) -> Result<()> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_osc52_sequence() {
+        assert_eq!(
+            osc52_sequence("hi", Multiplexer::None),
+            "\x1B]52;c;aGk=\x07"
+        );
+        assert_eq!(
+            osc52_sequence("hi", Multiplexer::Tmux),
+            "\x1BPtmux;\x1B\x1B]52;c;aGk=\x07\x1B\\"
+        );
+    }
+
+    #[test]
+    fn f_osc52_sequence_screen_chunks() {
+        let s = "x".repeat(100);
+        let seq = osc52_sequence(&s, Multiplexer::Screen);
+        let chunks = seq
+            .split("\x1B\\")
+            .filter(|c| !c.is_empty())
+            .collect::<Vec<_>>();
+        assert_eq!(chunks.len(), 2);
+        assert!(chunks.iter().all(|c| c.starts_with("\x1BP")));
+        let joined = chunks
+            .iter()
+            .map(|c| c.trim_start_matches("\x1BP"))
+            .collect::<String>();
+        assert_eq!(joined, osc52_sequence(&s, Multiplexer::None));
+    }
+
+    #[tokio::test]
+    async fn f_copy_fallback_to_command() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut c = CopyString {
+            board: None,
+            config: ClipboardConfig {
+                backends: vec![ClipboardBackend::Arboard, ClipboardBackend::Command],
+                osc52_only_ssh: true,
+                commands: vec![
+                    vec!["no-such-copy-cmd".to_string()],
+                    vec!["cat".to_string()],
+                ],
+            },
+            command_tx: None,
+        };
+        c.register_action_handler(tx).unwrap();
+        // 外部命令在后台跑，跑完后发回结果
+        let copy = action::Action::CopyStr("text".to_string());
+        assert_eq!(c.update(copy.clone()).unwrap(), None);
+        let done = rx.recv().await.unwrap();
+        assert_eq!(
+            c.update(done).unwrap(),
+            Some(action::Action::Notify(
+                "Copied 4 chars to command".to_string()
+            ))
+        );
+        c.config.commands.remove(1);
+        assert_eq!(c.update(copy).unwrap(), None);
+        let done = rx.recv().await.unwrap();
+        assert_eq!(
+            c.update(done).unwrap(),
+            Some(action::Action::Notify("Copy failed".to_string()))
+        );
+    }
+
+    #[test]
+    fn f_copy_command_timeout() {
+        let timeout = Duration::from_millis(100);
+        assert!(run_copy_command("cat", &[], "text", timeout).is_ok());
+        let sleep = ["5".to_string()];
+        let start = Instant::now();
+        assert!(run_copy_command("sleep", &sleep, "text", timeout).is_err());
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+}
@@ -1,21 +1,29 @@
 use super::{gdbtty, Component};
 use crate::mi::breakpointmi::{show_bkpt, show_breakpoint_deleted, BreakPointAction};
 use crate::mi::disassemble::DisassembleFunction;
+use crate::mi::evaluate::{self, EvalResult};
 use crate::mi::frame::Frame as FrameMi;
+use crate::mi::framer::LineFramer;
+use crate::mi::stream::{self, StreamRecord};
 use crate::mi::token::*;
-use crate::mi::{disassemble, miout};
+use crate::mi::{disassemble, miout, threadgroup};
 use crate::tool;
 use crate::{action, config::Config};
 use color_eyre::{eyre::eyre, eyre::Ok, Result};
 use portable_pty::{native_pty_system, PtySize};
 use ratatui::prelude::*;
 use serde::{Deserialize, Serialize};
-use smol::io::AsyncReadExt;
 use strum::Display;
-use tokio::{sync::mpsc::UnboundedSender, task::JoinHandle};
+use tokio::sync::mpsc::UnboundedSender;
 use tracing::error;
 use tracing::{debug, info};
 
+const MI_READ_BUF_SIZE: usize = 64 * 1024;
+/// 控制台中还没换行的输出最多保留这么多字节
+const CONSOLE_LINE_LIMIT: usize = 4096;
+const SHOW_SUBSTITUTE_PATH: &str = "-interpreter-exec console \"show substitute-path\"";
+const LIST_THREAD_GROUPS: &str = "-list-thread-groups";
+
 #[derive(Default)]
 pub struct Gdbmi {
     command_tx: Option<UnboundedSender<action::Action>>,
@@ -23,7 +31,11 @@ pub struct Gdbmi {
 
     gdb_mi_writer: Option<Box<dyn std::io::Write + Send>>,
     gdb_mi_reader: Option<Box<dyn std::io::Read + Send>>,
-    gdb_mi_read_task: Option<JoinHandle<()>>,
+    gdb_mi_read_task: Option<std::thread::JoinHandle<()>>,
+    /// 第一次停下时已经读取了路径规则和可执行文件
+    queried: bool,
+    /// gdb 控制台输出中还没换行的部分
+    console_line: Vec<u8>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
@@ -36,6 +48,15 @@ pub enum Action {
     ReadAsmFunc(DisassembleFunction),
     Breakpoint(BreakPointAction),
     BreakpointDeleted(u64),
+    /// `show substitute-path` 列出的全部规则
+    SubstitutePaths(Vec<(String, String)>),
+    Executable(String),
+    /// 在 mi 通道上执行一条命令
+    Command(String),
+    /// 执行一条命令，结果带着这个编号
+    TokenCommand((u64, String)),
+    /// 带编号的命令的结果
+    Evaluated((u64, EvalResult)),
 }
 
 impl Gdbmi {
@@ -43,75 +64,42 @@ impl Gdbmi {
         Self::default()
     }
 
-    async fn gdb_mi_reader(
-        reader: Box<dyn std::io::Read + Send>,
+    fn gdb_mi_reader(
+        mut reader: Box<dyn std::io::Read + Send>,
         send: UnboundedSender<action::Action>,
     ) {
-        // lazy_static! {
-        //     static ref LINE: std::sync::Mutex<String> = std::sync::Mutex::new(String::new());
-        // };
-        let mut buf = [0_u8; 32];
-        let mut line = String::new();
-        let mut reader = smol::io::BufReader::new(smol::Unblock::new(reader));
+        let parser = miout::TokOutputOnelineParser::new();
+        let mut framer = LineFramer::new();
+        let mut buf = vec![0_u8; MI_READ_BUF_SIZE];
+        let mut gdb_rules = None;
 
         loop {
-            // debug!("read start!");
-            // let n = reader.read(&mut buf).await.map_or(0, |n| n);
-            let n = reader.read(&mut buf).await.map_or(0, |n| n);
-
-            let mut out_line = vec![];
-            match n {
-                0 => {}
-                _ => {
-                    buf[0..n]
-                        .iter()
-                        .map(|c| char::from(*c))
-                        .filter(|c| *c != '\r')
-                        .for_each(|f| match f {
-                            '\n' => {
-                                line.push(f);
-                                out_line.push(line.clone());
-                                line.clear()
-                            }
-                            _ => line.push(f),
-                        });
+            let n = match reader.read(&mut buf) {
+                std::result::Result::Ok(0) => {
+                    info!(
+                        "gdb mi reader reach eof, drop {} pending bytes",
+                        framer.pending_len()
+                    );
+                    break;
                 }
-            };
-            let mut actions = vec![];
-            out_line.into_iter().for_each(|line| {
-                match miout::TokOutputOnelineParser::new().parse(line.as_str()) {
-                    std::result::Result::Ok(OutputOneline::OutOfBandRecord(a)) => {
-                        if let Some(frame) = get_frame(&a) {
-                            if let Some((file, line)) = show_file(&frame) {
-                                actions.push(Action::ShowFile((file, line, frame)));
-                            } else if let Some((func, addr)) = show_asm(&frame) {
-                                actions.push(Action::ShowAsm((func, addr, frame)));
-                            }
-                        }
-                        if let Some(bkpt) = show_bkpt(&a) {
-                            actions.push(Action::Breakpoint(bkpt));
-                        }
-                        if let Some(id) = show_breakpoint_deleted(&a) {
-                            actions.push(Action::BreakpointDeleted(id));
-                        }
-                    }
-                    std::result::Result::Ok(OutputOneline::ResultRecord(a)) => {
-                        if let Some(asmfunc) = disassemble::get_disassemble_function(a) {
-                            actions.push(Action::ReadAsmFunc(asmfunc));
-                        }
-                    }
-                    std::result::Result::Err(e) => {
-                        error!("unknow read gdb mi line {} {:?} ", &e, &line);
-                    }
+                std::result::Result::Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    info!("gdb mi reader stop: {:?}", e);
+                    break;
                 }
-                // actions.push(Action::Out(line));
-                info!("gdb mi read {:?}", &line);
-            });
-            actions.into_iter().for_each(|action| {
+            };
+            let actions = framer
+                .push(&buf[0..n])
+                .into_iter()
+                .flat_map(|line| mi_line_to_actions(&parser, &line, &mut gdb_rules))
+                .collect::<Vec<_>>();
+            for action in actions {
                 if send.send(action::Action::Gdbmi(action)).is_err() {
-                    error!("gdb tty read but send fail! {:?}", &buf[0..buf.len()]);
+                    error!("gdb mi read but send fail!");
+                    return;
                 };
-            });
+            }
         }
     }
 
@@ -151,10 +139,11 @@ impl Gdbmi {
             .map_err(|e| eyre!(format!("{:?}", e)))?;
 
         if let Some(send) = self.command_tx.clone() {
-            let reader_task = Self::gdb_mi_reader(gdb_mi_reader, send.clone());
-            self.gdb_mi_read_task = Some(tokio::spawn(async {
-                reader_task.await;
-            }));
+            // framing and parsing run on their own thread, not on the ui task
+            let reader_thread = std::thread::Builder::new()
+                .name("gdb-mi-reader".to_string())
+                .spawn(move || Self::gdb_mi_reader(gdb_mi_reader, send))?;
+            self.gdb_mi_read_task = Some(reader_thread);
             debug!("gdb mi start")
         } else {
             let msg = "gdb mi reader thread not start";
@@ -174,6 +163,49 @@ impl Gdbmi {
         // loop {}
         ret
     }
+
+    fn query(&mut self, cmds: &[&str]) -> Result<()> {
+        if let Some(write) = self.gdb_mi_writer.as_mut() {
+            for cmd in cmds {
+                writeln!(write, "{}", cmd)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 控制台中每输出完一行，检查它是否改变了路径规则或可执行文件
+    fn console_out(&mut self, out: &[u8]) -> Result<()> {
+        self.console_line.extend_from_slice(out);
+        let Some(end) = self.console_line.iter().rposition(|c| *c == b'\n') else {
+            let over = self.console_line.len().saturating_sub(CONSOLE_LINE_LIMIT);
+            self.console_line.drain(..over);
+            return Ok(());
+        };
+        let lines = self.console_line.drain(..=end).collect::<Vec<_>>();
+        let text = String::from_utf8_lossy(&strip_ansi_escapes::strip(lines)).into_owned();
+        let import = self.config.source.import_gdb_substitute_path;
+        let mut cmds = text
+            .lines()
+            .flat_map(|line| console_line_queries(line, import))
+            .collect::<Vec<_>>();
+        cmds.dedup();
+        self.query(&cmds)
+    }
+}
+
+/// 控制台中回显的命令或输出说明路径规则或可执行文件可能变了时，返回要重新读取它们的 mi 命令
+///
+/// 命令的回显在执行前输出，但 gdb 执行完这条命令才会读 mi 通道，所以读到的是执行后的结果。
+fn console_line_queries(line: &str, import_rules: bool) -> Vec<&'static str> {
+    let mut cmds = vec![];
+    if import_rules && line.contains("substitute-path") {
+        cmds.push(SHOW_SUBSTITUTE_PATH);
+    }
+    // `file` 或重新运行时读取新的可执行文件，用来和源码的修改时间比较
+    if line.trim_start().starts_with("Reading symbols from") {
+        cmds.push(LIST_THREAD_GROUPS);
+    }
+    cmds
 }
 
 impl Component for Gdbmi {
@@ -202,6 +234,31 @@ impl Component for Gdbmi {
                 }
                 Ok(None)
             }
+            action::Action::Gdbmi(Action::Command(cmd)) => {
+                if let Some(write) = self.gdb_mi_writer.as_mut() {
+                    writeln!(write, "{}", cmd)?;
+                }
+                Ok(None)
+            }
+            action::Action::Gdbmi(Action::TokenCommand((token, cmd))) => {
+                if let Some(write) = self.gdb_mi_writer.as_mut() {
+                    writeln!(write, "{}{}", token, cmd)?;
+                }
+                Ok(None)
+            }
+            action::Action::Gdbmi(Action::ShowFile(_)) if !self.queried => {
+                // 之后只在控制台中修改了规则或读取了新的可执行文件时重新读取
+                self.queried = true;
+                match self.config.source.import_gdb_substitute_path {
+                    true => self.query(&[SHOW_SUBSTITUTE_PATH, LIST_THREAD_GROUPS])?,
+                    false => self.query(&[LIST_THREAD_GROUPS])?,
+                }
+                Ok(None)
+            }
+            action::Action::Gdbtty(gdbtty::Action::Out(out)) => {
+                self.console_out(&out)?;
+                Ok(None)
+            }
             _ => Ok(None),
         }
     }
@@ -210,6 +267,70 @@ impl Component for Gdbmi {
     }
 }
 
+/// Turns one complete mi line into the actions rgdb reacts to.
+///
+/// `gdb_rules` collects the rules of a `show substitute-path` reply until its
+/// result record, they are sent together as the full set.
+fn mi_line_to_actions(
+    parser: &miout::TokOutputOnelineParser,
+    line: &str,
+    gdb_rules: &mut Option<Vec<(String, String)>>,
+) -> Vec<Action> {
+    let mut actions = vec![];
+    if let Some(record) = stream::parse_stream_record(line) {
+        if let StreamRecord::Console(text) = record {
+            if stream::is_substitute_path_header(&text) {
+                *gdb_rules = Some(vec![]);
+            } else if let Some(rule) = stream::substitute_path_rule(&text) {
+                if let Some(rules) = gdb_rules.as_mut() {
+                    rules.push(rule);
+                }
+            }
+        }
+        return actions;
+    }
+    match parser.parse(line) {
+        std::result::Result::Ok(OutputOneline::OutOfBandRecord(a)) => {
+            if let Some(frame) = get_frame(&a) {
+                if let Some((file, line)) = show_file(&frame) {
+                    actions.push(Action::ShowFile((file, line, frame)));
+                } else if let Some((func, addr)) = show_asm(&frame) {
+                    actions.push(Action::ShowAsm((func, addr, frame)));
+                }
+            }
+            if let Some(bkpt) = show_bkpt(&a) {
+                actions.push(Action::Breakpoint(bkpt));
+            }
+            if let Some(id) = show_breakpoint_deleted(&a) {
+                actions.push(Action::BreakpointDeleted(id));
+            }
+        }
+        std::result::Result::Ok(OutputOneline::ResultRecord(a)) => {
+            if let Some(rules) = gdb_rules.take() {
+                actions.push(Action::SubstitutePaths(rules));
+            }
+            // 只有求值命令带编号
+            if let Some(token) = a.token {
+                if let Some(result) = evaluate::get_eval_result(&a) {
+                    actions.push(Action::Evaluated((token, result)));
+                }
+                return actions;
+            }
+            if let Some(exe) = threadgroup::get_executable(&a) {
+                actions.push(Action::Executable(exe));
+            }
+            if let Some(asmfunc) = disassemble::get_disassemble_function(a) {
+                actions.push(Action::ReadAsmFunc(asmfunc));
+            }
+        }
+        std::result::Result::Err(e) => {
+            error!("unknow read gdb mi line {} {:?} ", &e, &line);
+        }
+    }
+    debug!("gdb mi read {} bytes", line.len());
+    actions
+}
+
 fn show_asm(frame: &FrameMi) -> Option<(String, String)> {
     match (&frame.fullname, &frame.line, &frame.func) {
         (_, _, Some(func)) => Some((func.clone(), frame.addr.clone())),
@@ -253,8 +374,15 @@ fn get_frame(a: &OutOfBandRecordType) -> Option<FrameMi> {
 #[cfg(test)]
 mod tests {
     use crate::components::gdbmi::get_frame;
+    use crate::components::gdbmi::mi_line_to_actions;
     use crate::components::gdbmi::show_asm;
     use crate::components::gdbmi::show_file;
+    use crate::components::gdbmi::Action;
+    use crate::components::gdbmi::{
+        console_line_queries, LIST_THREAD_GROUPS, SHOW_SUBSTITUTE_PATH,
+    };
+    use crate::mi::evaluate::EvalResult;
+    use crate::mi::framer::LineFramer;
     use crate::mi::miout;
     #[test]
     fn f_show_file() {
@@ -284,4 +412,116 @@ mod tests {
         println!("{:?} {:?}", &a, &b);
         assert!(b == Some(("main".to_string(), "0x0000555555581c20".to_string())));
     }
+
+    fn big_disassemble_record(n: usize) -> String {
+        let insts = (0..n)
+            .map(|i| {
+                format!(
+                    "{{address=\"0x{:016x}\",func-name=\"big_func\",offset=\"{}\",inst=\"mov    %r{},%rcx\"}}",
+                    0x5555_5558_0000_u64 + i as u64 * 4,
+                    i * 4,
+                    i % 16
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        format!("^done,asm_insns=[{}]\r\n", insts)
+    }
+
+    fn feed_mi_stream(record: &str, chunk: usize) -> Vec<Action> {
+        let parser = miout::TokOutputOnelineParser::new();
+        let mut framer = LineFramer::new();
+        record
+            .as_bytes()
+            .chunks(chunk)
+            .flat_map(|bytes| framer.push(bytes))
+            .flat_map(|line| mi_line_to_actions(&parser, &line, &mut None))
+            .collect()
+    }
+
+    #[test]
+    fn f_mi_stream_big_record() {
+        let record = big_disassemble_record(2000);
+        let actions = feed_mi_stream(&record, 32);
+        assert_eq!(actions.len(), 1);
+        match &actions[0] {
+            Action::ReadAsmFunc(func) => {
+                assert_eq!(func.func, "big_func");
+                assert_eq!(func.insts.len(), 2000);
+            }
+            a => panic!("unexpected action {:?}", a),
+        }
+    }
+
+    #[test]
+    fn f_mi_substitute_paths() {
+        let parser = miout::TokOutputOnelineParser::new();
+        let mut rules = None;
+        let lines = [
+            "~\"List of all source path substitution rules:\\n\"\n",
+            "~\"  `/build' -> `/src'.\\n\"\n",
+            "*stopped,reason=\"end-stepping-range\"\n",
+            "^done\n",
+        ];
+        let actions = lines
+            .iter()
+            .flat_map(|line| mi_line_to_actions(&parser, line, &mut rules))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actions,
+            vec![Action::SubstitutePaths(vec![(
+                "/build".to_string(),
+                "/src".to_string()
+            )])]
+        );
+        // 没有规则时也要送出空的规则集合
+        let actions = [lines[0], lines[3]]
+            .iter()
+            .flat_map(|line| mi_line_to_actions(&parser, line, &mut rules))
+            .collect::<Vec<_>>();
+        assert_eq!(actions, vec![Action::SubstitutePaths(vec![])]);
+        assert!(mi_line_to_actions(&parser, lines[3], &mut rules).is_empty());
+    }
+
+    #[test]
+    fn f_console_line_queries() {
+        assert_eq!(
+            console_line_queries("(gdb) unset substitute-path /build", true),
+            vec![SHOW_SUBSTITUTE_PATH]
+        );
+        assert!(console_line_queries("(gdb) set substitute-path /a /b", false).is_empty());
+        assert_eq!(
+            console_line_queries("Reading symbols from ./a.out...", true),
+            vec![LIST_THREAD_GROUPS]
+        );
+        assert!(console_line_queries("Breakpoint 1, main () at a.c:3", true).is_empty());
+    }
+
+    #[test]
+    fn f_mi_token_evaluated() {
+        let actions = feed_mi_stream("7^done,value=\"42\"\r\n", 4);
+        assert_eq!(
+            actions,
+            vec![Action::Evaluated((7, EvalResult::Value("42".to_string())))]
+        );
+        // 没有编号的结果照旧处理
+        assert!(feed_mi_stream("^done,value=\"42\"\r\n", 4).is_empty());
+    }
+
+    /// Run with `cargo test --release bench_mi_stream -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_mi_stream_big_record() {
+        let record = big_disassemble_record(200_000);
+        let start = std::time::Instant::now();
+        let actions = feed_mi_stream(&record, super::MI_READ_BUF_SIZE);
+        let elapsed = start.elapsed();
+        assert_eq!(actions.len(), 1);
+        println!(
+            "parse {} bytes mi record in {:?} ({:.2} MiB/s)",
+            record.len(),
+            elapsed,
+            record.len() as f64 / 1024.0 / 1024.0 / elapsed.as_secs_f64()
+        );
+    }
 }
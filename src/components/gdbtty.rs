@@ -1,5 +1,6 @@
 use super::Component;
 use crate::tool;
+use crate::tool::layout::SharedLayout;
 use crate::{action, config::Config};
 use color_eyre::{eyre::eyre, eyre::Ok, Result};
 use portable_pty::{native_pty_system, Child, CommandBuilder, PtyPair, PtySize};
@@ -25,7 +26,7 @@ pub struct Gdbtty {
     gdb_path: String,
     gdb_args: Vec<String>,
     handle_key: bool,
-    is_horizontal: bool,
+    layout: SharedLayout,
 }
 
 impl Gdbtty {
@@ -41,6 +42,8 @@ pub enum Action {
     SetGdb(String),
     SetGdbArgs(Vec<String>),
     GdbExit,
+    /// 清掉 gdb 提示符后已经输入的内容，执行一条命令
+    Command(String),
 }
 
 impl Gdbtty {
@@ -237,12 +240,20 @@ impl Component for Gdbtty {
         Ok(())
     }
 
-    fn draw(&mut self, _frame: &mut Frame, area: Rect) -> Result<()> {
-        let tool::Layouts { gdb: area, .. } = (area, self.is_horizontal).into();
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
+
+    fn draw(&mut self, _frame: &mut Frame, _area: Rect) -> Result<()> {
+        let tool::Layouts { gdb: area, .. } = self.layout.borrow().layouts();
         let area = area.inner(Margin {
             horizontal: 1,
             vertical: 1,
         });
+        if area.is_empty() {
+            return Ok(());
+        }
         if let Some(pty_pair) = &self.pty_pair {
             pty_pair
                 .master
@@ -295,6 +306,13 @@ impl Component for Gdbtty {
                 false => None,
             },
             action::Action::Gdbtty(Action::GdbExit) => Some(action::Action::Quit),
+            action::Action::Gdbtty(Action::Command(cmd)) => {
+                if let Some(write) = self.gdb_writer.as_mut() {
+                    // Ctrl-u 清空 readline 的当前行
+                    write.write_all(format!("\x15{}\n", cmd).as_bytes())?;
+                }
+                None
+            }
             action::Action::Mode(mode) => {
                 match mode {
                     crate::app::Mode::Gdb => self.set_handle_key(true),
@@ -302,10 +320,6 @@ impl Component for Gdbtty {
                 };
                 None
             }
-            action::Action::SwapHV => {
-                self.is_horizontal = !self.is_horizontal;
-                None
-            }
             _ => None,
         };
         Ok(ans)
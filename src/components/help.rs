@@ -0,0 +1,253 @@
+use super::{macros, Component};
+use crate::config::{key_sequence_to_string, Config, KeyBindings, MacroConfig, StyleKey, Styles};
+use crate::{action, app::Mode};
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    text::{Line, Span, Text},
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Action {
+    Up(usize),
+    Down(usize),
+    Close,
+}
+
+/// 帮助中的一行：按键和它触发的 action 的说明
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HelpLine {
+    category: &'static str,
+    keys: String,
+    description: String,
+}
+
+/// 列出某个模式下实际生效的按键绑定，按分类分组，可以输入文字过滤
+#[derive(Default)]
+pub struct HelpOverlay {
+    keybindings: KeyBindings,
+    macros: HashMap<String, MacroConfig>,
+    styles: Styles,
+    mode: Mode,
+    /// 打开帮助时的模式，列出它的按键，关闭后回到它
+    back: Mode,
+    filter: String,
+    scroll: usize,
+}
+
+impl HelpOverlay {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&mut self) {
+        self.back = self.mode;
+        self.filter.clear();
+        self.scroll = 0;
+    }
+
+    /// 过滤后的按键，先按分类再按按键排序
+    fn help_lines(&self) -> Vec<HelpLine> {
+        let filter = self.filter.to_lowercase();
+        let mut lines = self
+            .keybindings
+            .get(&self.back)
+            .into_iter()
+            .flatten()
+            .map(|(keys, action)| HelpLine {
+                category: action.category(),
+                keys: key_sequence_to_string(keys),
+                description: match action {
+                    action::Action::Macro(name) => macros::title(&self.macros, name),
+                    _ => action.description(),
+                },
+            })
+            .filter(|line| {
+                line.keys.to_lowercase().contains(&filter)
+                    || line.description.to_lowercase().contains(&filter)
+            })
+            .collect::<Vec<_>>();
+        lines.sort_by(|a, b| (a.category, &a.keys).cmp(&(b.category, &b.keys)));
+        lines
+    }
+
+    /// 每个分类前加一行标题
+    fn text_lines(&self) -> Vec<Line<'static>> {
+        let lines = self.help_lines();
+        let key_width = lines
+            .iter()
+            .map(|l| l.keys.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut ans = vec![];
+        let mut category = None;
+        for line in lines {
+            if category != Some(line.category) {
+                category = Some(line.category);
+                ans.push(Line::from(line.category).style(self.styles.get(StyleKey::Mode)));
+            }
+            ans.push(Line::from(vec![
+                Span::from(format!("  {:width$}  ", line.keys, width = key_width))
+                    .style(self.styles.get(StyleKey::Hint)),
+                Span::from(line.description),
+            ]));
+        }
+        ans
+    }
+
+    fn draw_help(&mut self, frame: &mut Frame) {
+        let [area] = Layout::horizontal([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Percentage(80)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::bordered()
+            .title(format!(" Keybindings: {:?} ", self.back))
+            .title_bottom(" <↑↓> Scroll <Esc> Close, type to filter ");
+        let inner = block.inner(area);
+        let lines = self.text_lines();
+        let height = inner.height.saturating_sub(1) as usize;
+        self.scroll = self.scroll.min(lines.len().saturating_sub(height));
+        let filter =
+            Line::from(format!("/ {}", self.filter)).style(self.styles.get(StyleKey::PromptInput));
+        let text = Text::from_iter(
+            std::iter::once(filter).chain(lines.into_iter().skip(self.scroll).take(height)),
+        );
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
+
+impl Component for HelpOverlay {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.keybindings = config.keybindings;
+        self.macros = config.macros;
+        self.styles = config.styles;
+        Ok(())
+    }
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<action::Action>> {
+        if self.mode != Mode::Help {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Char(c) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                self.filter.push(c);
+                self.scroll = 0;
+            }
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.scroll = 0;
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+    fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
+        let ret = match action {
+            action::Action::Mode(mode) => {
+                self.mode = mode;
+                None
+            }
+            action::Action::Help if self.mode == Mode::Help => {
+                Some(action::Action::Mode(self.back))
+            }
+            action::Action::Help => {
+                self.open();
+                Some(action::Action::Mode(Mode::Help))
+            }
+            action::Action::HelpOverlay(Action::Up(n)) => {
+                self.scroll = self.scroll.saturating_sub(n);
+                None
+            }
+            action::Action::HelpOverlay(Action::Down(n)) => {
+                // 超出的部分在绘制时按高度截断
+                self.scroll = self.scroll.saturating_add(n);
+                None
+            }
+            action::Action::HelpOverlay(Action::Close) => Some(action::Action::Mode(self.back)),
+            _ => None,
+        };
+        Ok(ret)
+    }
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        if self.mode == Mode::Help {
+            self.draw_help(frame);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::code;
+    use crate::config::parse_key_sequence;
+    use std::collections::HashMap;
+
+    fn help() -> HelpOverlay {
+        let code = HashMap::from([
+            (
+                parse_key_sequence("<Ctrl-l>").unwrap(),
+                action::Action::NextLayout,
+            ),
+            (
+                parse_key_sequence("<Ctrl-q>").unwrap(),
+                action::Action::Quit,
+            ),
+            (
+                parse_key_sequence("<Up>").unwrap(),
+                action::Action::Code(code::Action::Up(1)),
+            ),
+            (
+                parse_key_sequence("<z>").unwrap(),
+                action::Action::ToggleZoom,
+            ),
+        ]);
+        let gdb = HashMap::from([(
+            parse_key_sequence("<Ctrl-d>").unwrap(),
+            action::Action::Quit,
+        )]);
+        let mut help = HelpOverlay::new();
+        help.keybindings = KeyBindings(HashMap::from([(Mode::Code, code), (Mode::Gdb, gdb)]));
+        help
+    }
+
+    #[test]
+    fn f_help_lines() {
+        let mut help = help();
+        help.mode = Mode::Code;
+        assert_eq!(
+            help.update(action::Action::Help).unwrap(),
+            Some(action::Action::Mode(Mode::Help))
+        );
+        help.mode = Mode::Help;
+        let lines = help.help_lines();
+        let keys = lines.iter().map(|l| l.keys.as_str()).collect::<Vec<_>>();
+        assert_eq!(keys, vec!["<ctrl-q>", "<ctrl-l>", "<z>", "<up>"]);
+        assert_eq!(lines[1].category, "Layout");
+        assert_eq!(lines[3].description, "Scroll code up 1 line");
+        assert_eq!(
+            action::Action::Code(code::Action::Up(3)).description(),
+            "Scroll code up 3 lines"
+        );
+
+        help.filter = "ZOOM".to_string();
+        let lines = help.help_lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].keys, "<z>");
+        help.filter = "ctrl".to_string();
+        assert_eq!(help.help_lines().len(), 2);
+
+        assert_eq!(
+            help.update(action::Action::Help).unwrap(),
+            Some(action::Action::Mode(Mode::Code))
+        );
+    }
+}
@@ -2,10 +2,13 @@ use std::time::{Duration, Instant};
 
 use super::{
     gdbtty,
-    mouse_select::{self, MouseSelect, SelectionRange, TextSelection},
+    mouse_select::{self, MouseSelect, SelectMode, SelectionRange, TextSelection},
     Component,
 };
-use crate::{action, config::Config};
+use crate::{
+    action,
+    config::{Config, StyleKey},
+};
 use color_eyre::{eyre::Ok, Result};
 use ratatui::{prelude::*, widgets::*};
 use symbols::scrollbar;
@@ -14,9 +17,14 @@ use tracing::{debug, error};
 // use tracing::debug;
 use crate::app::Mode;
 use crate::tool;
+use crate::tool::layout::SharedLayout;
 use serde::{Deserialize, Serialize};
 use strum::Display;
 use tui_term::widget::PseudoTerminal;
+mod history;
+mod search;
+use history::ConsoleHistory;
+use search::ConsoleSearch;
 
 #[derive(Default)]
 pub struct Home {
@@ -24,37 +32,47 @@ pub struct Home {
     config: Config,
 
     vt100_parser: vt100::Parser,
-    vt100_parser_buffer: Vec<u8>,
+    vt100_parser_buffer: ConsoleHistory,
     vertical_scroll_state: ScrollbarState,
     vertical_scroll: usize,
     area: Rect,
     area_change_time: Option<Instant>,
     mode: Mode,
-    is_horizontal: bool,
+    layout: SharedLayout,
     focus: bool,
+    search: Option<ConsoleSearch>,
+    /// 搜索时又有新的输出，等到下一个 Tick 再重新搜索，输出很多时不会每块都搜一遍
+    search_stale: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
 pub enum Action {
     Up(usize),
     Down(usize),
+    SearchStart,
+    SearchNext,
+    SearchPrev,
+    SearchCopy,
 }
 
 impl Home {
     pub fn new() -> Self {
         let s = Self::default();
+        let console = s.config.console.clone();
         Self {
             command_tx: s.command_tx,
             config: s.config,
-            vt100_parser: vt100::Parser::new(24, 80, usize::MAX),
+            vt100_parser: vt100::Parser::new(24, 80, console.scrollback_lines),
             vertical_scroll_state: s.vertical_scroll_state,
             vertical_scroll: s.vertical_scroll,
             area: s.area,
-            vt100_parser_buffer: s.vt100_parser_buffer,
+            vt100_parser_buffer: ConsoleHistory::new(console.history_bytes),
             area_change_time: None,
             mode: s.mode,
-            is_horizontal: s.is_horizontal,
+            layout: s.layout,
             focus: true,
+            search: None,
+            search_stale: false,
         }
     }
     pub fn set_mode(&mut self, mode: Mode) {
@@ -75,23 +93,30 @@ impl Home {
         self.vertical_scroll = self.vertical_scroll.saturating_add(n);
         self.vertical_scroll_state = self.vertical_scroll_state.position(self.vertical_scroll);
     }
-    fn set_area(&mut self, area: &layout::Size) {
-        let area = Rect::new(0, 0, area.width, area.height);
-        tool::Layouts { gdb: self.area, .. } = (area, self.is_horizontal).into();
+    fn set_area(&mut self) {
+        self.area = self.layout.borrow().layouts().gdb;
     }
-    fn set_vt100_area(&mut self, area: &layout::Size) {
-        let area = Rect::new(0, 0, area.width, area.height);
-        let tool::Layouts { gdb: area, .. } = (area, self.is_horizontal).into();
+    fn set_vt100_area(&mut self) {
+        let area = self.layout.borrow().layouts().gdb;
         let in_size = area
             .inner(Margin {
                 vertical: 0,
                 horizontal: 1,
             })
             .as_size();
+        // 不在当前布局中的窗口保留原来的大小
+        if in_size.width == 0 || in_size.height == 0 {
+            return;
+        }
         debug!("start resize {}", self.vt100_parser_buffer.len());
-        self.vt100_parser = vt100::Parser::new(in_size.height, in_size.width, usize::MAX);
-        self.vt100_parser
-            .process(self.vt100_parser_buffer.as_slice());
+        self.vt100_parser = vt100::Parser::new(
+            in_size.height,
+            in_size.width,
+            self.config.console.scrollback_lines,
+        );
+        let (front, back) = self.vt100_parser_buffer.as_slices();
+        self.vt100_parser.process(front);
+        self.vt100_parser.process(back);
         debug!("end resize {}", self.vt100_parser_buffer.len());
     }
     fn set_scroll_bar_status(&mut self, test_len: usize) {
@@ -105,7 +130,7 @@ impl Home {
         self.vt100_parser.set_scrollback(self.vertical_scroll);
         let screen = self.vt100_parser.screen();
         let cursor_show = self.vertical_scroll == 0 && self.mode == Mode::Gdb && self.focus;
-        let cursor_style = Style::default().fg(Color::Rgb(255, 204, 0));
+        let cursor_style = self.config.styles.get(StyleKey::ConsoleCursor);
         let pseudo_term = PseudoTerminal::new(screen)
             .cursor(
                 tui_term::widget::Cursor::default()
@@ -113,28 +138,31 @@ impl Home {
                     .overlay_style(cursor_style.add_modifier(Modifier::REVERSED))
                     .visibility(cursor_show),
             )
-            .style(
-                Style::default()
-                    .fg(Color::White)
-                    .bg(Color::Black)
-                    .add_modifier(Modifier::BOLD),
-            );
+            .style(self.config.styles.get(StyleKey::Console));
 
         frame.render_widget(pseudo_term, area);
 
         // debug!("end one draw");
     }
+    fn is_history_truncated(&self, test_len: usize) -> bool {
+        self.vt100_parser_buffer.is_truncated() || test_len >= self.config.console.scrollback_lines
+    }
     fn draw_scroll(&mut self, frame: &mut Frame, area: Rect, test_len: usize) {
         let [area_in, _] =
             Layout::horizontal(vec![Constraint::Fill(1), Constraint::Length(1)]).areas(area);
-        let text_scroll_status = match self.vertical_scroll {
-            0 => String::new(),
-            _ => format!("[{}/{}]", test_len - self.vertical_scroll, test_len),
+        let text_scroll_status = match (self.vertical_scroll, self.is_history_truncated(test_len)) {
+            (0, _) => String::new(),
+            (_, false) => format!("[{}/{}]", test_len - self.vertical_scroll, test_len),
+            (_, true) => format!(
+                "[history truncated] [{}/{}]",
+                test_len - self.vertical_scroll,
+                test_len
+            ),
         };
         let scroll_block = Block::default().title(
             Line::from(text_scroll_status)
                 .right_aligned()
-                .fg(Color::White),
+                .style(self.config.styles.get(StyleKey::Text)),
         );
         let scrollbar =
             Scrollbar::new(ScrollbarOrientation::VerticalRight).symbols(scrollbar::VERTICAL);
@@ -148,18 +176,102 @@ impl Home {
         );
         Some((start_row, start_col))
     }
+    /// 收集 vt100 scrollback 和当前屏幕中的全部行
+    fn get_history_lines(&mut self) -> Vec<String> {
+        let now_scrollback = self.vt100_parser.screen().scrollback();
+        let scrollback_len = self.get_text_hight(&self.area.clone());
+        let (rows, cols) = self.vt100_parser.screen().size();
+        let total = scrollback_len.saturating_add(rows as usize);
+        let mut lines = Vec::with_capacity(total);
+        while lines.len() < total && rows > 0 {
+            let top = lines.len();
+            let scrollback = scrollback_len.saturating_sub(top);
+            self.vt100_parser.set_scrollback(scrollback);
+            let skip = top - scrollback_len.saturating_sub(scrollback);
+            lines.extend(
+                self.vt100_parser
+                    .screen()
+                    .rows(0, cols)
+                    .skip(skip)
+                    .take(total - top),
+            );
+        }
+        self.vt100_parser.set_scrollback(now_scrollback);
+        lines
+    }
+    fn search_refresh(&mut self) {
+        self.search_stale = false;
+        if self.search.is_some() {
+            let lines = self.get_history_lines();
+            if let Some(search) = self.search.as_mut() {
+                search.set_lines(lines);
+            }
+        }
+    }
+    fn search_show_current(&mut self) {
+        let scrollback_len = self.get_text_hight(&self.area.clone());
+        let (rows, _) = self.vt100_parser.screen().size();
+        if let Some(m) = self.search.as_ref().and_then(|s| s.current()) {
+            self.vertical_scroll = search::scrollback_for_row(m.row, scrollback_len, rows as usize);
+        }
+    }
+    fn handle_search_key_event(&mut self, key: crossterm::event::KeyEvent) {
+        use crossterm::event::{KeyCode, KeyModifiers};
+        if let Some(search) = self.search.as_mut() {
+            match key.code {
+                KeyCode::Char(c) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                    search.push_char(c);
+                }
+                KeyCode::Backspace => search.pop_char(),
+                _ => return,
+            }
+            self.search_show_current();
+        }
+    }
+    fn draw_search(&self, frame: &mut Frame, area: Rect, n: usize) {
+        if let Some(search) = &self.search {
+            let top = n.saturating_sub(self.vertical_scroll);
+            let bottom = top.saturating_add(area.height as usize);
+            search
+                .matches_in_rows(top, bottom)
+                .for_each(|(m, is_current)| {
+                    let style = match is_current {
+                        true => self.config.styles.get(StyleKey::SearchCurrent),
+                        false => self.config.styles.get(StyleKey::SearchMatch),
+                    };
+                    let select = Rect::new(
+                        area.x.saturating_add(m.start_col as u16),
+                        area.y.saturating_add((m.row - top) as u16),
+                        m.end_col.saturating_sub(m.start_col) as u16,
+                        1,
+                    )
+                    .intersection(area);
+                    frame.buffer_mut().set_style(select, style);
+                });
+            let [_, area_prompt] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+            let prompt =
+                Paragraph::new(search.status()).style(self.config.styles.get(StyleKey::StatusBar));
+            frame.render_widget(Clear, area_prompt);
+            frame.render_widget(prompt, area_prompt);
+        }
+    }
 }
 
 impl Component for Home {
-    fn init(&mut self, area: Size) -> Result<()> {
-        self.set_area(&area);
-        self.set_vt100_area(&area);
+    fn init(&mut self, _area: Size) -> Result<()> {
+        self.set_area();
+        self.set_vt100_area();
         Ok(())
     }
     fn register_action_handler(&mut self, tx: UnboundedSender<action::Action>) -> Result<()> {
         self.command_tx = Some(tx);
         Ok(())
     }
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
     fn handle_events(
         &mut self,
         event: Option<crate::tui::Event>,
@@ -183,9 +295,20 @@ impl Component for Home {
     }
 
     fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.vt100_parser_buffer
+            .set_max_bytes(config.console.history_bytes);
         self.config = config;
         Ok(())
     }
+    fn handle_key_event(
+        &mut self,
+        key: crossterm::event::KeyEvent,
+    ) -> Result<Option<action::Action>> {
+        if self.mode == Mode::Search {
+            self.handle_search_key_event(key);
+        }
+        Ok(None)
+    }
     fn handle_mouse_event(
         &mut self,
         mouse: crossterm::event::MouseEvent,
@@ -209,9 +332,9 @@ impl Component for Home {
     }
     fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
         let ret_action = match action {
-            action::Action::Resize(x, y) => {
-                self.set_area(&layout::Size::new(x, y));
-                // self.set_vt100_area(&layout::Size::new(x, y));
+            action::Action::Resize(_, _) => {
+                self.set_area();
+                // self.set_vt100_area();
                 self.area_change_time = Some(Instant::now());
                 None
             }
@@ -225,17 +348,47 @@ impl Component for Home {
             }
             action::Action::Mode(mode) => {
                 self.set_mode(mode);
+                if mode != Mode::Search {
+                    self.search = None;
+                }
+                None
+            }
+            action::Action::Home(Action::SearchStart) => {
+                self.search = Some(ConsoleSearch::new());
+                self.search_refresh();
+                Some(action::Action::Mode(Mode::Search))
+            }
+            action::Action::Home(Action::SearchNext) => {
+                if let Some(search) = self.search.as_mut() {
+                    search.next();
+                }
+                self.search_show_current();
                 None
             }
+            action::Action::Home(Action::SearchPrev) => {
+                if let Some(search) = self.search.as_mut() {
+                    search.prev();
+                }
+                self.search_show_current();
+                None
+            }
+            action::Action::Home(Action::SearchCopy) => self
+                .search
+                .as_ref()
+                .and_then(|search| search.current_line())
+                .map(|line| action::Action::CopyStr(line.to_string())),
             action::Action::Gdbtty(gdbtty::Action::Out(out)) => {
-                self.vt100_parser_buffer.append(out.clone().as_mut());
+                self.vt100_parser_buffer.push(out.as_slice());
                 self.vt100_parser.process(out.as_slice());
                 self.vt100_parser.set_scrollback(0);
                 self.vertical_scroll = 0;
+                self.search_stale = self.search.is_some();
                 None
             }
-            action::Action::SwapHV => {
-                self.is_horizontal = !self.is_horizontal;
+            action::Action::Tick => {
+                if self.search_stale {
+                    self.search_refresh();
+                }
                 None
             }
             action::Action::MouseSelect(mouse_select::Action::SelectionRange(select_action)) => {
@@ -277,23 +430,27 @@ impl Component for Home {
         Ok(ret_action)
     }
 
-    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
         // debug!("start one draw");
         if let Some(now) = self.area_change_time {
             match now.elapsed() > Duration::from_millis(400) {
                 true => {
                     self.area_change_time = None;
-                    self.set_vt100_area(&area.as_size());
+                    self.set_vt100_area();
                 }
                 false => {
                     return Ok(());
                 }
             }
         }
-        let tool::Layouts { gdb: area, .. } = (area, self.is_horizontal).into();
+        let tool::Layouts { gdb: area, .. } = self.layout.borrow().layouts();
+        if area.is_empty() {
+            return Ok(());
+        }
         let n = self.get_text_hight(&area);
         self.set_scroll_bar_status(n);
         self.draw_cmd(frame, area);
+        self.draw_search(frame, area, n);
         self.draw_scroll(frame, area, n);
         Ok(())
     }
@@ -308,20 +465,36 @@ impl TextSelection for Home {
             return None;
         }
         let screen = self.vt100_parser.screen();
+        let (_, width) = screen.size();
         let MouseSelect {
             start: (select_start_row, select_start_col),
             end: (select_end_row, select_end_col),
+            mode,
         } = select;
         let tty_select_start =
             self.change_tui_position_to_tty_position(*select_start_row, *select_start_col)?;
         let tty_select_end =
             self.change_tui_position_to_tty_position(*select_end_row, *select_end_col)?;
-        let ans = screen.contents_between(
-            tty_select_start.0,
-            tty_select_start.1,
-            tty_select_end.0,
-            tty_select_end.1,
-        );
+        let ans = match mode {
+            SelectMode::Char => screen.contents_between(
+                tty_select_start.0,
+                tty_select_start.1,
+                tty_select_end.0,
+                tty_select_end.1,
+            ),
+            SelectMode::Line => {
+                screen.contents_between(tty_select_start.0, 0, tty_select_end.0, width)
+            }
+            SelectMode::Block => screen
+                .rows(
+                    tty_select_start.1,
+                    tty_select_end.1.saturating_sub(tty_select_start.1),
+                )
+                .skip(tty_select_start.0 as usize)
+                .take((tty_select_end.0.saturating_sub(tty_select_start.0) as usize) + 1)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        };
         match ans.is_empty() {
             true => None,
             false => Some(ans),
@@ -341,6 +514,7 @@ impl TextSelection for Home {
         let MouseSelect {
             start: (select_start_row, select_start_col),
             end: (select_end_row, select_end_col),
+            mode,
         } = select;
         let tty_select_start =
             self.change_tui_position_to_tty_position(*select_start_row, *select_start_col)?;
@@ -352,13 +526,13 @@ impl TextSelection for Home {
             .enumerate()
             .map(|(id, line_id)| {
                 let id = id + 1;
-                let start = match id == 1 {
-                    true => tty_select_start.1,
-                    false => 0,
+                let start = match (mode, id == 1) {
+                    (SelectMode::Block, _) | (SelectMode::Char, true) => tty_select_start.1,
+                    _ => 0,
                 } as usize;
-                let end = match id == select_row_len {
-                    true => tty_select_end.1,
-                    false => width,
+                let end = match (mode, id == select_row_len) {
+                    (SelectMode::Block, _) | (SelectMode::Char, true) => tty_select_end.1,
+                    _ => width,
                 } as usize;
                 SelectionRange {
                     line_number: line_id as usize,
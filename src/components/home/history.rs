@@ -0,0 +1,85 @@
+use std::collections::VecDeque;
+
+/// gdb 控制台原始输出的环形缓冲区，超过 `max_bytes` 时从最早的整行开始丢弃
+#[derive(Debug, Default)]
+pub struct ConsoleHistory {
+    buf: VecDeque<u8>,
+    max_bytes: usize,
+    dropped_bytes: usize,
+}
+
+impl ConsoleHistory {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            buf: VecDeque::new(),
+            max_bytes,
+            dropped_bytes: 0,
+        }
+    }
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        self.shrink();
+    }
+    pub fn push(&mut self, out: &[u8]) {
+        self.buf.extend(out);
+        self.shrink();
+    }
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+    pub fn is_truncated(&self) -> bool {
+        self.dropped_bytes > 0
+    }
+    pub fn as_slices(&self) -> (&[u8], &[u8]) {
+        self.buf.as_slices()
+    }
+    fn shrink(&mut self) {
+        if self.buf.len() <= self.max_bytes {
+            return;
+        }
+        let excess = self.buf.len() - self.max_bytes;
+        // 尽量在换行处截断，避免切开转义序列和 utf8 字符
+        let end = self
+            .buf
+            .iter()
+            .skip(excess - 1)
+            .position(|c| *c == b'\n')
+            .map_or(excess, |pos| excess.saturating_add(pos));
+        self.buf.drain(..end);
+        self.dropped_bytes = self.dropped_bytes.saturating_add(end);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(history: &ConsoleHistory) -> Vec<u8> {
+        let (a, b) = history.as_slices();
+        a.iter().chain(b.iter()).copied().collect()
+    }
+
+    #[test]
+    fn f_history_not_full() {
+        let mut history = ConsoleHistory::new(64);
+        history.push(b"(gdb) r\r\n");
+        assert_eq!(contents(&history), b"(gdb) r\r\n");
+        assert!(!history.is_truncated());
+    }
+
+    #[test]
+    fn f_history_drop_whole_line() {
+        let mut history = ConsoleHistory::new(16);
+        history.push(b"line one\r\nline two\r\nline 3");
+        assert_eq!(contents(&history), b"line two\r\nline 3");
+        assert!(history.is_truncated());
+        assert!(history.len() <= 16);
+    }
+
+    #[test]
+    fn f_history_no_newline() {
+        let mut history = ConsoleHistory::new(4);
+        history.push(b"0123456789");
+        assert_eq!(contents(&history), b"6789");
+    }
+}
@@ -0,0 +1,222 @@
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+/// 一个匹配在控制台历史中的位置，`row` 是从最早的 scrollback 行开始的绝对行号
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchMatch {
+    pub row: usize,
+    pub start_col: usize,
+    pub end_col: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct ConsoleSearch {
+    query: String,
+    regex: Option<Regex>,
+    error: Option<String>,
+    lines: Vec<String>,
+    matches: Vec<SearchMatch>,
+    current: Option<usize>,
+}
+
+impl ConsoleSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn push_char(&mut self, c: char) {
+        self.query.push(c);
+        self.compile();
+    }
+    pub fn pop_char(&mut self) {
+        self.query.pop();
+        self.compile();
+    }
+    pub fn set_lines(&mut self, lines: Vec<String>) {
+        self.lines = lines;
+        self.rematch();
+    }
+    pub fn next(&mut self) {
+        let n = self.matches.len();
+        self.current = self.current.map(|id| (id + 1) % n);
+    }
+    pub fn prev(&mut self) {
+        let n = self.matches.len();
+        self.current = self.current.map(|id| (id + n - 1) % n);
+    }
+    pub fn current(&self) -> Option<&SearchMatch> {
+        self.current.and_then(|id| self.matches.get(id))
+    }
+    pub fn current_line(&self) -> Option<&str> {
+        self.current()
+            .and_then(|m| self.lines.get(m.row))
+            .map(|line| line.trim_end())
+    }
+    /// 返回 `[start_row, end_row)` 内的匹配，以及是否为当前匹配
+    pub fn matches_in_rows(
+        &self,
+        start_row: usize,
+        end_row: usize,
+    ) -> impl Iterator<Item = (&SearchMatch, bool)> {
+        let start = self.matches.partition_point(|m| m.row < start_row);
+        self.matches
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take_while(move |(_, m)| m.row < end_row)
+            .map(|(id, m)| (m, Some(id) == self.current))
+    }
+    pub fn status(&self) -> String {
+        let state = match (&self.error, self.current) {
+            (Some(e), _) => format!("[{}]", e),
+            (None, Some(id)) => format!("[{}/{}]", id + 1, self.matches.len()),
+            (None, None) if self.query.is_empty() => String::new(),
+            (None, None) => "[no match]".to_string(),
+        };
+        format!("/{} {}", self.query, state)
+    }
+    fn compile(&mut self) {
+        match self.query.is_empty() {
+            true => {
+                self.regex = None;
+                self.error = None;
+            }
+            false => match Regex::new(&self.query) {
+                std::result::Result::Ok(re) => {
+                    self.regex = Some(re);
+                    self.error = None;
+                }
+                Err(_) => {
+                    self.regex = None;
+                    self.error = Some("invalid regex".to_string());
+                }
+            },
+        }
+        self.rematch();
+    }
+    fn rematch(&mut self) {
+        let current_row = self.current().map(|m| m.row);
+        self.matches = match &self.regex {
+            Some(re) => find_matches(re, &self.lines),
+            None => vec![],
+        };
+        // 保持在原来的行附近，否则跳到最新的匹配
+        self.current = match (current_row, self.matches.is_empty()) {
+            (_, true) => None,
+            (Some(row), false) => Some(
+                self.matches
+                    .partition_point(|m| m.row < row)
+                    .min(self.matches.len() - 1),
+            ),
+            (None, false) => Some(self.matches.len() - 1),
+        };
+    }
+}
+
+pub fn find_matches(re: &Regex, lines: &[String]) -> Vec<SearchMatch> {
+    lines
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            re.find_iter(line)
+                .filter(|m| !m.is_empty())
+                .map(move |m| SearchMatch {
+                    row,
+                    start_col: line[..m.start()].width(),
+                    end_col: line[..m.end()].width(),
+                })
+        })
+        .collect()
+}
+
+/// 计算让第 `row` 行显示在窗口中间所需的 scrollback 值
+pub fn scrollback_for_row(row: usize, scrollback_len: usize, height: usize) -> usize {
+    let top = row.saturating_sub(height.div_euclid(2));
+    scrollback_len.saturating_sub(top)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines() -> Vec<String> {
+        vec![
+            "(gdb) b main".to_string(),
+            "Breakpoint 1 at 0x1139: file a.c, line 3.".to_string(),
+            "(gdb) r".to_string(),
+            "Breakpoint 1, main () at a.c:3".to_string(),
+            "中文 Breakpoint".to_string(),
+        ]
+    }
+
+    #[test]
+    fn f_find_matches() {
+        let re = Regex::new("Breakpoint [0-9]").unwrap();
+        let m = find_matches(&re, &lines());
+        assert_eq!(
+            m,
+            vec![
+                SearchMatch {
+                    row: 1,
+                    start_col: 0,
+                    end_col: 12
+                },
+                SearchMatch {
+                    row: 3,
+                    start_col: 0,
+                    end_col: 12
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn f_find_matches_wide_char() {
+        let re = Regex::new("Break").unwrap();
+        let m = find_matches(&re, &lines());
+        assert_eq!(
+            m.last(),
+            Some(&SearchMatch {
+                row: 4,
+                start_col: 5,
+                end_col: 10
+            })
+        );
+    }
+
+    #[test]
+    fn f_search_navigation() {
+        let mut search = ConsoleSearch::new();
+        search.set_lines(lines());
+        "Breakpoint".chars().for_each(|c| search.push_char(c));
+        assert_eq!(search.current().map(|m| m.row), Some(4));
+        search.next();
+        assert_eq!(search.current().map(|m| m.row), Some(1));
+        search.prev();
+        search.prev();
+        assert_eq!(search.current().map(|m| m.row), Some(3));
+        assert_eq!(
+            search.current_line(),
+            Some("Breakpoint 1, main () at a.c:3")
+        );
+        assert_eq!(search.status(), "/Breakpoint [2/3]");
+        assert_eq!(search.matches_in_rows(2, 4).count(), 1);
+    }
+
+    #[test]
+    fn f_search_invalid_regex() {
+        let mut search = ConsoleSearch::new();
+        search.set_lines(lines());
+        search.push_char('(');
+        assert_eq!(search.current(), None);
+        assert_eq!(search.status(), "/( [invalid regex]");
+        search.pop_char();
+        assert_eq!(search.status(), "/ ");
+    }
+
+    #[test]
+    fn f_scrollback_for_row() {
+        assert_eq!(scrollback_for_row(10, 100, 20), 100);
+        assert_eq!(scrollback_for_row(60, 100, 20), 50);
+        assert_eq!(scrollback_for_row(115, 100, 20), 0);
+    }
+}
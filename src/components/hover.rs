@@ -0,0 +1,541 @@
+use super::{gdbmi, gdbtty, Component};
+use crate::config::{Config, HoverConfig, StyleKey, Styles};
+use crate::mi::evaluate::{self, EvalResult, VarObj};
+use crate::{action, app::Mode, tool};
+use color_eyre::Result;
+use ratatui::{
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum::Display;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{debug, error};
+
+/// 弹窗最多显示的行数
+const MAX_HEIGHT: u16 = 12;
+const MIN_WIDTH: u16 = 24;
+const MAX_WIDTH: u16 = 80;
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Action {
+    /// 求值表达式，弹窗显示在屏幕位置 (row, column) 的下面，
+    /// 用按键打开的进入 Hover 模式，鼠标停留打开的只显示
+    Open((String, (u16, u16), bool)),
+    /// 鼠标离开了表达式，关闭鼠标打开的弹窗
+    Leave,
+    Up,
+    Down,
+    /// 展开或收起选中的成员
+    Expand,
+    /// 收起选中的成员，已经收起时选中它的上一级
+    Collapse,
+    /// 用 `display` 让 gdb 每次停下时显示它
+    Watch,
+    /// 在 gdb 控制台中 `print`
+    Print,
+    Close,
+}
+
+/// 发出的带编号的命令等待的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Request {
+    Value,
+    Create,
+    Children(String),
+}
+
+/// 变量对象树中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Node {
+    var: VarObj,
+    depth: usize,
+    expanded: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Popup {
+    expr: String,
+    pos: (u16, u16),
+    interactive: bool,
+    /// `-data-evaluate-expression` 的结果，还没返回时为 None
+    value: Option<Result<String, String>>,
+    /// 变量对象创建后第一项是表达式本身，展开的成员跟在后面
+    nodes: Vec<Node>,
+    selected: usize,
+}
+
+/// 在当前栈帧中对源码窗口光标处的表达式求值，结构体可以通过变量对象展开
+#[derive(Default)]
+pub struct Hover {
+    command_tx: Option<UnboundedSender<action::Action>>,
+    config: HoverConfig,
+    styles: Styles,
+    mode: Mode,
+    /// 进入 Hover 模式前的模式，关闭后回到它
+    back: Mode,
+    popup: Option<Popup>,
+    pending: HashMap<u64, Request>,
+    next_token: u64,
+}
+
+impl Hover {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn send(&self, action: action::Action) {
+        match self.command_tx.as_ref() {
+            Some(send) => tool::send_action(send, action),
+            None => error!("{}", "send hover error"),
+        }
+    }
+
+    fn request(&mut self, request: Request, cmd: String) {
+        self.next_token += 1;
+        let token = self.next_token;
+        debug!("hover request {} {}", token, &cmd);
+        self.pending.insert(token, request);
+        self.send(action::Action::Gdbmi(gdbmi::Action::TokenCommand((
+            token, cmd,
+        ))));
+    }
+
+    fn open(&mut self, expr: String, pos: (u16, u16), interactive: bool) {
+        if let Some(popup) = self.popup.as_mut().filter(|p| p.expr == expr) {
+            popup.interactive |= interactive;
+            return;
+        }
+        self.close();
+        let quoted = evaluate::quote(&expr);
+        self.popup = Some(Popup {
+            expr,
+            pos,
+            interactive,
+            value: None,
+            nodes: vec![],
+            selected: 0,
+        });
+        self.request(
+            Request::Value,
+            format!("-data-evaluate-expression {}", quoted),
+        );
+        self.request(Request::Create, format!("-var-create - * {}", quoted));
+    }
+
+    /// 关闭弹窗并删除 gdb 中的变量对象，还没返回的结果到达后丢弃
+    fn close(&mut self) {
+        if let Some(root) = self.popup.take().and_then(|p| p.nodes.into_iter().next()) {
+            self.delete_var(&root.var.name);
+        }
+        self.pending.clear();
+    }
+
+    /// 关闭弹窗，是用按键打开的时回到之前的模式
+    fn close_action(&mut self) -> Option<action::Action> {
+        let interactive = self.popup.as_ref().is_some_and(|p| p.interactive);
+        self.close();
+        match interactive && self.mode == Mode::Hover {
+            true => Some(action::Action::Mode(self.back)),
+            false => None,
+        }
+    }
+
+    fn delete_var(&self, name: &str) {
+        self.send(action::Action::Gdbmi(gdbmi::Action::Command(format!(
+            "-var-delete {}",
+            name
+        ))));
+    }
+
+    fn on_result(&mut self, token: u64, result: EvalResult) -> Option<action::Action> {
+        let Some(request) = self.pending.remove(&token) else {
+            // 弹窗关闭后才创建好的变量对象
+            if let EvalResult::Var(var) = result {
+                self.delete_var(&var.name);
+            }
+            return None;
+        };
+        let popup = self.popup.as_mut()?;
+        match (request, result) {
+            (Request::Value, EvalResult::Value(value)) => popup.value = Some(Ok(value)),
+            (Request::Value, EvalResult::Error(msg)) => {
+                // 鼠标停在不能求值的单词上时不打扰
+                if !popup.interactive {
+                    self.close();
+                    return None;
+                }
+                popup.value = Some(Err(msg));
+            }
+            (Request::Create, EvalResult::Var(mut var)) => {
+                var.exp = popup.expr.clone();
+                popup.nodes = vec![Node {
+                    var,
+                    depth: 0,
+                    expanded: false,
+                }];
+            }
+            (Request::Children(name), EvalResult::Children(children)) => {
+                let id = popup.nodes.iter().position(|n| n.var.name == name)?;
+                if popup.nodes[id].expanded {
+                    return None;
+                }
+                popup.nodes[id].expanded = true;
+                let depth = popup.nodes[id].depth + 1;
+                let children = children.into_iter().map(|var| Node {
+                    var,
+                    depth,
+                    expanded: false,
+                });
+                popup.nodes.splice(id + 1..id + 1, children);
+            }
+            (Request::Children(_), EvalResult::Error(msg)) => {
+                return Some(action::Action::Notify(msg));
+            }
+            (request, result) => debug!("hover ignore {:?} for {:?}", result, request),
+        }
+        None
+    }
+
+    fn expand(&mut self) {
+        let Some(popup) = self.popup.as_mut() else {
+            return;
+        };
+        let Some(node) = popup.nodes.get(popup.selected) else {
+            return;
+        };
+        if node.expanded {
+            collapse(&mut popup.nodes, popup.selected);
+        } else if node.var.numchild > 0 {
+            let name = node.var.name.clone();
+            let cmd = format!(
+                "-var-list-children --all-values {} 0 {}",
+                name, self.config.max_children
+            );
+            self.request(Request::Children(name), cmd);
+        }
+    }
+
+    fn collapse(&mut self) {
+        let Some(popup) = self.popup.as_mut() else {
+            return;
+        };
+        let Some(node) = popup.nodes.get(popup.selected) else {
+            return;
+        };
+        if node.expanded {
+            collapse(&mut popup.nodes, popup.selected);
+        } else if let Some(parent) = popup.nodes[..popup.selected]
+            .iter()
+            .rposition(|n| n.depth < node.depth)
+        {
+            popup.selected = parent;
+        }
+    }
+
+    /// 在控制台中执行一条关于表达式的命令，然后关闭弹窗
+    fn console(&mut self, cmd: &str) -> Option<action::Action> {
+        let expr = self.popup.as_ref()?.expr.clone();
+        self.send(action::Action::Gdbtty(gdbtty::Action::Command(format!(
+            "{} {}",
+            cmd, expr
+        ))));
+        self.close_action()
+    }
+
+    fn lines(&self, popup: &Popup) -> Vec<Line<'_>> {
+        let type_style = self.styles.get(StyleKey::Hint);
+        let value = match &popup.value {
+            None => "…".to_string(),
+            Some(Ok(value)) => value.clone(),
+            Some(Err(msg)) => return vec![Line::from(msg.clone())],
+        };
+        if popup.nodes.is_empty() {
+            return vec![Line::from(format!("{} = {}", popup.expr, one_line(&value)))];
+        }
+        popup
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(id, node)| {
+                let marker = match (node.var.numchild > 0, node.expanded) {
+                    (false, _) => "  ",
+                    (true, false) => "▸ ",
+                    (true, true) => "▾ ",
+                };
+                let value = match id {
+                    0 => value.clone(),
+                    _ => node.var.value.clone().unwrap_or_default(),
+                };
+                let mut spans = vec![
+                    Span::from("  ".repeat(node.depth)),
+                    Span::from(marker),
+                    Span::from(format!("{} = {}", node.var.exp, one_line(&value))),
+                ];
+                if let Some(t) = &node.var.type_name {
+                    spans.push(Span::from(format!("  {}", t)).style(type_style));
+                }
+                let line = Line::from(spans);
+                match popup.interactive && id == popup.selected {
+                    true => line.style(self.styles.get(StyleKey::ListSelected)),
+                    false => line,
+                }
+            })
+            .collect()
+    }
+
+    fn draw_popup(&self, frame: &mut Frame, popup: &Popup) {
+        let lines = self.lines(popup);
+        let screen = frame.area();
+        let width = lines
+            .iter()
+            .map(|l| l.width() as u16)
+            .max()
+            .unwrap_or(0)
+            .saturating_add(2)
+            .clamp(MIN_WIDTH, MAX_WIDTH)
+            .min(screen.width);
+        let height = (lines.len() as u16)
+            .saturating_add(2)
+            .min(MAX_HEIGHT)
+            .min(screen.height);
+        let (row, col) = popup.pos;
+        // 下面放不下时放在上面
+        let y = match row.saturating_add(1).saturating_add(height) <= screen.bottom() {
+            true => row.saturating_add(1),
+            false => row.saturating_sub(height),
+        };
+        let x = col.min(screen.right().saturating_sub(width));
+        let area = Rect::new(x, y, width, height).intersection(screen);
+        let mut block = Block::bordered();
+        if popup.interactive {
+            block = block.title_bottom(" <Enter> Expand <w> Watch <p> Print <Esc> Close ");
+        }
+        // 选中的行保持在可见范围内
+        let inner = height.saturating_sub(2) as usize;
+        let skip = popup.selected.saturating_add(1).saturating_sub(inner);
+        let lines = lines.into_iter().skip(skip).collect::<Vec<_>>();
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(lines).block(block), area);
+    }
+}
+
+/// 去掉 `id` 展开的所有下级成员
+fn collapse(nodes: &mut Vec<Node>, id: usize) {
+    let depth = nodes[id].depth;
+    let end = nodes[id + 1..]
+        .iter()
+        .position(|n| n.depth <= depth)
+        .map_or(nodes.len(), |n| id + 1 + n);
+    nodes.drain(id + 1..end);
+    nodes[id].expanded = false;
+}
+
+fn one_line(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl Component for Hover {
+    fn register_action_handler(&mut self, tx: UnboundedSender<action::Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.config = config.hover;
+        self.styles = config.styles;
+        Ok(())
+    }
+    fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
+        let ret = match action {
+            action::Action::Mode(mode) => {
+                if mode == Mode::Hover && self.mode != Mode::Hover {
+                    // 可视模式的选择在进入 Hover 时已经丢弃
+                    self.back = match self.mode {
+                        Mode::Visual => Mode::Code,
+                        mode => mode,
+                    };
+                }
+                self.mode = mode;
+                // 切到其它模式时弹窗已经没用了
+                if mode != Mode::Hover && self.popup.as_ref().is_some_and(|p| p.interactive) {
+                    self.close();
+                }
+                None
+            }
+            action::Action::Hover(Action::Open((expr, pos, interactive))) => {
+                self.open(expr, pos, interactive);
+                match interactive && self.mode != Mode::Hover {
+                    true => Some(action::Action::Mode(Mode::Hover)),
+                    false => None,
+                }
+            }
+            action::Action::Hover(Action::Leave) => {
+                if self.popup.as_ref().is_some_and(|p| !p.interactive) {
+                    self.close();
+                }
+                None
+            }
+            action::Action::Hover(Action::Up) => {
+                if let Some(popup) = self.popup.as_mut() {
+                    popup.selected = popup.selected.saturating_sub(1);
+                }
+                None
+            }
+            action::Action::Hover(Action::Down) => {
+                if let Some(popup) = self.popup.as_mut() {
+                    popup.selected = popup
+                        .selected
+                        .saturating_add(1)
+                        .min(popup.nodes.len().saturating_sub(1));
+                }
+                None
+            }
+            action::Action::Hover(Action::Expand) => {
+                self.expand();
+                None
+            }
+            action::Action::Hover(Action::Collapse) => {
+                self.collapse();
+                None
+            }
+            action::Action::Hover(Action::Watch) => {
+                let expr = self.popup.as_ref().map(|p| p.expr.clone());
+                let ret = self.console("display");
+                if let Some(expr) = expr {
+                    self.send(action::Action::Notify(format!("Watching {}", expr)));
+                }
+                ret
+            }
+            action::Action::Hover(Action::Print) => self.console("print"),
+            action::Action::Hover(Action::Close) => self.close_action(),
+            action::Action::Gdbmi(gdbmi::Action::Evaluated((token, result))) => {
+                self.on_result(token, result)
+            }
+            // 程序停在了新的位置，显示的值已经过时
+            action::Action::Gdbmi(gdbmi::Action::ShowFile(_))
+            | action::Action::Gdbmi(gdbmi::Action::ShowAsm(_)) => self.close_action(),
+            _ => None,
+        };
+        Ok(ret)
+    }
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        if let Some(popup) = &self.popup {
+            self.draw_popup(frame, popup);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc::UnboundedReceiver;
+
+    fn hover() -> (Hover, UnboundedReceiver<action::Action>) {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut hover = Hover::new();
+        hover.register_action_handler(tx).unwrap();
+        hover.config = HoverConfig::default();
+        (hover, rx)
+    }
+
+    fn sent(rx: &mut UnboundedReceiver<action::Action>) -> Vec<action::Action> {
+        std::iter::from_fn(|| rx.try_recv().ok()).collect()
+    }
+
+    fn var(name: &str, exp: &str, numchild: u64) -> VarObj {
+        VarObj {
+            name: name.to_string(),
+            exp: exp.to_string(),
+            value: Some("1".to_string()),
+            type_name: Some("int".to_string()),
+            numchild,
+        }
+    }
+
+    #[test]
+    fn f_hover_expand() {
+        let (mut h, mut rx) = hover();
+        let open = Action::Open(("p".to_string(), (3, 4), true));
+        assert_eq!(
+            h.update(action::Action::Hover(open)).unwrap(),
+            Some(action::Action::Mode(Mode::Hover))
+        );
+        h.update(action::Action::Mode(Mode::Hover)).unwrap();
+        assert_eq!(
+            sent(&mut rx),
+            vec![
+                action::Action::Gdbmi(gdbmi::Action::TokenCommand((
+                    1,
+                    "-data-evaluate-expression \"p\"".to_string()
+                ))),
+                action::Action::Gdbmi(gdbmi::Action::TokenCommand((
+                    2,
+                    "-var-create - * \"p\"".to_string()
+                ))),
+            ]
+        );
+        h.on_result(1, EvalResult::Value("0x1234".to_string()));
+        h.on_result(2, EvalResult::Var(var("var1", "", 2)));
+        h.update(action::Action::Hover(Action::Expand)).unwrap();
+        assert_eq!(
+            sent(&mut rx),
+            vec![action::Action::Gdbmi(gdbmi::Action::TokenCommand((
+                3,
+                "-var-list-children --all-values var1 0 100".to_string()
+            )))]
+        );
+        let children = vec![var("var1.a", "a", 0), var("var1.next", "next", 2)];
+        h.on_result(3, EvalResult::Children(children));
+        let popup = h.popup.as_ref().unwrap();
+        assert_eq!(
+            popup.nodes.iter().map(|n| n.depth).collect::<Vec<_>>(),
+            vec![0, 1, 1]
+        );
+        assert_eq!(popup.nodes[0].var.exp, "p");
+        h.update(action::Action::Hover(Action::Down)).unwrap();
+        h.update(action::Action::Hover(Action::Collapse)).unwrap();
+        assert_eq!(h.popup.as_ref().unwrap().selected, 0);
+        h.update(action::Action::Hover(Action::Collapse)).unwrap();
+        assert_eq!(h.popup.as_ref().unwrap().nodes.len(), 1);
+
+        assert_eq!(
+            h.update(action::Action::Hover(Action::Print)).unwrap(),
+            Some(action::Action::Mode(Mode::Gdb))
+        );
+        assert_eq!(
+            sent(&mut rx),
+            vec![
+                action::Action::Gdbtty(gdbtty::Action::Command("print p".to_string())),
+                action::Action::Gdbmi(gdbmi::Action::Command("-var-delete var1".to_string())),
+            ]
+        );
+        assert!(h.popup.is_none());
+    }
+
+    #[test]
+    fn f_hover_mouse() {
+        let (mut h, mut rx) = hover();
+        let open = Action::Open(("x".to_string(), (3, 4), false));
+        assert_eq!(h.update(action::Action::Hover(open)).unwrap(), None);
+        // 不能求值时直接关闭，之后创建好的变量对象被删除
+        h.on_result(1, EvalResult::Error("No symbol \"x\"".to_string()));
+        assert!(h.popup.is_none());
+        sent(&mut rx);
+        h.on_result(2, EvalResult::Var(var("var7", "", 0)));
+        assert_eq!(
+            sent(&mut rx),
+            vec![action::Action::Gdbmi(gdbmi::Action::Command(
+                "-var-delete var7".to_string()
+            ))]
+        );
+
+        let open = Action::Open(("y".to_string(), (3, 4), false));
+        h.update(action::Action::Hover(open)).unwrap();
+        h.update(action::Action::Hover(Action::Leave)).unwrap();
+        assert!(h.popup.is_none());
+    }
+}
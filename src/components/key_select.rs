@@ -0,0 +1,315 @@
+use super::{
+    mouse_select::{self, MouseSelect, SelectMode, SelectionRangeType},
+    Component,
+};
+use crate::components::code;
+use crate::config::{Config, StyleKey, Styles};
+use crate::tool::layout::SharedLayout;
+use crate::{action, app::Mode, tool};
+use color_eyre::{eyre::Ok, Result};
+use ratatui::{layout::Rect, Frame};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Action {
+    Start((SelectionRangeType, SelectMode)),
+    SetMode(SelectMode),
+    SwitchPane,
+    Left(u16),
+    Right(u16),
+    Up(u16),
+    Down(u16),
+    LineStart,
+    LineEnd,
+    Yank,
+    Cancel,
+}
+
+/// 键盘选择的状态，坐标为屏幕上的 (row, column)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KeySelect {
+    pane: SelectionRangeType,
+    anchor: (u16, u16),
+    cursor: (u16, u16),
+    mode: SelectMode,
+}
+
+/// 用键盘在源码窗口或 gdb 窗口中选择文本，复用鼠标选择的高亮和复制流程
+#[derive(Default)]
+pub struct KeySelectComponent {
+    command_tx: Option<UnboundedSender<action::Action>>,
+    layout: SharedLayout,
+    select: Option<KeySelect>,
+    styles: Styles,
+}
+
+impl KeySelectComponent {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn pane_area(&self, pane: &SelectionRangeType) -> Rect {
+        let layouts = self.layout.borrow().layouts();
+        match pane {
+            SelectionRangeType::SrcWindow => layouts.src,
+            SelectionRangeType::GdbtTTYWindeow => layouts.gdb,
+        }
+    }
+
+    /// 源码窗口从中间行开始，gdb 窗口从最后一行开始
+    fn start_position(&self, pane: &SelectionRangeType) -> (u16, u16) {
+        let rect = self.pane_area(pane);
+        let row = match pane {
+            SelectionRangeType::SrcWindow => rect.y.saturating_add(rect.height / 2),
+            SelectionRangeType::GdbtTTYWindeow => rect.bottom().saturating_sub(1),
+        };
+        (row, rect.x)
+    }
+
+    fn start(&mut self, pane: SelectionRangeType, mode: SelectMode) {
+        let pos = self.start_position(&pane);
+        self.select = Some(KeySelect {
+            pane,
+            anchor: pos,
+            cursor: pos,
+            mode,
+        });
+        self.send_cursor();
+    }
+
+    fn move_cursor(&mut self, f: impl FnOnce((u16, u16), Rect) -> (u16, u16)) {
+        let rect = match &self.select {
+            Some(select) => self.pane_area(&select.pane),
+            None => return,
+        };
+        if let Some(select) = self.select.as_mut() {
+            let (row, col) = f(select.cursor, rect);
+            select.cursor = (
+                row.clamp(rect.y, rect.bottom().saturating_sub(1).max(rect.y)),
+                col.clamp(rect.x, rect.right().saturating_sub(1).max(rect.x)),
+            );
+        }
+        self.send_cursor();
+    }
+
+    /// 源码窗口记下光标位置，宏和求值用光标处的单词
+    fn send_cursor(&self) {
+        if let Some(select) = &self.select {
+            if select.pane == SelectionRangeType::SrcWindow {
+                self.send(action::Action::Code(code::Action::SetCursor(select.cursor)));
+            }
+        }
+    }
+
+    /// 光标所在的格子也算作选中，所以结束列向右扩展一格，最多到窗口右边界（不含）
+    fn get_mouse_select(&self) -> Option<MouseSelect> {
+        let select = self.select.as_ref()?;
+        let rect = self.pane_area(&select.pane);
+        let mut ret = MouseSelect {
+            start: select.anchor,
+            end: select.cursor,
+            mode: select.mode,
+        }
+        .legalization();
+        ret.end.1 = ret.end.1.saturating_add(1).min(rect.right());
+        Some(ret)
+    }
+
+    fn send(&self, action: action::Action) {
+        match self.command_tx.as_ref() {
+            Some(send) => tool::send_action(send, action),
+            None => error!("{}", "send key select error"),
+        }
+    }
+
+    fn clear_highlight(&self, pane: SelectionRangeType) {
+        self.send(action::Action::MouseSelect(
+            mouse_select::Action::DelectSelectionRange(pane),
+        ));
+    }
+
+    fn selection_action(&self, done: bool) -> Option<action::Action> {
+        self.get_mouse_select().map(|select| {
+            action::Action::MouseSelect(mouse_select::Action::SelectionRange((done, select)))
+        })
+    }
+}
+
+impl Component for KeySelectComponent {
+    fn register_action_handler(&mut self, tx: UnboundedSender<action::Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
+
+    fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
+        let action = match action {
+            action::Action::Mode(mode) => {
+                // 被其他按键切出可视模式时丢弃选择
+                if mode != Mode::Visual {
+                    if let Some(select) = self.select.take() {
+                        self.clear_highlight(select.pane);
+                    }
+                }
+                None
+            }
+            action::Action::KeySelect(action) => match action {
+                Action::Start((pane, mode)) => {
+                    self.start(pane, mode);
+                    if let Some(action) = self.selection_action(false) {
+                        self.send(action);
+                    }
+                    Some(action::Action::Mode(Mode::Visual))
+                }
+                Action::SetMode(mode) => {
+                    if let Some(select) = self.select.as_mut() {
+                        select.mode = mode;
+                    }
+                    self.selection_action(false)
+                }
+                Action::SwitchPane => {
+                    let (pane, mode) = match self.select.take() {
+                        Some(select) => (select.pane, select.mode),
+                        None => return Ok(None),
+                    };
+                    self.clear_highlight(pane.clone());
+                    let pane = match pane {
+                        SelectionRangeType::SrcWindow => SelectionRangeType::GdbtTTYWindeow,
+                        SelectionRangeType::GdbtTTYWindeow => SelectionRangeType::SrcWindow,
+                    };
+                    self.start(pane, mode);
+                    self.selection_action(false)
+                }
+                Action::Left(n) => {
+                    self.move_cursor(|(row, col), _| (row, col.saturating_sub(n)));
+                    self.selection_action(false)
+                }
+                Action::Right(n) => {
+                    self.move_cursor(|(row, col), _| (row, col.saturating_add(n)));
+                    self.selection_action(false)
+                }
+                Action::Up(n) => {
+                    self.move_cursor(|(row, col), _| (row.saturating_sub(n), col));
+                    self.selection_action(false)
+                }
+                Action::Down(n) => {
+                    self.move_cursor(|(row, col), _| (row.saturating_add(n), col));
+                    self.selection_action(false)
+                }
+                Action::LineStart => {
+                    self.move_cursor(|(row, _), rect| (row, rect.x));
+                    self.selection_action(false)
+                }
+                Action::LineEnd => {
+                    self.move_cursor(|(row, _), rect| (row, rect.right()));
+                    self.selection_action(false)
+                }
+                Action::Yank => {
+                    if let Some(action) = self.selection_action(true) {
+                        self.send(action);
+                    }
+                    self.select = None;
+                    Some(action::Action::Mode(Mode::Code))
+                }
+                Action::Cancel => Some(action::Action::Mode(Mode::Code)),
+            },
+            _ => None,
+        };
+        Ok(action)
+    }
+
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        if let Some(select) = &self.select {
+            let (row, col) = select.cursor;
+            let cursor = Rect::new(col, row, 1, 1).intersection(frame.area());
+            frame
+                .buffer_mut()
+                .set_style(cursor, self.styles.get(StyleKey::SelectCursor));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LayoutConfig;
+    use crate::tool::layout::LayoutService;
+
+    fn component() -> KeySelectComponent {
+        let mut c = KeySelectComponent::new();
+        let layout = LayoutService::new(&LayoutConfig::default()).shared();
+        layout.borrow_mut().set_area(Rect::new(0, 0, 80, 40));
+        c.register_layout_handler(layout).unwrap();
+        c
+    }
+
+    #[test]
+    fn f_key_select_move_clamped() {
+        let mut c = component();
+        c.start(SelectionRangeType::SrcWindow, SelectMode::Char);
+        let rect = c.pane_area(&SelectionRangeType::SrcWindow);
+        c.update(action::Action::KeySelect(Action::Left(5)))
+            .unwrap();
+        c.update(action::Action::KeySelect(Action::Up(1000)))
+            .unwrap();
+        assert_eq!(c.select.as_ref().unwrap().cursor, (rect.y, rect.x));
+        c.update(action::Action::KeySelect(Action::LineEnd))
+            .unwrap();
+        assert_eq!(
+            c.select.as_ref().unwrap().cursor,
+            (rect.y, rect.right() - 1)
+        );
+    }
+
+    #[test]
+    fn f_key_select_include_cursor() {
+        let mut c = component();
+        c.start(SelectionRangeType::GdbtTTYWindeow, SelectMode::Block);
+        c.update(action::Action::KeySelect(Action::Right(3)))
+            .unwrap();
+        c.update(action::Action::KeySelect(Action::Up(2))).unwrap();
+        let (row, col) = c.start_position(&SelectionRangeType::GdbtTTYWindeow);
+        assert_eq!(
+            c.get_mouse_select(),
+            Some(MouseSelect {
+                start: (row - 2, col),
+                end: (row, col + 4),
+                mode: SelectMode::Block,
+            })
+        );
+    }
+
+    #[test]
+    fn f_key_select_last_column() {
+        let mut c = component();
+        c.start(SelectionRangeType::SrcWindow, SelectMode::Char);
+        let rect = c.pane_area(&SelectionRangeType::SrcWindow);
+        c.update(action::Action::KeySelect(Action::LineEnd))
+            .unwrap();
+        assert_eq!(c.select.as_ref().unwrap().cursor.1, rect.right() - 1);
+        // 光标在最后一列时这一格也能选中
+        assert_eq!(c.get_mouse_select().unwrap().end.1, rect.right());
+    }
+
+    #[test]
+    fn f_key_select_switch_pane() {
+        let mut c = component();
+        c.start(SelectionRangeType::SrcWindow, SelectMode::Line);
+        c.update(action::Action::KeySelect(Action::SwitchPane))
+            .unwrap();
+        let select = c.select.as_ref().unwrap();
+        assert_eq!(select.pane, SelectionRangeType::GdbtTTYWindeow);
+        assert_eq!(select.mode, SelectMode::Line);
+    }
+}
@@ -0,0 +1,229 @@
+use super::{gdbmi, gdbtty, Component};
+use crate::config::{Config, MacroCommand, MacroConfig};
+use crate::{action, tool};
+use color_eyre::Result;
+use ratatui::{layout::Rect, Frame};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum::Display;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::{error, info};
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Action {
+    /// 源码窗口收到 `Macro` 后补上光标处的单词、文件和行号发出
+    Run((String, MacroContext)),
+}
+
+/// 替换宏命令中占位符的值，没有的为 `None`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MacroContext {
+    pub word_under_cursor: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub selection: Option<String>,
+}
+
+impl MacroContext {
+    fn get(&self, name: &str) -> Result<Option<String>, String> {
+        let value = match name {
+            "word_under_cursor" => self.word_under_cursor.clone(),
+            "file" => self.file.clone(),
+            "line" => self.line.map(|l| l.to_string()),
+            // 多行的选择放在一行中，否则会被 gdb 当作多条命令
+            "selection" => self
+                .selection
+                .as_ref()
+                .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" ")),
+            _ => return Err(format!("unknown placeholder `{{{}}}`", name)),
+        };
+        Ok(value.filter(|v| !v.is_empty()))
+    }
+}
+
+/// 替换模板中的占位符，`{{` 和 `}}` 是大括号本身
+pub fn expand(template: &str, context: &MacroContext) -> Result<String, String> {
+    let mut ans = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(id) = rest.find(['{', '}']) {
+        ans.push_str(&rest[..id]);
+        let tail = &rest[id..];
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            ans.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+        let Some(end) = tail.strip_prefix('{').and_then(|t| t.find('}')) else {
+            return Err(format!("unmatched `{}` in `{}`", &tail[..1], template));
+        };
+        let name = &tail[1..end + 1];
+        match context.get(name)? {
+            Some(value) => ans.push_str(&value),
+            None => return Err(format!("no {} for `{}`", name.replace('_', " "), template)),
+        }
+        rest = &tail[end + 2..];
+    }
+    ans.push_str(rest);
+    Ok(ans)
+}
+
+/// 检查模板中的占位符都认识，加载配置时调用
+pub fn check_template(template: &str) -> Result<(), String> {
+    let context = MacroContext {
+        word_under_cursor: Some("x".to_string()),
+        file: Some("x".to_string()),
+        line: Some(1),
+        selection: Some("x".to_string()),
+    };
+    expand(template, &context).map(|_| ())
+}
+
+/// 帮助和命令面板中显示的名字，配置了说明时用说明
+pub fn title(macros: &HashMap<String, MacroConfig>, name: &str) -> String {
+    match macros.get(name) {
+        Some(m) if !m.description.is_empty() => m.description.clone(),
+        _ => action::Action::Macro(name.to_string()).description(),
+    }
+}
+
+/// 把宏展开成要执行的 action，有一条命令缺少占位符的值时一条都不执行
+fn macro_actions(
+    macros: &HashMap<String, MacroConfig>,
+    name: &str,
+    context: &MacroContext,
+) -> Result<Vec<action::Action>, String> {
+    let Some(m) = macros.get(name) else {
+        return Err(format!("No macro named {}", name));
+    };
+    m.commands
+        .iter()
+        .map(|command| match command {
+            MacroCommand::Console(cmd) => {
+                expand(cmd, context).map(|cmd| action::Action::Gdbtty(gdbtty::Action::Command(cmd)))
+            }
+            MacroCommand::Mi { mi } => {
+                expand(mi, context).map(|cmd| action::Action::Gdbmi(gdbmi::Action::Command(cmd)))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Macro {}: {}", name, e))
+}
+
+/// 执行配置中的宏，`{selection}` 是最近一次选择复制的文本
+#[derive(Default)]
+pub struct Macros {
+    command_tx: Option<UnboundedSender<action::Action>>,
+    macros: HashMap<String, MacroConfig>,
+    selection: Option<String>,
+}
+
+impl Macros {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn send(&self, action: action::Action) {
+        match self.command_tx.as_ref() {
+            Some(send) => tool::send_action(send, action),
+            None => error!("{}", "send macro error"),
+        }
+    }
+}
+
+impl Component for Macros {
+    fn register_action_handler(&mut self, tx: UnboundedSender<action::Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.macros = config.macros;
+        Ok(())
+    }
+    fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
+        let ret = match action {
+            action::Action::CopyStr(text) => {
+                self.selection = Some(text);
+                None
+            }
+            action::Action::Macros(Action::Run((name, mut context))) => {
+                context.selection = self.selection.clone();
+                match macro_actions(&self.macros, &name, &context) {
+                    Ok(actions) => {
+                        info!("run macro {} with {:?}", &name, &context);
+                        actions.into_iter().for_each(|a| self.send(a));
+                        None
+                    }
+                    Err(e) => Some(action::Action::Notify(e)),
+                }
+            }
+            _ => None,
+        };
+        Ok(ret)
+    }
+    fn draw(&mut self, _frame: &mut Frame, _area: Rect) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> MacroContext {
+        MacroContext {
+            word_under_cursor: Some("this".to_string()),
+            file: Some("/src/a.cpp".to_string()),
+            line: Some(42),
+            selection: Some("a +\n  b".to_string()),
+        }
+    }
+
+    #[test]
+    fn f_expand() {
+        let c = context();
+        assert_eq!(expand("p *{word_under_cursor}", &c).unwrap(), "p *this");
+        assert_eq!(
+            expand("-break-insert {file}:{line}", &c).unwrap(),
+            "-break-insert /src/a.cpp:42"
+        );
+        assert_eq!(expand("p {selection}", &c).unwrap(), "p a + b");
+        assert_eq!(expand("p {{x}} {line}", &c).unwrap(), "p {x} 42");
+        assert!(expand("p {nope}", &c).is_err());
+        assert!(expand("p {line", &c).is_err());
+        assert!(expand("p }", &c).is_err());
+        let empty = MacroContext::default();
+        assert_eq!(
+            expand("p {word_under_cursor}", &empty),
+            Err("no word under cursor for `p {word_under_cursor}`".to_string())
+        );
+        assert!(check_template("info frame").is_ok());
+        assert!(check_template("p {word}").is_err());
+    }
+
+    #[test]
+    fn f_macro_actions() {
+        let macros = HashMap::from([(
+            "this".to_string(),
+            MacroConfig {
+                description: String::new(),
+                commands: vec![
+                    MacroCommand::Console("p *{word_under_cursor}".to_string()),
+                    MacroCommand::Mi {
+                        mi: "-stack-info-frame".to_string(),
+                    },
+                ],
+            },
+        )]);
+        assert_eq!(
+            macro_actions(&macros, "this", &context()),
+            Ok(vec![
+                action::Action::Gdbtty(gdbtty::Action::Command("p *this".to_string())),
+                action::Action::Gdbmi(gdbmi::Action::Command("-stack-info-frame".to_string())),
+            ])
+        );
+        // 缺少值时一条都不执行
+        assert!(macro_actions(&macros, "this", &MacroContext::default()).is_err());
+        assert!(macro_actions(&macros, "nope", &context()).is_err());
+        assert_eq!(title(&macros, "this"), "Macro: this");
+    }
+}
@@ -1,14 +1,10 @@
 use super::Component;
 use crate::action;
+use crate::config::{Config, StyleKey, Styles};
+use crate::tool::layout::SharedLayout;
 use color_eyre::eyre::Ok;
 use crossterm::event::MouseButton;
-use ratatui::{
-    layout::Rect,
-    style::{Color, Stylize},
-    text::Text,
-    widgets::Paragraph,
-    Frame,
-};
+use ratatui::{layout::Rect, text::Text, widgets::Paragraph, Frame};
 use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, hash::Hash};
 use strum::Display;
@@ -35,16 +31,33 @@ pub struct SelectionRange {
     pub end_column: usize,
 }
 
+/// 选择的形状：按字符流、整行或矩形块
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Display, Serialize, Deserialize)]
+pub enum SelectMode {
+    #[default]
+    Char,
+    Line,
+    Block,
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Default, Serialize, Deserialize)]
 pub struct MouseSelect {
     pub start: (u16, u16),
     pub end: (u16, u16),
+    #[serde(default)]
+    pub mode: SelectMode,
 }
 
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct MouseSelectComponent {
     select_range_now: Option<MouseSelect>,
     select_ranges: HashMap<SelectionRangeType, Vec<SelectionRange>>,
+    styles: Styles,
+    layout: SharedLayout,
+    /// 在两个窗口的分界上按下鼠标后拖动改变分割比例，而不是选择文本
+    drag_divider: bool,
+    /// 开始拖动时的分割比例，松开时变了才保存
+    drag_from: u16,
 }
 
 pub trait TextSelection {
@@ -64,6 +77,34 @@ impl MouseSelectComponent {
         MouseSelectComponent {
             select_range_now: None,
             select_ranges: HashMap::new(),
+            styles: Styles::default(),
+            layout: SharedLayout::default(),
+            drag_divider: false,
+            drag_from: 0,
+        }
+    }
+
+    fn handle_divider_drag(
+        &mut self,
+        mouse: crossterm::event::MouseEvent,
+    ) -> Option<action::Action> {
+        let pos = (mouse.row, mouse.column);
+        match mouse.kind {
+            crossterm::event::MouseEventKind::Drag(MouseButton::Left) => {
+                let layout = self.layout.borrow();
+                match layout.split_at(pos) {
+                    Some(split) if split != layout.split() => {
+                        Some(action::Action::DragSplit(split))
+                    }
+                    _ => None,
+                }
+            }
+            crossterm::event::MouseEventKind::Up(MouseButton::Left) => {
+                self.drag_divider = false;
+                let split = self.layout.borrow().split();
+                (split != self.drag_from).then_some(action::Action::SetSplit(split))
+            }
+            _ => None,
         }
     }
 
@@ -72,7 +113,7 @@ impl MouseSelectComponent {
             let select_len = s.end_column.saturating_sub(s.start_column);
             // let text = Text::from_iter(Line::from_iter(std::iter::repeat_n("", select_len)));
             let text = Text::from("");
-            let paragraph = Paragraph::new(text).bg(Color::Rgb(255, 204, 153));
+            let paragraph = Paragraph::new(text).style(self.styles.get(StyleKey::Selection));
             let area = Rect::new(
                 s.start_column as u16,
                 s.line_number as u16,
@@ -94,6 +135,7 @@ impl MouseSelectComponent {
                 self.select_range_now = Some(MouseSelect {
                     start: pos,
                     end: pos,
+                    mode: SelectMode::Char,
                 });
                 self.select_range_now.clone().and_then(|s| Some((false, s)))
             }
@@ -129,20 +171,35 @@ impl MouseSelectComponent {
 }
 impl MouseSelect {
     pub fn legalization(&self) -> MouseSelect {
-        match self.start < self.end {
-            true => MouseSelect {
+        match (self.mode, self.start < self.end) {
+            (SelectMode::Block, _) => MouseSelect {
+                start: (self.start.0.min(self.end.0), self.start.1.min(self.end.1)),
+                end: (self.start.0.max(self.end.0), self.start.1.max(self.end.1)),
+                mode: self.mode,
+            },
+            (_, true) => MouseSelect {
                 start: self.start,
                 end: self.end,
+                mode: self.mode,
             },
-            false => MouseSelect {
+            (_, false) => MouseSelect {
                 start: self.end,
                 end: self.start,
+                mode: self.mode,
             },
         }
     }
 }
 
 impl Component for MouseSelectComponent {
+    fn register_config_handler(&mut self, config: Config) -> color_eyre::eyre::Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> color_eyre::eyre::Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
     fn draw(&mut self, frame: &mut Frame, _area: Rect) -> color_eyre::eyre::Result<()> {
         let range = self
             .select_ranges
@@ -161,10 +218,19 @@ impl Component for MouseSelectComponent {
             crossterm::event::MouseEventKind::Up(MouseButton::Left)
             | crossterm::event::MouseEventKind::Down(MouseButton::Left)
             | crossterm::event::MouseEventKind::Drag(MouseButton::Left) => {
-                // 处理鼠标选择事件
-                match self.handle_selection(mouse.clone()) {
-                    Some(v) => Some(action::Action::MouseSelect(Action::SelectionRange(v))),
-                    _ => None,
+                if let crossterm::event::MouseEventKind::Down(MouseButton::Left) = mouse.kind {
+                    let pos = (mouse.row, mouse.column);
+                    let layout = self.layout.borrow();
+                    self.drag_divider = layout.is_divider(pos);
+                    self.drag_from = layout.split();
+                }
+                match self.drag_divider {
+                    true => self.handle_divider_drag(mouse),
+                    // 处理鼠标选择事件
+                    false => match self.handle_selection(mouse.clone()) {
+                        Some(v) => Some(action::Action::MouseSelect(Action::SelectionRange(v))),
+                        _ => None,
+                    },
                 }
             }
             _ => None,
@@ -190,3 +256,69 @@ impl Component for MouseSelectComponent {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_legalization_char() {
+        let select = MouseSelect {
+            start: (5, 2),
+            end: (3, 8),
+            mode: SelectMode::Char,
+        };
+        assert_eq!(select.legalization().start, (3, 8));
+        assert_eq!(select.legalization().end, (5, 2));
+    }
+
+    #[test]
+    fn f_legalization_block() {
+        let select = MouseSelect {
+            start: (5, 2),
+            end: (3, 8),
+            mode: SelectMode::Block,
+        };
+        assert_eq!(select.legalization().start, (3, 2));
+        assert_eq!(select.legalization().end, (5, 8));
+    }
+
+    #[test]
+    fn f_divider_drag_saves_on_release() {
+        use crate::config::LayoutConfig;
+        use crate::tool::layout::LayoutService;
+        use crossterm::event::{KeyModifiers, MouseEvent, MouseEventKind};
+        let mut layout = LayoutService::new(&LayoutConfig {
+            split: 25,
+            preset: "stacked".to_string(),
+            ..LayoutConfig::default()
+        });
+        layout.set_area(Rect::new(0, 0, 100, 40));
+        let layout = layout.shared();
+        let mut select = MouseSelectComponent::new();
+        select.register_layout_handler(layout.clone()).unwrap();
+        let mut mouse = |kind, row| {
+            let event = MouseEvent {
+                kind,
+                column: 7,
+                row,
+                modifiers: KeyModifiers::NONE,
+            };
+            select.handle_mouse_event(event).unwrap()
+        };
+        let left = MouseButton::Left;
+        assert_eq!(mouse(MouseEventKind::Down(left), 9), None);
+        assert_eq!(
+            mouse(MouseEventKind::Drag(left), 29),
+            Some(action::Action::DragSplit(75))
+        );
+        layout.borrow_mut().set_split(75);
+        assert_eq!(
+            mouse(MouseEventKind::Up(left), 29),
+            Some(action::Action::SetSplit(75))
+        );
+        // 只按下松开没有拖动，不保存
+        assert_eq!(mouse(MouseEventKind::Down(left), 29), None);
+        assert_eq!(mouse(MouseEventKind::Up(left), 29), None);
+    }
+}
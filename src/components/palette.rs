@@ -0,0 +1,456 @@
+use super::{
+    home, key_select, macros,
+    mouse_select::{SelectMode, SelectionRangeType},
+    transcript, Component,
+};
+use crate::config::{key_sequence_to_string, Config, KeyBindings, MacroConfig, StyleKey, Styles};
+use crate::{action, app::Mode, components::code, components::gdbtty, tool};
+use color_eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    text::{Line, Span, Text},
+    widgets::{Block, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use strum::Display;
+use tokio::sync::mpsc::UnboundedSender;
+use tracing::error;
+
+/// 以它开头的输入作为 gdb 命令发到控制台
+const GDB_PREFIX: char = ':';
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Action {
+    /// 打开面板，参数是初始输入，`":"` 直接输入 gdb 命令
+    Open(String),
+    Up,
+    Down,
+    Accept,
+    Cancel,
+}
+
+/// 面板中的一项：说明、执行的 action 和当前绑定的按键
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    title: String,
+    action: action::Action,
+    keys: Vec<String>,
+}
+
+/// 可以模糊搜索的命令面板，列出能触发的 action 和它们的按键
+#[derive(Default)]
+pub struct Palette {
+    command_tx: Option<UnboundedSender<action::Action>>,
+    keybindings: KeyBindings,
+    styles: Styles,
+    presets: Vec<String>,
+    macros: HashMap<String, MacroConfig>,
+    mode: Mode,
+    /// 打开面板前的模式，关闭后回到它
+    back: Mode,
+    input: String,
+    entries: Vec<Entry>,
+    /// 匹配输入的项在 `entries` 中的下标，按匹配程度排序
+    matches: Vec<usize>,
+    selected: usize,
+}
+
+impl Palette {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&mut self, input: String) {
+        self.back = self.mode;
+        self.input = input;
+        self.entries = entries(&self.keybindings, self.back, &self.presets, &self.macros);
+        self.refresh();
+    }
+
+    fn refresh(&mut self) {
+        let mut scored = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(id, e)| fuzzy_score(&self.input, &e.title).map(|s| (s, id)))
+            .collect::<Vec<_>>();
+        // 分数相同时保持原来的顺序
+        scored.sort_by_key(|(score, id)| (-score, *id));
+        self.matches = scored.into_iter().map(|(_, id)| id).collect();
+        self.selected = 0;
+    }
+
+    fn gdb_command(&self) -> Option<&str> {
+        self.input.strip_prefix(GDB_PREFIX).map(str::trim)
+    }
+
+    /// 回车时执行的 action
+    fn chosen(&self) -> Option<action::Action> {
+        match self.gdb_command() {
+            Some("") => None,
+            Some(cmd) => Some(action::Action::Gdbtty(gdbtty::Action::Command(
+                cmd.to_string(),
+            ))),
+            None => self
+                .matches
+                .get(self.selected)
+                .map(|id| self.entries[*id].action.clone()),
+        }
+    }
+
+    fn send(&self, action: action::Action) {
+        match self.command_tx.as_ref() {
+            Some(send) => tool::send_action(send, action),
+            None => error!("{}", "send palette error"),
+        }
+    }
+
+    fn entry_line(&self, entry: &Entry, width: u16, selected: bool) -> Line<'_> {
+        let keys = entry.keys.join(" ");
+        let pad = (width as usize)
+            .saturating_sub(entry.title.chars().count())
+            .saturating_sub(keys.chars().count())
+            .max(1);
+        let line = Line::from(vec![
+            Span::from(entry.title.clone()),
+            Span::from(" ".repeat(pad)),
+            Span::from(keys).style(self.styles.get(StyleKey::Hint)),
+        ]);
+        match selected {
+            true => line.style(self.styles.get(StyleKey::ListSelected)),
+            false => line,
+        }
+    }
+
+    fn draw_palette(&self, frame: &mut Frame) {
+        let [area] = Layout::horizontal([Constraint::Max(80)])
+            .flex(Flex::Center)
+            .areas(frame.area());
+        let [area] = Layout::vertical([Constraint::Percentage(60)])
+            .flex(Flex::Center)
+            .areas(area);
+        let block = Block::bordered()
+            .title(" Command Palette ")
+            .title_bottom(" <Enter> Run <:cmd> GDB command <Esc> Cancel ");
+        let inner = block.inner(area);
+        let input =
+            Line::from(format!("> {}", self.input)).style(self.styles.get(StyleKey::PromptInput));
+        let lines = match self.gdb_command() {
+            Some(_) => vec![Line::from("Send the command to the gdb console")],
+            None => {
+                // 选中的项保持在可见范围内
+                let height = inner.height.saturating_sub(1) as usize;
+                let skip = self.selected.saturating_add(1).saturating_sub(height);
+                self.matches
+                    .iter()
+                    .enumerate()
+                    .skip(skip)
+                    .take(height)
+                    .map(|(i, id)| {
+                        self.entry_line(&self.entries[*id], inner.width, i == self.selected)
+                    })
+                    .collect()
+            }
+        };
+        let text = Text::from_iter(std::iter::once(input).chain(lines));
+        frame.render_widget(Clear, area);
+        frame.render_widget(Paragraph::new(text).block(block), area);
+    }
+}
+
+/// 面板中固定列出的 action，没有绑定按键也能从面板执行
+fn catalog(presets: &[String], macros: &HashMap<String, MacroConfig>) -> Vec<action::Action> {
+    use action::Action as A;
+    let select = |pane| A::KeySelect(key_select::Action::Start((pane, SelectMode::Char)));
+    let fixed = [
+        A::Quit,
+        A::Suspend,
+        A::ClearScreen,
+        A::ReloadConfig,
+        A::Help,
+        A::Mode(Mode::Code),
+        A::Mode(Mode::Gdb),
+        A::FocusNext,
+        A::ToggleZoom,
+        A::SwapHV,
+        A::NextLayout,
+        A::ResizeSplit(5),
+        A::ResizeSplit(-5),
+        A::Home(home::Action::SearchStart),
+        A::Code(code::Action::LocateStart),
+        A::Code(code::Action::Evaluate),
+        select(SelectionRangeType::SrcWindow),
+        select(SelectionRangeType::GdbtTTYWindeow),
+        A::Transcript(transcript::Action::Save),
+    ];
+    let mut names = macros.keys().cloned().collect::<Vec<_>>();
+    names.sort();
+    fixed
+        .into_iter()
+        .chain(presets.iter().map(|name| A::SetLayout(name.clone())))
+        .chain(names.into_iter().map(A::Macro))
+        .collect()
+}
+
+/// 目录中的 action 加上 `mode` 中其它绑定了按键的 action，每项带上 `mode` 中绑定它的按键
+fn entries(
+    keybindings: &KeyBindings,
+    mode: Mode,
+    presets: &[String],
+    macros: &HashMap<String, MacroConfig>,
+) -> Vec<Entry> {
+    let mut bound = keybindings
+        .get(&mode)
+        .into_iter()
+        .flat_map(|keymap| keymap.iter())
+        // 面板、帮助和求值弹窗自己的按键只在它们打开时有用
+        .filter(|(_, a)| {
+            !matches!(
+                a,
+                action::Action::Palette(_)
+                    | action::Action::HelpOverlay(_)
+                    | action::Action::Hover(_)
+            )
+        })
+        .map(|(keys, a)| (key_sequence_to_string(keys), a.clone()))
+        .collect::<Vec<_>>();
+    bound.sort_by(|a, b| a.0.cmp(&b.0));
+    let title = |action: &action::Action| match action {
+        action::Action::Macro(name) => macros::title(macros, name),
+        _ => action.description(),
+    };
+    let mut entries = catalog(presets, macros)
+        .into_iter()
+        .map(|action| Entry {
+            title: title(&action),
+            action,
+            keys: vec![],
+        })
+        .collect::<Vec<_>>();
+    for (keys, action) in bound {
+        let entry = match entries.iter().position(|e| e.action == action) {
+            Some(id) => &mut entries[id],
+            None => {
+                entries.push(Entry {
+                    title: title(&action),
+                    action,
+                    keys: vec![],
+                });
+                entries.last_mut().unwrap()
+            }
+        };
+        if !entry.keys.contains(&keys) {
+            entry.keys.push(keys);
+        }
+    }
+    entries
+}
+
+/// 不区分大小写的子序列匹配，连续匹配和匹配在单词开头得分更高，不匹配时返回 None
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let text = text.chars().collect::<Vec<_>>();
+    let mut score = 0_i64;
+    let mut pos = 0_usize;
+    let mut last: Option<usize> = None;
+    for p in pattern.chars().filter(|c| !c.is_whitespace()) {
+        let p = p.to_lowercase().collect::<String>();
+        let found = (pos..text.len()).find(|i| text[*i].to_lowercase().collect::<String>() == p)?;
+        let word_start = found == 0 || !text[found - 1].is_alphanumeric();
+        score += match (last.is_some_and(|l| l + 1 == found), word_start) {
+            (true, _) => 8,
+            (false, true) => 6,
+            (false, false) => 1,
+        };
+        score -= found.saturating_sub(pos).min(5) as i64;
+        last = Some(found);
+        pos = found + 1;
+    }
+    Some(score)
+}
+
+impl Component for Palette {
+    fn register_action_handler(&mut self, tx: UnboundedSender<action::Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.keybindings = config.keybindings;
+        self.styles = config.styles;
+        let mut presets = config.layout.presets.into_keys().collect::<Vec<_>>();
+        presets.sort();
+        self.presets = presets;
+        self.macros = config.macros;
+        Ok(())
+    }
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<Option<action::Action>> {
+        if self.mode != Mode::Palette {
+            return Ok(None);
+        }
+        match key.code {
+            KeyCode::Char(c) if (key.modifiers - KeyModifiers::SHIFT).is_empty() => {
+                self.input.push(c);
+                self.refresh();
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+                self.refresh();
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+    fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
+        let ret = match action {
+            action::Action::Mode(mode) => {
+                self.mode = mode;
+                None
+            }
+            action::Action::Palette(Action::Open(input)) => {
+                self.open(input);
+                Some(action::Action::Mode(Mode::Palette))
+            }
+            action::Action::Palette(Action::Up) => {
+                self.selected = self.selected.saturating_sub(1);
+                None
+            }
+            action::Action::Palette(Action::Down) => {
+                self.selected = self
+                    .selected
+                    .saturating_add(1)
+                    .min(self.matches.len().saturating_sub(1));
+                None
+            }
+            action::Action::Palette(Action::Accept) => {
+                let chosen = self.chosen();
+                self.send(action::Action::Mode(self.back));
+                // 先回到原来的模式，选中的 action 可能再切换模式
+                chosen.inspect(|a| self.send(a.clone()));
+                None
+            }
+            action::Action::Palette(Action::Cancel) => Some(action::Action::Mode(self.back)),
+            _ => None,
+        };
+        Ok(ret)
+    }
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        if self.mode == Mode::Palette {
+            self.draw_palette(frame);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_key_sequence;
+    use std::collections::HashMap;
+
+    #[test]
+    fn f_fuzzy_score() {
+        assert!(fuzzy_score("", "Quit").is_some());
+        assert!(fuzzy_score("zm", "Zoom focused pane").is_some());
+        assert!(fuzzy_score("ZOOM", "Zoom focused pane").is_some());
+        assert!(fuzzy_score("xyz", "Zoom focused pane").is_none());
+        assert!(fuzzy_score("pz", "Zoom focused pane").is_none());
+        // 连续匹配和单词开头优先
+        assert!(fuzzy_score("next", "Next layout") > fuzzy_score("next", "Focus next pane"));
+        assert!(fuzzy_score("fnp", "Focus next pane") > fuzzy_score("fnp", "Grow source pane"));
+    }
+
+    fn keybindings() -> KeyBindings {
+        let mut code = HashMap::new();
+        code.insert(
+            parse_key_sequence("<Ctrl-l>").unwrap(),
+            action::Action::NextLayout,
+        );
+        code.insert(
+            parse_key_sequence("<Ctrl-q>").unwrap(),
+            action::Action::Quit,
+        );
+        code.insert(
+            parse_key_sequence("<Ctrl-p>").unwrap(),
+            action::Action::Palette(Action::Open(String::new())),
+        );
+        let mut gdb = HashMap::new();
+        gdb.insert(
+            parse_key_sequence("<Ctrl-q>").unwrap(),
+            action::Action::Quit,
+        );
+        gdb.insert(
+            parse_key_sequence("<Ctrl-d>").unwrap(),
+            action::Action::Quit,
+        );
+        gdb.insert(
+            parse_key_sequence("<Ctrl-y>").unwrap(),
+            action::Action::Code(code::Action::Up(1)),
+        );
+        KeyBindings(HashMap::from([(Mode::Code, code), (Mode::Gdb, gdb)]))
+    }
+
+    #[test]
+    fn f_entries() {
+        let macros = HashMap::from([(
+            "this".to_string(),
+            MacroConfig {
+                description: "Print *this".to_string(),
+                commands: vec![],
+            },
+        )]);
+        let entries = entries(&keybindings(), Mode::Gdb, &["tabs".to_string()], &macros);
+        let find = |a: &action::Action| entries.iter().find(|e| e.action == *a).unwrap();
+        assert_eq!(
+            find(&action::Action::Quit).keys,
+            vec!["<ctrl-d>", "<ctrl-q>"]
+        );
+        // 只列出打开面板前所在模式的按键
+        assert!(find(&action::Action::NextLayout).keys.is_empty());
+        assert!(find(&action::Action::ToggleZoom).keys.is_empty());
+        assert_eq!(
+            find(&action::Action::SetLayout("tabs".to_string())).title,
+            "Layout: tabs"
+        );
+        assert_eq!(
+            find(&action::Action::Macro("this".to_string())).title,
+            "Print *this"
+        );
+        // 没在目录中的按键绑定也列出来
+        assert_eq!(
+            find(&action::Action::Code(code::Action::Up(1))).keys,
+            vec!["<ctrl-y>"]
+        );
+        assert!(entries
+            .iter()
+            .all(|e| !matches!(e.action, action::Action::Palette(_))));
+
+        let entries = super::entries(&keybindings(), Mode::Code, &[], &HashMap::new());
+        let find = |a: &action::Action| entries.iter().find(|e| e.action == *a).unwrap();
+        assert_eq!(find(&action::Action::Quit).keys, vec!["<ctrl-q>"]);
+        assert_eq!(find(&action::Action::NextLayout).keys, vec!["<ctrl-l>"]);
+        assert!(entries
+            .iter()
+            .all(|e| e.action != action::Action::Code(code::Action::Up(1))));
+    }
+
+    #[test]
+    fn f_palette_choose() {
+        let mut palette = Palette::new();
+        palette.keybindings = keybindings();
+        palette.open("zoom".to_string());
+        assert_eq!(palette.chosen(), Some(action::Action::ToggleZoom));
+        palette.open(": info frame ".to_string());
+        assert_eq!(
+            palette.chosen(),
+            Some(action::Action::Gdbtty(gdbtty::Action::Command(
+                "info frame".to_string()
+            )))
+        );
+        palette.open(":".to_string());
+        assert_eq!(palette.chosen(), None);
+        palette.open("no such thing".to_string());
+        assert_eq!(palette.chosen(), None);
+    }
+}
@@ -1,12 +1,13 @@
 use super::Component;
 use crate::action;
 use crate::components::code;
+use crate::config::{Config, ConfigIssue, StyleKey, Styles};
 use crate::tool;
+use crate::tool::layout::SharedLayout;
 use color_eyre::Result;
 use ratatui::text::Line;
 use ratatui::{
     layout::{Constraint, Layout, Rect},
-    style::{Color, Style, Stylize},
     widgets::Paragraph,
     Frame,
 };
@@ -15,7 +16,9 @@ use tui_widgets::big_text::{BigText, PixelSize};
 #[derive(Debug, Clone, PartialEq)]
 pub struct StartPage {
     is_start: bool,
-    is_horizontal: bool,
+    layout: SharedLayout,
+    styles: Styles,
+    issues: Vec<ConfigIssue>,
 }
 
 impl Default for StartPage {
@@ -28,7 +31,9 @@ impl StartPage {
     pub fn new() -> Self {
         Self {
             is_start: true,
-            is_horizontal: false,
+            layout: SharedLayout::default(),
+            styles: Styles::default(),
+            issues: vec![],
         }
     }
     fn is_start(&self) -> bool {
@@ -38,13 +43,13 @@ impl StartPage {
         self.is_start = val;
     }
 
-    fn draw_all(&mut self, frame: &mut Frame, area: Rect) {
+    fn draw_all(&mut self, frame: &mut Frame) {
         if self.is_start() {
             let tool::Layouts {
                 src: area,
                 src_status: area_status,
                 ..
-            } = tool::Layouts::from((area, self.is_horizontal));
+            } = self.layout.borrow().layouts();
             let half = area.height.saturating_sub(12).div_euclid(2);
             let [_, area, area_version] = Layout::vertical([
                 Constraint::Max(half),
@@ -60,15 +65,14 @@ impl StartPage {
     fn draw_status(&self, frame: &mut Frame, area_status: Rect) {
         let title = "*";
         let paragraph_status = Paragraph::new(title)
-            .fg(Color::Black)
-            .bg(Color::Gray)
+            .style(self.styles.get(StyleKey::StatusBar))
             .right_aligned();
         frame.render_widget(paragraph_status, area_status);
     }
     fn draw_page(&self, frame: &mut Frame, area: Rect) {
         let big_text = BigText::builder()
             .pixel_size(PixelSize::Full)
-            .style(Style::new().blue())
+            .style(self.styles.get(StyleKey::Title))
             .lines(vec!["rgdb".into()])
             .centered()
             .build();
@@ -76,17 +80,44 @@ impl StartPage {
     }
     fn draw_version(&self, frame: &mut Frame, area: Rect) {
         let version_str = format!("version {}", env!("CARGO_PKG_VERSION"));
-        let lines = vec![
+        let mut lines = vec![
             Line::from("No Code No Bug"),
             Line::from("a tui debugger"),
             Line::from(version_str),
         ];
-        let paragraph_version = Paragraph::new(lines).fg(Color::Blue).centered();
+        if !self.issues.is_empty() {
+            let notify = self.styles.get(StyleKey::Notify);
+            lines.push(Line::default());
+            lines.push(
+                Line::from(format!(
+                    "{} problems in the config, the defaults are used instead:",
+                    self.issues.len()
+                ))
+                .style(notify),
+            );
+            lines.extend(
+                self.issues
+                    .iter()
+                    .map(|issue| Line::from(issue.to_string()).style(notify)),
+            );
+        }
+        let paragraph_version = Paragraph::new(lines)
+            .style(self.styles.get(StyleKey::Title))
+            .centered();
         frame.render_widget(paragraph_version, area);
     }
 }
 
 impl Component for StartPage {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        self.issues = config.issues;
+        Ok(())
+    }
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
     fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
         if self.is_start() {
             match action {
@@ -94,17 +125,14 @@ impl Component for StartPage {
                 | action::Action::Code(code::Action::AsmFileEnd) => {
                     self.set_is_start(false);
                 }
-                action::Action::SwapHV => {
-                    self.is_horizontal = !self.is_horizontal;
-                }
                 _ => {}
             };
         }
         Ok(None)
     }
 
-    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
-        self.draw_all(frame, area);
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        self.draw_all(frame);
         Ok(())
     }
 }
@@ -1,22 +1,28 @@
 use super::Component;
 use crate::action;
 use crate::app::Mode;
+use crate::config::{Config, StyleKey, Styles};
 use crate::tool;
+use crate::tool::keys::PendingHint;
+use crate::tool::layout::SharedLayout;
 use color_eyre::Result;
 use ratatui::prelude::*;
 use ratatui::text::Line;
-use ratatui::{
-    layout::Rect,
-    style::{Color, Stylize},
-    widgets::Paragraph,
-    Frame,
-};
+use ratatui::{layout::Rect, widgets::Paragraph, Frame};
+use std::time::{Duration, Instant};
+
+/// 通知在状态栏上停留的时间
+const NOTIFY_DURATION: Duration = Duration::from_secs(3);
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StatusBar {
     is_show: bool,
-    is_horizontal: bool,
+    layout: SharedLayout,
     mode: Mode,
+    notify: Option<(String, Instant)>,
+    /// 输入了一部分的按键序列，显示时代替提示
+    pending: PendingHint,
+    styles: Styles,
 }
 
 impl Default for StatusBar {
@@ -29,8 +35,11 @@ impl StatusBar {
     pub fn new() -> Self {
         Self {
             is_show: true,
-            is_horizontal: false,
+            layout: SharedLayout::default(),
             mode: Mode::default(),
+            notify: None,
+            pending: PendingHint::default(),
+            styles: Styles::default(),
         }
     }
     pub fn set_mode(&mut self, mode: Mode) {
@@ -43,69 +52,159 @@ impl StatusBar {
     //     self.is_show = val;
     // }
     fn hit_text(&self) -> Vec<Span<'_>> {
+        if !self.pending.is_empty() {
+            return self.pending_text();
+        }
         let hits = match self.mode {
-            Mode::Gdb => vec!["<Ctrl-q> Exit", "<Esc> CODE"],
+            Mode::Gdb => vec![
+                "<Ctrl-q> Exit",
+                "<F11> Zoom",
+                "<F2> Palette",
+                "<F1> Help",
+                "<Esc> CODE",
+            ],
             Mode::Code => vec![
-                "<←↓↑→> Scroll Code",
+                "<hjkl/←↓↑→> Scroll Code",
+                "<Space> More",
                 "<Ctrl-w> Swap",
+                "<Ctrl-l> Layout",
+                "<z> Zoom",
+                "<Ctrl-p> Palette",
+                "<?> Help",
+                "<Ctrl-f> Search GDB",
+                "<v> Select",
                 "<Ctrl-q> Exit",
                 "<Esc> GDB",
             ],
+            Mode::Search => vec![
+                "<Enter/↓> Next",
+                "<↑> Prev",
+                "<Ctrl-y> Copy Line",
+                "<Esc> GDB",
+            ],
+            Mode::Visual => vec![
+                "<hjkl> Move",
+                "<v/V/Ctrl-v> Char/Line/Block",
+                "<Tab> Switch Pane",
+                "<y> Yank",
+                "<Esc> Cancel",
+            ],
+            Mode::Locate => vec![
+                "<Tab> Complete",
+                "<↑↓> Select",
+                "<Enter> Open",
+                "<Ctrl-s> Remember",
+                "<Esc> Cancel",
+            ],
+            Mode::Palette => vec!["<↑↓> Select", "<Enter> Run", "<Esc> Cancel"],
+            Mode::Help => vec!["<↑↓> Scroll", "<Esc> Close"],
+            Mode::Hover => vec![
+                "<↑↓> Select",
+                "<Enter/→> Expand",
+                "<←> Collapse",
+                "<w> Watch",
+                "<p> Print",
+                "<Esc> Close",
+            ],
         };
         hits.into_iter()
-            .map(|hit| {
-                Span::from(hit)
-                    .bg(Color::Rgb(160, 160, 160))
-                    .fg(Color::Black)
-            })
+            .map(|hit| Span::from(hit).style(self.styles.get(StyleKey::Hint)))
             .collect::<Vec<_>>()
     }
 
+    /// 已经输入的按键和接着可以按的键
+    fn pending_text(&self) -> Vec<Span<'_>> {
+        let typed = Span::from(self.pending.typed.as_str()).style(self.styles.get(StyleKey::Mode));
+        let next = self.pending.next.iter().map(|(keys, description)| {
+            Span::from(format!("{} {}", keys, description)).style(self.styles.get(StyleKey::Hint))
+        });
+        std::iter::once(typed).chain(next).collect()
+    }
+
     fn mode_text(&self) -> Span<'_> {
         let mode_name = match self.mode {
             Mode::Gdb => "GDB",
             Mode::Code => "CODE",
+            Mode::Search => "SEARCH",
+            Mode::Visual => "VISUAL",
+            Mode::Locate => "LOCATE",
+            Mode::Palette => "PALETTE",
+            Mode::Help => "HELP",
+            Mode::Hover => "HOVER",
+        };
+        let text = match self.layout.borrow().zoomed() {
+            true => format!("{} ZOOM", mode_name),
+            false => mode_name.to_string(),
         };
-        Span::from(mode_name).fg(Color::Gray).bg(Color::Black)
+        Span::from(text).style(self.styles.get(StyleKey::Mode))
     }
 
-    fn draw_all(&mut self, frame: &mut Frame, area: Rect) {
+    fn draw_all(&mut self, frame: &mut Frame) {
         if self.is_show() {
-            let tool::Layouts { status: area, .. } = (area, self.is_horizontal).into();
+            let tool::Layouts { status: area, .. } = self.layout.borrow().layouts();
             self.draw_status(frame, area);
         }
     }
+    fn notify_text(&self) -> Option<Span<'_>> {
+        self.notify
+            .as_ref()
+            .filter(|(_, time)| time.elapsed() < NOTIFY_DURATION)
+            .map(|(msg, _)| Span::from(msg.as_str()).style(self.styles.get(StyleKey::Notify)))
+    }
+
     fn draw_status(&self, frame: &mut Frame, area_status: Rect) {
+        if let Some(notify) = self.notify_text() {
+            frame.render_widget(Paragraph::new(Line::from(notify)), area_status);
+        }
         let mode_name = self.mode_text();
         let hit = self.hit_text();
-        let s = Span::from(" ").bg(Color::Black);
+        let status_line = self.styles.get(StyleKey::StatusLine);
+        let s = Span::from(" ").style(status_line);
         let hits = hit
             .into_iter()
             .flat_map(|it| vec![it, s.clone()])
             .chain(std::iter::once(mode_name));
         let line = Line::from_iter(hits);
-        let paragraph_status = Paragraph::new(line)
-            .fg(Color::Gray)
-            .bg(Color::Black)
-            .right_aligned();
+        let paragraph_status = Paragraph::new(line).style(status_line).right_aligned();
+        // 左侧留给通知，提示文字右对齐
+        let area_status = match self.notify_text() {
+            Some(notify) => {
+                let width = (notify.width() as u16).saturating_add(1);
+                Rect {
+                    x: area_status.x.saturating_add(width),
+                    width: area_status.width.saturating_sub(width),
+                    ..area_status
+                }
+            }
+            None => area_status,
+        };
         frame.render_widget(paragraph_status, area_status);
     }
 }
 
 impl Component for StatusBar {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
     fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
         if self.is_show() {
             match action {
                 action::Action::Mode(mode) => self.set_mode(mode),
-                action::Action::SwapHV => self.is_horizontal = !self.is_horizontal,
+                action::Action::Notify(msg) => self.notify = Some((msg, Instant::now())),
+                action::Action::PendingKeys(hint) => self.pending = hint,
                 _ => {}
             };
         }
         Ok(None)
     }
 
-    fn draw(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
-        self.draw_all(frame, area);
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        self.draw_all(frame);
         Ok(())
     }
 }
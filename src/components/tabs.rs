@@ -0,0 +1,39 @@
+use super::Component;
+use crate::config::{Config, StyleKey, Styles};
+use crate::tool::layout::SharedLayout;
+use color_eyre::Result;
+use ratatui::{layout::Rect, widgets::Tabs, Frame};
+
+/// 绘制布局中标签页节点的标签栏，当前显示的页高亮
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TabBars {
+    layout: SharedLayout,
+    styles: Styles,
+}
+
+impl TabBars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Component for TabBars {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+    fn register_layout_handler(&mut self, layout: SharedLayout) -> Result<()> {
+        self.layout = layout;
+        Ok(())
+    }
+    fn draw(&mut self, frame: &mut Frame, _area: Rect) -> Result<()> {
+        self.layout.borrow().tab_bars().iter().for_each(|bar| {
+            let tabs = Tabs::new(bar.titles.clone())
+                .select(bar.active)
+                .style(self.styles.get(StyleKey::StatusLine))
+                .highlight_style(self.styles.get(StyleKey::ListSelected));
+            frame.render_widget(tabs, bar.area);
+        });
+        Ok(())
+    }
+}
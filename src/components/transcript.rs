@@ -0,0 +1,245 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use color_eyre::Result;
+use ratatui::{layout::Rect, Frame};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use tracing::{error, info};
+
+use super::{gdbtty, Component};
+use crate::{action, config};
+
+#[derive(Debug, Clone, PartialEq, Eq, Display, Serialize, Deserialize)]
+pub enum Action {
+    Save,
+}
+
+/// `strip_ansi_escapes::Writer` 的输出，按行取出
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps a plain text copy of the gdb console output, writes it to the
+/// `--transcript` file as it arrives and dumps it on demand.
+pub struct Transcript {
+    stripper: strip_ansi_escapes::Writer<SharedBuffer>,
+    stripped: SharedBuffer,
+    /// 最近的输出，总字节数不超过 `console.history_bytes`，旧的行先丢弃
+    lines: VecDeque<String>,
+    bytes: usize,
+    budget: usize,
+    file: Option<BufWriter<File>>,
+    timestamp: bool,
+    start: Instant,
+}
+
+impl Transcript {
+    pub fn new(path: Option<PathBuf>, timestamp: bool) -> Self {
+        let stripped = SharedBuffer::default();
+        let file = path.and_then(|path| match File::create(&path) {
+            Ok(f) => {
+                info!("write gdb transcript to {:?}", &path);
+                Some(BufWriter::new(f))
+            }
+            Err(e) => {
+                error!("open transcript {:?} fail: {}", &path, e);
+                None
+            }
+        });
+        Self {
+            stripper: strip_ansi_escapes::Writer::new(stripped.clone()),
+            stripped,
+            lines: VecDeque::new(),
+            bytes: 0,
+            budget: config::ConsoleConfig::default().history_bytes,
+            file,
+            timestamp,
+            start: Instant::now(),
+        }
+    }
+
+    fn add_out(&mut self, out: &[u8]) -> Result<()> {
+        self.stripper.write_all(out)?;
+        // 它按行缓存，刷新后没有换行的部分也能在 `pending` 中取到
+        self.stripper.flush()?;
+        let text = {
+            let mut buf = self.stripped.0.borrow_mut();
+            let end = match buf.iter().rposition(|c| *c == b'\n') {
+                Some(pos) => pos.saturating_add(1),
+                None => return Ok(()),
+            };
+            let text = String::from_utf8_lossy(&buf[..end]).into_owned();
+            buf.drain(..end);
+            text
+        };
+        let lines = text
+            .lines()
+            .map(|line| self.format_line(line))
+            .collect::<Vec<_>>();
+        if let Some(file) = self.file.as_mut() {
+            lines
+                .iter()
+                .try_for_each(|line| writeln!(file, "{}", line))?;
+            file.flush()?;
+        }
+        lines.into_iter().for_each(|line| self.push_line(line));
+        Ok(())
+    }
+
+    fn push_line(&mut self, line: String) {
+        self.bytes = self.bytes.saturating_add(line.len());
+        self.lines.push_back(line);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.bytes > self.budget {
+            match self.lines.pop_front() {
+                Some(line) => self.bytes = self.bytes.saturating_sub(line.len()),
+                None => break,
+            }
+        }
+    }
+
+    /// 还没有换行的最后一部分输出，例如 gdb 的提示符
+    fn pending(&self) -> Option<String> {
+        let buf = self.stripped.0.borrow();
+        match buf.is_empty() {
+            true => None,
+            false => Some(self.format_line(&String::from_utf8_lossy(&buf))),
+        }
+    }
+
+    fn format_line(&self, line: &str) -> String {
+        match self.timestamp {
+            true => format!("[{:>10.3}] {}", self.start.elapsed().as_secs_f64(), line),
+            false => line.to_string(),
+        }
+    }
+
+    fn save(&self) -> Result<PathBuf> {
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        let directory = config::get_data_dir();
+        std::fs::create_dir_all(&directory)?;
+        let path = directory.join(format!("gdb-transcript-{}.txt", secs));
+        let mut file = BufWriter::new(File::create(&path)?);
+        self.lines
+            .iter()
+            .chain(self.pending().as_ref())
+            .try_for_each(|line| writeln!(file, "{}", line))?;
+        file.flush()?;
+        Ok(path)
+    }
+}
+
+impl Drop for Transcript {
+    /// 退出时把没有换行的最后一部分也写进 `--transcript` 文件
+    fn drop(&mut self) {
+        let pending = self.pending();
+        if let (Some(file), Some(line)) = (self.file.as_mut(), pending) {
+            if let Err(e) = writeln!(file, "{}", line).and_then(|_| file.flush()) {
+                error!("write gdb transcript fail: {}", e);
+            }
+        }
+    }
+}
+
+impl Component for Transcript {
+    fn register_config_handler(&mut self, config: config::Config) -> Result<()> {
+        self.budget = config.console.history_bytes;
+        self.trim();
+        Ok(())
+    }
+    fn update(&mut self, action: action::Action) -> Result<Option<action::Action>> {
+        let ret = match action {
+            action::Action::Gdbtty(gdbtty::Action::Out(out)) => {
+                if let Err(e) = self.add_out(&out) {
+                    error!("write gdb transcript fail: {}", e);
+                    self.file = None;
+                }
+                None
+            }
+            action::Action::Transcript(Action::Save) => {
+                let msg = match self.save() {
+                    Ok(path) => {
+                        info!("gdb transcript saved to {:?}", &path);
+                        format!("Transcript saved to {}", path.display())
+                    }
+                    Err(e) => {
+                        error!("save gdb transcript fail: {}", e);
+                        format!("Save transcript fail: {}", e)
+                    }
+                };
+                Some(action::Action::Notify(msg))
+            }
+            _ => None,
+        };
+        Ok(ret)
+    }
+
+    fn draw(&mut self, _frame: &mut Frame, _area: Rect) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_transcript_strip_ansi() {
+        let mut t = Transcript::new(None, false);
+        t.add_out(b"\x1b[32m(gdb) \x1b[mb ma").unwrap();
+        assert!(t.lines.is_empty());
+        t.add_out(b"in\r\nBreakpoint 1 at \x1b[3").unwrap();
+        t.add_out(b"4m0x1139\x1b[m\r\n").unwrap();
+        assert_eq!(t.lines, vec!["(gdb) b main", "Breakpoint 1 at 0x1139"]);
+    }
+
+    #[test]
+    fn f_transcript_timestamp() {
+        let mut t = Transcript::new(None, true);
+        t.add_out(b"(gdb) r\n").unwrap();
+        assert!(t.lines[0].starts_with('['));
+        assert!(t.lines[0].ends_with("] (gdb) r"));
+    }
+
+    #[test]
+    fn f_transcript_bounded() {
+        let mut t = Transcript::new(None, false);
+        t.budget = 10;
+        t.add_out(b"aaaa\nbbbb\ncccc\n").unwrap();
+        assert_eq!(t.lines, vec!["bbbb", "cccc"]);
+        assert_eq!(t.bytes, 8);
+    }
+
+    #[test]
+    fn f_transcript_flush_pending() {
+        let path = std::env::temp_dir().join(format!("rgdb-transcript-{}.txt", std::process::id()));
+        let mut t = Transcript::new(Some(path.clone()), false);
+        t.add_out(b"Starting program\n(gdb) ").unwrap();
+        assert_eq!(t.pending(), Some("(gdb) ".to_string()));
+        drop(t);
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(text, "Starting program\n(gdb) \n");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,6 +1,6 @@
 #![allow(dead_code)] // Remove this once you start using the code
 
-use std::{collections::HashMap, env, path::PathBuf};
+use std::{collections::HashMap, env, path::PathBuf, time::SystemTime};
 
 use color_eyre::Result;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
@@ -13,8 +13,24 @@ use tracing::error;
 
 use crate::{action::Action, app::Mode};
 
+pub mod layout;
+mod styles;
+mod validate;
+pub use layout::{LayoutConfig, LayoutNode};
+pub use styles::{adapt_color, ColorDepth, StyleKey, Styles};
+pub use validate::ConfigIssue;
+
 const CONFIG: &str = include_str!("../.config/config.json5");
 
+/// 配置目录中会读取的文件，按顺序合并
+const CONFIG_FILES: [(&str, config::FileFormat); 5] = [
+    ("config.json5", config::FileFormat::Json5),
+    ("config.json", config::FileFormat::Json),
+    ("config.yaml", config::FileFormat::Yaml),
+    ("config.toml", config::FileFormat::Toml),
+    ("config.ini", config::FileFormat::Ini),
+];
+
 #[derive(Clone, Debug, Deserialize, Default)]
 pub struct AppConfig {
     #[serde(default)]
@@ -23,6 +39,198 @@ pub struct AppConfig {
     pub config_dir: PathBuf,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ConsoleConfig {
+    /// Number of lines the gdb console keeps above the screen.
+    pub scrollback_lines: usize,
+    /// Upper bound of raw gdb console output kept to rebuild the console on resize,
+    /// also the plain text kept for saving the transcript.
+    pub history_bytes: usize,
+}
+
+impl Default for ConsoleConfig {
+    fn default() -> Self {
+        Self {
+            scrollback_lines: 10000,
+            history_bytes: 4 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct InputConfig {
+    /// How long a key sequence such as `<Space><b>` or a count waits for the next key.
+    pub sequence_timeout_ms: u64,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        Self {
+            sequence_timeout_ms: 1000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HoverConfig {
+    /// How long the mouse rests on an identifier before its value pops up, 0 disables it.
+    pub mouse_delay_ms: u64,
+    /// Most members listed when a structure or array is expanded.
+    pub max_children: u64,
+}
+
+impl Default for HoverConfig {
+    fn default() -> Self {
+        Self {
+            mouse_delay_ms: 500,
+            max_children: 100,
+        }
+    }
+}
+
+/// One way of putting text on the clipboard, tried in the configured order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClipboardBackend {
+    Arboard,
+    Osc52,
+    Command,
+    File,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct ClipboardConfig {
+    /// Backends tried in order until one succeeds.
+    pub backends: Vec<ClipboardBackend>,
+    /// Only use OSC 52 inside an ssh session.
+    pub osc52_only_ssh: bool,
+    /// Commands that read the text from stdin, e.g. `["xclip", "-selection", "clipboard"]`.
+    pub commands: Vec<Vec<String>>,
+}
+
+impl Default for ClipboardConfig {
+    fn default() -> Self {
+        Self {
+            backends: vec![
+                ClipboardBackend::Arboard,
+                ClipboardBackend::Osc52,
+                ClipboardBackend::Command,
+                ClipboardBackend::File,
+            ],
+            osc52_only_ssh: true,
+            commands: [
+                vec!["wl-copy"],
+                vec!["xclip", "-selection", "clipboard"],
+                vec!["xsel", "--clipboard", "--input"],
+                vec!["pbcopy"],
+            ]
+            .into_iter()
+            .map(|cmd| cmd.into_iter().map(String::from).collect())
+            .collect(),
+        }
+    }
+}
+
+/// Rewrites a source path reported by gdb into one that exists locally.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum PathRule {
+    /// Replaces a leading path prefix, matched on whole path components.
+    Prefix { from: String, to: String },
+    /// Replaces the first regex match, `to` may use `$1` style groups.
+    Regex { regex: String, to: String },
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct SourceConfig {
+    /// Rules tried in order before a source file is opened.
+    pub substitute_path: Vec<PathRule>,
+    /// Directories searched by trailing path components when no rule matches.
+    pub search_paths: Vec<PathBuf>,
+    /// Also use the rules set in gdb with `set substitute-path`.
+    pub import_gdb_substitute_path: bool,
+    /// Memory budget in bytes for cached source files, and again for cached
+    /// disassembly. The shown file and files with breakpoints are never evicted.
+    pub cache_bytes: usize,
+}
+
+impl Default for SourceConfig {
+    fn default() -> Self {
+        Self {
+            substitute_path: vec![],
+            search_paths: vec![],
+            import_gdb_substitute_path: true,
+            cache_bytes: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// Picks a syntax for the files matching `glob`.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct SyntaxRule {
+    /// `*` and `?` stay within one path component and `**` matches any path.
+    /// A glob without `/` is matched against the file name only.
+    pub glob: String,
+    /// Syntax name as listed by `rgdb list syntaxes`, or one of its extensions.
+    pub syntax: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct HighlightConfig {
+    /// Theme name as listed by `rgdb list themes`.
+    pub theme: String,
+    /// Rules tried in order before the file extension.
+    pub syntax_map: Vec<SyntaxRule>,
+    /// Files without an extension under these directories are highlighted as
+    /// C++, like the standard library headers. `*` and `**` work as in globs.
+    pub cpp_include_dirs: Vec<String>,
+}
+
+impl Default for HighlightConfig {
+    fn default() -> Self {
+        Self {
+            theme: "Nord".to_string(),
+            syntax_map: ["*.inl", "*.tcc", "*.ipp", "*.cu", "*.cuh"]
+                .into_iter()
+                .map(|glob| SyntaxRule {
+                    glob: glob.to_string(),
+                    syntax: "C++".to_string(),
+                })
+                .collect(),
+            cpp_include_dirs: ["/usr/include", "/usr/local/include", "**/include/c++"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+        }
+    }
+}
+
+/// One command sent by a macro. `{word_under_cursor}`, `{file}`, `{line}` and
+/// `{selection}` are replaced before it is sent, `{{` and `}}` are literal braces.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum MacroCommand {
+    /// Typed into the gdb console, e.g. `p *this`.
+    Console(String),
+    /// Written to the mi channel, e.g. `{"mi": "-break-insert {file}:{line}"}`.
+    Mi { mi: String },
+}
+
+/// Commands run in order by binding `{"Macro": "<name>"}` to a key.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct MacroConfig {
+    /// Shown in the command palette and the help overlay instead of the name.
+    #[serde(default)]
+    pub description: String,
+    pub commands: Vec<MacroCommand>,
+}
+
 #[derive(Clone, Debug, Default, Deserialize)]
 pub struct Config {
     #[serde(default, flatten)]
@@ -31,6 +239,25 @@ pub struct Config {
     pub keybindings: KeyBindings,
     #[serde(default)]
     pub styles: Styles,
+    #[serde(default)]
+    pub console: ConsoleConfig,
+    #[serde(default)]
+    pub input: InputConfig,
+    #[serde(default)]
+    pub clipboard: ClipboardConfig,
+    #[serde(default)]
+    pub source: SourceConfig,
+    #[serde(default)]
+    pub highlight: HighlightConfig,
+    #[serde(default)]
+    pub layout: LayoutConfig,
+    #[serde(default)]
+    pub macros: HashMap<String, MacroConfig>,
+    #[serde(default)]
+    pub hover: HoverConfig,
+    /// Problems found while loading, the entries are replaced by their defaults.
+    #[serde(skip)]
+    pub issues: Vec<ConfigIssue>,
 }
 
 lazy_static! {
@@ -46,36 +273,48 @@ lazy_static! {
 }
 
 impl Config {
+    /// 出错的条目不会让加载失败，而是用默认值代替并记在 `issues` 中
     pub fn new() -> Result<Self, config::ConfigError> {
-        let default_config: Config = json5::from_str(CONFIG).unwrap();
+        let mut issues = vec![];
+        let default_config: Config = json5::from_str(CONFIG).unwrap_or_else(|e| {
+            issues.push(ConfigIssue::new("", format!("built-in config: {}", e)));
+            Config::default()
+        });
         let data_dir = get_data_dir();
         let config_dir = get_config_dir();
         let mut builder = config::Config::builder()
             .set_default("data_dir", data_dir.to_str().unwrap())?
             .set_default("config_dir", config_dir.to_str().unwrap())?;
 
-        let config_files = [
-            ("config.json5", config::FileFormat::Json5),
-            ("config.json", config::FileFormat::Json),
-            ("config.yaml", config::FileFormat::Yaml),
-            ("config.toml", config::FileFormat::Toml),
-            ("config.ini", config::FileFormat::Ini),
-        ];
-        let mut found_config = false;
-        for (file, format) in &config_files {
-            let source = config::File::from(config_dir.join(file))
-                .format(*format)
-                .required(false);
-            builder = builder.add_source(source);
-            if config_dir.join(file).exists() {
-                found_config = true
+        let mut files = vec![];
+        for (file, format) in &CONFIG_FILES {
+            let path = config_dir.join(file);
+            if !path.exists() {
+                continue;
+            }
+            let source = config::File::from(path.clone()).format(*format);
+            // 有语法错误的文件整个跳过
+            match config::Config::builder().add_source(source.clone()).build() {
+                Ok(_) => {
+                    builder = builder.add_source(source);
+                    let text = std::fs::read_to_string(&path).unwrap_or_default();
+                    files.push((path.clone(), text));
+                }
+                Err(e) => issues.push(ConfigIssue::new("", e.to_string()).in_file(&path)),
             }
         }
-        if !found_config {
+        if files.is_empty() {
             error!("No configuration file found. Application may not behave as expected");
         }
 
-        let mut cfg: Self = builder.build()?.try_deserialize()?;
+        let mut value = builder.build()?.try_deserialize::<serde_json::Value>()?;
+        let mut found = validate::sanitize(&mut value);
+        found.iter_mut().for_each(|issue| issue.locate(&files));
+        issues.extend(found);
+        let mut cfg: Self = serde_json::from_value(value).unwrap_or_else(|e| {
+            issues.push(ConfigIssue::new("", e.to_string()));
+            Config::default()
+        });
 
         for (mode, default_bindings) in default_config.keybindings.iter() {
             let user_bindings = cfg.keybindings.entry(*mode).or_default();
@@ -85,17 +324,39 @@ impl Config {
                     .or_insert_with(|| cmd.clone());
             }
         }
-        for (mode, default_styles) in default_config.styles.iter() {
-            let user_styles = cfg.styles.entry(*mode).or_default();
-            for (style_key, style) in default_styles.iter() {
-                user_styles.entry(style_key.clone()).or_insert(*style);
-            }
+        for (name, preset) in default_config.layout.presets {
+            cfg.layout.presets.entry(name).or_insert(preset);
+        }
+        for (name, m) in default_config.macros {
+            cfg.macros.entry(name).or_insert(m);
+        }
+        if !cfg.layout.presets.contains_key(&cfg.layout.preset) {
+            let msg = format!("no layout named `{}`", cfg.layout.preset);
+            let mut issue = ConfigIssue::new("layout.preset", msg).at(&cfg.layout.preset);
+            issue.locate(&files);
+            issues.push(issue);
+            cfg.layout.preset = layout::DEFAULT_PRESET.to_string();
         }
 
+        issues.iter().for_each(|issue| error!("config: {}", issue));
+        cfg.issues = issues;
         Ok(cfg)
     }
 }
 
+/// 每个配置文件的修改时间，不存在的为 `None`，变化时说明需要重新加载
+pub fn config_files_stamp() -> Vec<Option<SystemTime>> {
+    let config_dir = get_config_dir();
+    CONFIG_FILES
+        .iter()
+        .map(|(file, _)| {
+            std::fs::metadata(config_dir.join(file))
+                .and_then(|m| m.modified())
+                .ok()
+        })
+        .collect()
+}
+
 pub fn get_data_dir() -> PathBuf {
     let directory = if let Some(s) = DATA_FOLDER.clone() {
         s
@@ -137,11 +398,12 @@ impl<'de> Deserialize<'de> for KeyBindings {
             .map(|(mode, inner_map)| {
                 let converted_inner_map = inner_map
                     .into_iter()
-                    .map(|(key_str, cmd)| (parse_key_sequence(&key_str).unwrap(), cmd))
-                    .collect();
-                (mode, converted_inner_map)
+                    .map(|(key_str, cmd)| Ok((parse_key_sequence(&key_str)?, cmd)))
+                    .collect::<Result<_, String>>()?;
+                Ok((mode, converted_inner_map))
             })
-            .collect();
+            .collect::<Result<_, String>>()
+            .map_err(serde::de::Error::custom)?;
 
         Ok(KeyBindings(keybindings))
     }
@@ -246,7 +508,7 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
         KeyCode::Delete => "delete",
         KeyCode::Insert => "insert",
         KeyCode::F(c) => {
-            char = format!("f({c})");
+            char = format!("f{c}");
             &char
         }
         KeyCode::Char(' ') => "space",
@@ -291,6 +553,13 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
     key
 }
 
+/// 按配置文件中的写法显示一串按键，如 `<ctrl-l>` 或 `<g><g>`
+pub fn key_sequence_to_string(keys: &[KeyEvent]) -> String {
+    keys.iter()
+        .map(|k| format!("<{}>", key_event_to_string(k)))
+        .collect()
+}
+
 pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     if raw.chars().filter(|c| *c == '>').count() != raw.chars().filter(|c| *c == '<').count() {
         return Err(format!("Unable to parse `{}`", raw));
@@ -318,31 +587,6 @@ pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     sequences.into_iter().map(parse_key_event).collect()
 }
 
-#[derive(Clone, Debug, Default, Deref, DerefMut)]
-pub struct Styles(pub HashMap<Mode, HashMap<String, Style>>);
-
-impl<'de> Deserialize<'de> for Styles {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let parsed_map = HashMap::<Mode, HashMap<String, String>>::deserialize(deserializer)?;
-
-        let styles = parsed_map
-            .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(str, style)| (str, parse_style(&style)))
-                    .collect();
-                (mode, converted_inner_map)
-            })
-            .collect();
-
-        Ok(Styles(styles))
-    }
-}
-
 pub fn parse_style(line: &str) -> Style {
     let (foreground, background) =
         line.split_at(line.to_lowercase().find("on ").unwrap_or(line.len()));
@@ -360,6 +604,24 @@ pub fn parse_style(line: &str) -> Style {
     style
 }
 
+/// `parse_style` 会忽略不认识的颜色，检查配置时用它找出来
+pub fn style_error(line: &str) -> Option<String> {
+    let (foreground, background) =
+        line.split_at(line.to_lowercase().find("on ").unwrap_or(line.len()));
+    [foreground.to_string(), background.replace("on ", "")]
+        .iter()
+        .map(|part| process_color_string(part).0)
+        .map(|color| {
+            color
+                .split_whitespace()
+                .filter(|w| !["bold", "underline", "inverse"].contains(w))
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .find(|color| !color.is_empty() && parse_color(color).is_none())
+        .map(|color| format!("unknown color `{}`", color))
+}
+
 fn process_color_string(color_str: &str) -> (String, Modifier) {
     let color = color_str
         .replace("grey", "gray")
@@ -399,17 +661,20 @@ fn parse_color(s: &str) -> Option<Color> {
             .unwrap_or_default();
         Some(Color::Indexed(c))
     } else if s.contains("gray") {
-        let c = 232
-            + s.trim_start_matches("gray")
-                .parse::<u8>()
-                .unwrap_or_default();
-        Some(Color::Indexed(c))
-    } else if s.contains("rgb") {
-        let red = (s.as_bytes()[3] as char).to_digit(10).unwrap_or_default() as u8;
-        let green = (s.as_bytes()[4] as char).to_digit(10).unwrap_or_default() as u8;
-        let blue = (s.as_bytes()[5] as char).to_digit(10).unwrap_or_default() as u8;
-        let c = 16 + red * 36 + green * 6 + blue;
-        Some(Color::Indexed(c))
+        // 灰度只有 gray0 到 gray23
+        let level = s
+            .trim_start_matches("gray")
+            .parse::<u8>()
+            .unwrap_or_default();
+        let level = Some(level).filter(|level| *level < 24)?;
+        Some(Color::Indexed(232_u8.checked_add(level)?))
+    } else if s.contains("rgb") && s.len() >= 6 {
+        // 每个分量只有 0 到 5，合起来是一个六进制数
+        let c = s.as_bytes()[3..6].iter().try_fold(0_u8, |c, digit| {
+            let digit = (*digit as char).to_digit(6)? as u8;
+            c.checked_mul(6)?.checked_add(digit)
+        })?;
+        Some(Color::Indexed(c.checked_add(16)?))
     } else if s == "bold black" {
         Some(Color::Indexed(8))
     } else if s == "bold red" {
@@ -442,6 +707,9 @@ fn parse_color(s: &str) -> Option<Color> {
         Some(Color::Indexed(6))
     } else if s == "white" {
         Some(Color::Indexed(7))
+    } else if let Some(hex) = s.strip_prefix('#').filter(|h| h.len() == 6) {
+        let c = u32::from_str_radix(hex, 16).ok()?;
+        Some(Color::Rgb((c >> 16) as u8, (c >> 8) as u8, c as u8))
     } else {
         None
     }
@@ -451,6 +719,7 @@ fn parse_color(s: &str) -> Option<Color> {
 mod tests {
     use pretty_assertions::assert_eq;
 
+    use super::styles::StylePreset;
     use super::*;
 
     #[test]
@@ -492,6 +761,42 @@ mod tests {
         let color = parse_color("rgb123");
         let expected = 16 + 36 + 2 * 6 + 3;
         assert_eq!(color, Some(Color::Indexed(expected)));
+        assert_eq!(parse_color("rgb555"), Some(Color::Indexed(231)));
+        assert_eq!(parse_color("rgb999"), None);
+        assert_eq!(parse_color("gray23"), Some(Color::Indexed(255)));
+        assert_eq!(parse_color("gray30"), None);
+        assert_eq!(
+            style_error("rgb999 on gray30"),
+            Some("unknown color `rgb999`".to_string())
+        );
+        assert_eq!(
+            style_error("bold on gray30"),
+            Some("unknown color `gray30`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_color_hex() {
+        assert_eq!(parse_color("#ffcc00"), Some(Color::Rgb(255, 204, 0)));
+        assert_eq!(parse_color("#ffcc0"), None);
+    }
+
+    #[test]
+    fn test_styles_config() {
+        let c: Config = json5::from_str(
+            r##"{"styles": {"preset": "light", "colors": "256", "overrides": {"breakpoint": "#ff0000"}}}"##,
+        )
+        .unwrap();
+        assert_eq!(c.styles.preset, StylePreset::Light);
+        assert_eq!(c.styles.colors, ColorDepth::Ansi256);
+        assert_eq!(
+            c.styles.get(StyleKey::Breakpoint).fg,
+            Some(Color::Indexed(196))
+        );
+        let c: Config = json5::from_str(CONFIG).unwrap();
+        assert_eq!(c.styles.preset, StylePreset::Dark);
+        assert_eq!(c.styles.colors, ColorDepth::Auto);
+        assert!(c.styles.overrides.is_empty());
     }
 
     #[test]
@@ -597,6 +902,110 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_console_config() {
+        let c: Config = json5::from_str(r#"{"console": {"scrollback_lines": 100}}"#).unwrap();
+        assert_eq!(c.console.scrollback_lines, 100);
+        assert_eq!(
+            c.console.history_bytes,
+            ConsoleConfig::default().history_bytes
+        );
+        let c: Config = json5::from_str(CONFIG).unwrap();
+        assert_eq!(c.console.scrollback_lines, 10000);
+    }
+
+    #[test]
+    fn test_input_config() {
+        let c: Config = json5::from_str(r#"{"input": {"sequence_timeout_ms": 500}}"#).unwrap();
+        assert_eq!(c.input.sequence_timeout_ms, 500);
+        let c: Config = json5::from_str(CONFIG).unwrap();
+        assert_eq!(c.input.sequence_timeout_ms, 1000);
+    }
+
+    #[test]
+    fn test_clipboard_config() {
+        let c: Config = json5::from_str(
+            r#"{"clipboard": {"backends": ["command", "file"], "commands": [["wl-copy"]]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            c.clipboard.backends,
+            vec![ClipboardBackend::Command, ClipboardBackend::File]
+        );
+        assert_eq!(c.clipboard.commands, vec![vec!["wl-copy".to_string()]]);
+        assert!(c.clipboard.osc52_only_ssh);
+    }
+
+    #[test]
+    fn test_source_config() {
+        let c: Config = json5::from_str(
+            r#"{"source": {"substitute_path": [
+                {"from": "/build", "to": "/src"},
+                {"regex": "^/ci/[0-9]+", "to": "/src"},
+            ]}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            c.source.substitute_path,
+            vec![
+                PathRule::Prefix {
+                    from: "/build".to_string(),
+                    to: "/src".to_string()
+                },
+                PathRule::Regex {
+                    regex: "^/ci/[0-9]+".to_string(),
+                    to: "/src".to_string()
+                },
+            ]
+        );
+        assert!(c.source.import_gdb_substitute_path);
+        assert_eq!(c.source.cache_bytes, SourceConfig::default().cache_bytes);
+        let c: Config = json5::from_str(r#"{"source": {"cache_bytes": 1048576}}"#).unwrap();
+        assert_eq!(c.source.cache_bytes, 1048576);
+    }
+
+    #[test]
+    fn test_layout_config() {
+        let c: Config = json5::from_str(
+            r#"{"layout": {"split": 70, "preset": "mine", "presets": {"mine": {"pane": "source"}}}}"#,
+        )
+        .unwrap();
+        assert_eq!(c.layout.split, 70);
+        assert_eq!(c.layout.preset, "mine");
+        assert_eq!(
+            c.layout.presets["mine"],
+            layout::LayoutNode::pane(layout::PaneKind::Source, layout::PaneSize::Fill)
+        );
+        let c: Config = json5::from_str(CONFIG).unwrap();
+        assert_eq!(c.layout, LayoutConfig::default());
+    }
+
+    #[test]
+    fn test_highlight_config() {
+        let c: Config = json5::from_str(
+            r#"{"highlight": {"theme": "InspiredGitHub", "syntax_map": [{"glob": "*.x", "syntax": "C"}]}}"#,
+        )
+        .unwrap();
+        assert_eq!(c.highlight.theme, "InspiredGitHub");
+        assert_eq!(
+            c.highlight.syntax_map,
+            vec![SyntaxRule {
+                glob: "*.x".to_string(),
+                syntax: "C".to_string()
+            }]
+        );
+        let c: Config = json5::from_str(CONFIG).unwrap();
+        assert_eq!(c.highlight.theme, "Nord");
+        assert_eq!(
+            c.highlight.syntax_map,
+            HighlightConfig::default().syntax_map
+        );
+        assert_eq!(
+            c.highlight.cpp_include_dirs,
+            HighlightConfig::default().cpp_include_dirs
+        );
+    }
+
     #[test]
     fn test_simple_keys() {
         assert_eq!(
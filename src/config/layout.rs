@@ -0,0 +1,230 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// 源码窗口默认占的百分比
+pub const DEFAULT_SPLIT: u16 = 50;
+pub const DEFAULT_PRESET: &str = "stacked";
+
+/// A pane that can be placed in a layout.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaneKind {
+    /// Source or disassembly, with its status line at the bottom.
+    Source,
+    /// The gdb console, with the mode and key hints line at the bottom.
+    Console,
+}
+
+impl PaneKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PaneKind::Source => "source",
+            PaneKind::Console => "console",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SplitDirection {
+    /// Children are stacked from top to bottom.
+    Vertical,
+    /// Children are placed from left to right.
+    Horizontal,
+}
+
+impl SplitDirection {
+    pub fn flip(self) -> Self {
+        match self {
+            SplitDirection::Vertical => SplitDirection::Horizontal,
+            SplitDirection::Horizontal => SplitDirection::Vertical,
+        }
+    }
+}
+
+/// 子节点在分割中占的大小：`"fill"`、`"ratio"`（运行时可调的比例）、`"30%"` 或行数/列数 `"10"`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PaneSize {
+    #[default]
+    Fill,
+    Ratio,
+    Percent(u16),
+    Length(u16),
+}
+
+impl FromStr for PaneSize {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("invalid pane size `{}`", s);
+        match s {
+            "fill" => Ok(PaneSize::Fill),
+            "ratio" => Ok(PaneSize::Ratio),
+            _ => match s.strip_suffix('%') {
+                Some(p) => match p.trim().parse::<u16>() {
+                    Ok(p) if p <= 100 => Ok(PaneSize::Percent(p)),
+                    _ => Err(invalid()),
+                },
+                None => s.parse().map(PaneSize::Length).map_err(|_| invalid()),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PaneSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum NodeKind {
+    Pane {
+        pane: PaneKind,
+    },
+    Split {
+        split: SplitDirection,
+        children: Vec<LayoutNode>,
+    },
+    /// 同一块区域里一次只显示一个子节点，顶上一行是标签栏
+    Tabs {
+        tabs: Vec<LayoutNode>,
+    },
+}
+
+/// A node of the layout tree, e.g.
+/// `{"split": "vertical", "children": [{"pane": "source", "size": "ratio"}, {"pane": "console"}]}`
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct LayoutNode {
+    /// Size in the parent split, ignored elsewhere.
+    #[serde(default)]
+    pub size: PaneSize,
+    #[serde(flatten)]
+    pub kind: NodeKind,
+}
+
+impl LayoutNode {
+    pub fn pane(pane: PaneKind, size: PaneSize) -> Self {
+        Self {
+            size,
+            kind: NodeKind::Pane { pane },
+        }
+    }
+    pub fn split(split: SplitDirection, children: Vec<LayoutNode>) -> Self {
+        Self {
+            size: PaneSize::Fill,
+            kind: NodeKind::Split { split, children },
+        }
+    }
+    pub fn tabs(tabs: Vec<LayoutNode>) -> Self {
+        Self {
+            size: PaneSize::Fill,
+            kind: NodeKind::Tabs { tabs },
+        }
+    }
+    /// 按先序列出这个节点里的窗口
+    pub fn panes(&self) -> Vec<PaneKind> {
+        match &self.kind {
+            NodeKind::Pane { pane } => vec![*pane],
+            NodeKind::Split { children: c, .. } | NodeKind::Tabs { tabs: c } => {
+                c.iter().flat_map(|n| n.panes()).collect()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct LayoutConfig {
+    /// Percent taken by the `"ratio"` sized pane. The ratio last set with the
+    /// keys or by dragging the divider is saved in the data dir and wins.
+    pub split: u16,
+    /// Name of the preset used at start.
+    pub preset: String,
+    /// Named layout trees, switchable at runtime.
+    pub presets: HashMap<String, LayoutNode>,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        let two_panes = |split| {
+            LayoutNode::split(
+                split,
+                vec![
+                    LayoutNode::pane(PaneKind::Source, PaneSize::Ratio),
+                    LayoutNode::pane(PaneKind::Console, PaneSize::Fill),
+                ],
+            )
+        };
+        let tabs = LayoutNode::tabs(vec![
+            LayoutNode::pane(PaneKind::Source, PaneSize::Fill),
+            LayoutNode::pane(PaneKind::Console, PaneSize::Fill),
+        ]);
+        Self {
+            split: DEFAULT_SPLIT,
+            preset: DEFAULT_PRESET.to_string(),
+            presets: HashMap::from([
+                (
+                    DEFAULT_PRESET.to_string(),
+                    two_panes(SplitDirection::Vertical),
+                ),
+                (
+                    "side_by_side".to_string(),
+                    two_panes(SplitDirection::Horizontal),
+                ),
+                ("tabs".to_string(), tabs),
+            ]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_pane_size() {
+        assert_eq!("fill".parse(), Ok(PaneSize::Fill));
+        assert_eq!("ratio".parse(), Ok(PaneSize::Ratio));
+        assert_eq!("30%".parse(), Ok(PaneSize::Percent(30)));
+        assert_eq!("12".parse(), Ok(PaneSize::Length(12)));
+        assert!("120%".parse::<PaneSize>().is_err());
+        assert!("half".parse::<PaneSize>().is_err());
+    }
+
+    #[test]
+    fn f_layout_node() {
+        let node: LayoutNode = json5::from_str(
+            r#"{"split": "horizontal", "children": [
+                {"pane": "source", "size": "ratio"},
+                {"size": "10", "tabs": [{"pane": "console"}]},
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            node,
+            LayoutNode::split(
+                SplitDirection::Horizontal,
+                vec![
+                    LayoutNode::pane(PaneKind::Source, PaneSize::Ratio),
+                    LayoutNode {
+                        size: PaneSize::Length(10),
+                        ..LayoutNode::tabs(vec![LayoutNode::pane(
+                            PaneKind::Console,
+                            PaneSize::Fill
+                        )])
+                    },
+                ]
+            )
+        );
+        assert_eq!(node.panes(), vec![PaneKind::Source, PaneKind::Console]);
+        assert!(json5::from_str::<LayoutNode>(r#"{"pane": "stack"}"#).is_err());
+    }
+}
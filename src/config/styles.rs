@@ -0,0 +1,408 @@
+use super::parse_style;
+use clap::ValueEnum;
+use ratatui::style::{Color, Modifier, Style};
+use serde::{Deserialize, Deserializer};
+use std::collections::HashMap;
+use tracing::info;
+
+/// A styled part of the ui, configured by its snake_case name.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StyleKey {
+    /// Source lines that are not highlighted yet.
+    Text,
+    LineNumber,
+    LineNumberCurrent,
+    /// The `├──>` pointer at the current line.
+    Pointer,
+    Breakpoint,
+    BreakpointDisabled,
+    Scrollbar,
+    /// Status line of the source pane, the start page and the console search prompt.
+    StatusBar,
+    /// The bottom line with the key hints and the mode.
+    StatusLine,
+    Mode,
+    Hint,
+    Notify,
+    Console,
+    ConsoleCursor,
+    SearchMatch,
+    SearchCurrent,
+    Selection,
+    SelectCursor,
+    PromptInput,
+    ListSelected,
+    Title,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StylePreset {
+    #[default]
+    Dark,
+    Light,
+}
+
+/// How many colors the terminal can show.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+pub enum ColorDepth {
+    /// Detect from `COLORTERM` and terminfo
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+    #[serde(rename = "256")]
+    #[value(name = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    #[value(name = "16")]
+    Ansi16,
+    /// No colors, only bold and reversed text
+    #[serde(rename = "never")]
+    Never,
+}
+
+impl ColorDepth {
+    /// `Auto` 时检测当前终端，其它值原样返回
+    pub fn resolve(self) -> Self {
+        match self {
+            ColorDepth::Auto => {
+                let env = |name| std::env::var(name).ok();
+                let terminfo_colors = term::terminfo::TermInfo::from_env()
+                    .ok()
+                    .and_then(|info| info.numbers.get("colors").copied());
+                let depth = detect_color_depth(
+                    env("COLORTERM").as_deref(),
+                    env("TERM").as_deref(),
+                    terminfo_colors,
+                    env("NO_COLOR").is_some_and(|s| !s.is_empty()),
+                );
+                info!("detect terminal color depth {:?}", depth);
+                depth
+            }
+            depth => depth,
+        }
+    }
+}
+
+/// 按 `NO_COLOR`、`COLORTERM`、terminfo 的 colors、`TERM` 的名字依次判断
+fn detect_color_depth(
+    colorterm: Option<&str>,
+    term: Option<&str>,
+    terminfo_colors: Option<u32>,
+    no_color: bool,
+) -> ColorDepth {
+    if no_color || term == Some("dumb") {
+        return ColorDepth::Never;
+    }
+    if matches!(colorterm, Some("truecolor" | "24bit")) {
+        return ColorDepth::TrueColor;
+    }
+    match terminfo_colors {
+        Some(n) if n >= 1 << 24 => ColorDepth::TrueColor,
+        Some(n) if n >= 256 => ColorDepth::Ansi256,
+        Some(n) if n >= 8 => ColorDepth::Ansi16,
+        _ => match term {
+            Some(term) if term.ends_with("-direct") => ColorDepth::TrueColor,
+            Some(term) if term.contains("256color") => ColorDepth::Ansi256,
+            _ => ColorDepth::Ansi16,
+        },
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Styles {
+    pub preset: StylePreset,
+    pub colors: ColorDepth,
+    /// Per key styles like `"bold #ffcc00 on black"`, replacing the preset ones.
+    #[serde(deserialize_with = "deserialize_overrides")]
+    pub overrides: HashMap<StyleKey, Style>,
+}
+
+fn deserialize_overrides<'de, D>(deserializer: D) -> Result<HashMap<StyleKey, Style>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let parsed = HashMap::<StyleKey, String>::deserialize(deserializer)?;
+    Ok(parsed
+        .into_iter()
+        .map(|(key, style)| (key, parse_style(&style)))
+        .collect())
+}
+
+impl Styles {
+    /// 取一个界面元素的样式：用户配置优先，其次是预设，再按终端支持的颜色数降级
+    pub fn get(&self, key: StyleKey) -> Style {
+        let (truecolor, ansi16) = preset_style(self.preset, key);
+        match (self.overrides.get(&key), self.colors) {
+            (_, ColorDepth::Never) => no_color_style(key),
+            (Some(style), colors) => adapt_style(*style, colors),
+            (None, ColorDepth::Ansi16) => ansi16,
+            (None, colors) => adapt_style(truecolor, colors),
+        }
+    }
+}
+
+/// 没有颜色时用反色标出需要突出的部分
+fn no_color_style(key: StyleKey) -> Style {
+    use StyleKey as K;
+    match key {
+        K::StatusBar
+        | K::Hint
+        | K::Notify
+        | K::ConsoleCursor
+        | K::SearchMatch
+        | K::SelectCursor
+        | K::Selection
+        | K::ListSelected => Style::new().add_modifier(Modifier::REVERSED),
+        K::LineNumberCurrent | K::Pointer | K::Breakpoint | K::SearchCurrent | K::Title => {
+            Style::new().add_modifier(Modifier::BOLD)
+        }
+        _ => Style::new(),
+    }
+}
+
+fn fg(c: Color) -> Style {
+    Style::new().fg(c)
+}
+
+fn on(fg: Color, bg: Color) -> Style {
+    Style::new().fg(fg).bg(bg)
+}
+
+/// 预设的真彩色样式和 16 色样式
+fn preset_style(preset: StylePreset, key: StyleKey) -> (Style, Style) {
+    use Color::*;
+    use StyleKey as K;
+    match preset {
+        StylePreset::Dark => match key {
+            K::Text | K::Scrollbar => (fg(White), fg(White)),
+            K::LineNumber => (Style::new(), Style::new()),
+            K::LineNumberCurrent | K::Pointer => (fg(LightGreen), fg(LightGreen)),
+            K::Breakpoint => (fg(Rgb(255, 0, 0)), fg(LightRed)),
+            K::BreakpointDisabled => (fg(Rgb(255, 128, 0)), fg(Yellow)),
+            K::StatusBar | K::ListSelected => (on(Black, Gray), on(Black, Gray)),
+            K::StatusLine | K::Mode => (on(Gray, Black), on(Gray, Black)),
+            K::Hint => (on(Black, Rgb(160, 160, 160)), on(Black, Gray)),
+            K::Notify => (on(Yellow, Black), on(Yellow, Black)),
+            K::Console => {
+                let style = on(White, Black).add_modifier(Modifier::BOLD);
+                (style, style)
+            }
+            K::ConsoleCursor | K::PromptInput => (fg(Rgb(255, 204, 0)), fg(Yellow)),
+            K::SearchMatch => (on(Black, Rgb(255, 255, 153)), on(Black, LightYellow)),
+            K::SearchCurrent => (on(Black, Rgb(255, 204, 0)), on(Black, Yellow)),
+            K::Selection => (Style::new().bg(Rgb(255, 204, 153)), Style::new().bg(Yellow)),
+            K::SelectCursor => {
+                let style = Style::new().add_modifier(Modifier::REVERSED);
+                (style, style)
+            }
+            K::Title => (fg(Blue), fg(Blue)),
+        },
+        StylePreset::Light => match key {
+            K::Text => (fg(Black), fg(Black)),
+            K::LineNumber | K::Scrollbar => (fg(DarkGray), fg(DarkGray)),
+            K::LineNumberCurrent | K::Pointer => (fg(Rgb(0, 128, 0)), fg(Green)),
+            K::Breakpoint => (fg(Rgb(200, 0, 0)), fg(Red)),
+            K::BreakpointDisabled => (fg(Rgb(200, 100, 0)), fg(Yellow)),
+            K::StatusBar => (on(White, Rgb(90, 90, 90)), on(White, DarkGray)),
+            K::StatusLine | K::Mode => (on(Black, Rgb(220, 220, 220)), on(Black, Gray)),
+            K::Hint => (on(White, Rgb(110, 110, 110)), on(White, DarkGray)),
+            K::Notify => (on(Rgb(160, 60, 0), Rgb(220, 220, 220)), on(Red, Gray)),
+            K::Console => (on(Black, White), on(Black, White)),
+            K::ConsoleCursor | K::PromptInput | K::Title => (fg(Rgb(0, 90, 200)), fg(Blue)),
+            K::SearchMatch => (on(Black, Rgb(255, 255, 153)), on(Black, LightYellow)),
+            K::SearchCurrent => (on(Black, Rgb(255, 204, 0)), on(Black, Yellow)),
+            K::Selection => (
+                Style::new().bg(Rgb(173, 214, 255)),
+                Style::new().bg(LightCyan),
+            ),
+            K::SelectCursor => {
+                let style = Style::new().add_modifier(Modifier::REVERSED);
+                (style, style)
+            }
+            K::ListSelected => (on(White, Rgb(0, 90, 200)), on(White, Blue)),
+        },
+    }
+}
+
+pub fn adapt_style(style: Style, colors: ColorDepth) -> Style {
+    Style {
+        fg: style.fg.map(|c| adapt_color(c, colors)),
+        bg: style.bg.map(|c| adapt_color(c, colors)),
+        ..style
+    }
+}
+
+/// 把真彩色换成终端支持的最接近的颜色
+pub fn adapt_color(color: Color, colors: ColorDepth) -> Color {
+    match (color, colors) {
+        (Color::Rgb(r, g, b), ColorDepth::Ansi256) => Color::Indexed(rgb_to_ansi256(r, g, b)),
+        (Color::Rgb(r, g, b), ColorDepth::Ansi16) => rgb_to_ansi16(r, g, b),
+        (Color::Indexed(i), ColorDepth::Ansi16) if i >= 16 => {
+            let (r, g, b) = ansi256_to_rgb(i);
+            rgb_to_ansi16(r, g, b)
+        }
+        (_, ColorDepth::Never) => Color::Reset,
+        (c, _) => c,
+    }
+}
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).unsigned_abs().pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+/// xterm 256 色中 6x6x6 色块和 24 级灰度里最接近的一个
+pub fn rgb_to_ansi256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|i| CUBE_LEVELS[*i].abs_diff(c))
+            .unwrap_or(0) as u8
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = 16 + 36 * ri + 6 * gi + bi;
+    let cube_rgb = (
+        CUBE_LEVELS[ri as usize],
+        CUBE_LEVELS[gi as usize],
+        CUBE_LEVELS[bi as usize],
+    );
+    let avg = ((u16::from(r) + u16::from(g) + u16::from(b)) / 3) as u8;
+    let gray_id = (avg.saturating_sub(8) / 10).min(23);
+    let gray = 8 + 10 * gray_id;
+    match distance((gray, gray, gray), (r, g, b)) < distance(cube_rgb, (r, g, b)) {
+        true => 232 + gray_id,
+        false => cube,
+    }
+}
+
+fn ansi256_to_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..=15 => ANSI16[i as usize].1,
+        16..=231 => {
+            let i = i - 16;
+            (
+                CUBE_LEVELS[(i / 36) as usize],
+                CUBE_LEVELS[(i / 6 % 6) as usize],
+                CUBE_LEVELS[(i % 6) as usize],
+            )
+        }
+        _ => {
+            let v = 8 + 10 * (i - 232);
+            (v, v, v)
+        }
+    }
+}
+
+/// xterm 默认的 16 色
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+pub fn rgb_to_ansi16(r: u8, g: u8, b: u8) -> Color {
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map(|(c, _)| *c)
+        .unwrap_or(Color::White)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_rgb_to_ansi256() {
+        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+        assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
+        assert_eq!(rgb_to_ansi256(128, 128, 128), 244);
+        assert_eq!(rgb_to_ansi256(255, 204, 0), 220);
+    }
+
+    #[test]
+    fn f_rgb_to_ansi16() {
+        assert_eq!(rgb_to_ansi16(250, 10, 10), Color::LightRed);
+        assert_eq!(rgb_to_ansi16(20, 20, 20), Color::Black);
+        assert_eq!(
+            adapt_color(Color::Indexed(196), ColorDepth::Ansi16),
+            Color::LightRed
+        );
+    }
+
+    #[test]
+    fn f_styles_get() {
+        let mut styles = Styles::default();
+        assert_eq!(
+            styles.get(StyleKey::Breakpoint).fg,
+            Some(Color::Rgb(255, 0, 0))
+        );
+        styles.colors = ColorDepth::Ansi256;
+        assert_eq!(
+            styles.get(StyleKey::Breakpoint).fg,
+            Some(Color::Indexed(196))
+        );
+        styles.colors = ColorDepth::Ansi16;
+        assert_eq!(styles.get(StyleKey::Breakpoint).fg, Some(Color::LightRed));
+        styles
+            .overrides
+            .insert(StyleKey::Breakpoint, Style::new().fg(Color::Magenta));
+        assert_eq!(styles.get(StyleKey::Breakpoint).fg, Some(Color::Magenta));
+        styles.colors = ColorDepth::Never;
+        assert_eq!(styles.get(StyleKey::Breakpoint).fg, None);
+        assert!(styles
+            .get(StyleKey::Selection)
+            .add_modifier
+            .contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn f_detect_color_depth() {
+        let detect = detect_color_depth;
+        assert_eq!(
+            detect(Some("truecolor"), Some("xterm"), Some(8), false),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            detect(None, Some("xterm-256color"), Some(256), false),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            detect(None, Some("xterm-direct"), Some(1 << 24), false),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            detect(None, Some("screen-256color"), None, false),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(detect(None, Some("vt100"), None, false), ColorDepth::Ansi16);
+        assert_eq!(
+            detect(None, Some("linux"), Some(8), false),
+            ColorDepth::Ansi16
+        );
+        assert_eq!(detect(None, Some("dumb"), None, false), ColorDepth::Never);
+        assert_eq!(
+            detect(Some("truecolor"), Some("xterm"), None, true),
+            ColorDepth::Never
+        );
+    }
+}
@@ -0,0 +1,553 @@
+use super::{parse_key_sequence, style_error, LayoutNode, MacroCommand, MacroConfig, StyleKey};
+use crate::{action::Action, app::Mode, components::macros};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fmt;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// 配置文件中可以出现的顶层条目
+const SECTIONS: [&str; 12] = [
+    "data_dir",
+    "config_dir",
+    "keybindings",
+    "styles",
+    "console",
+    "input",
+    "clipboard",
+    "source",
+    "highlight",
+    "layout",
+    "macros",
+    "hover",
+];
+
+/// A problem found in the config. The entry is dropped so its default is used.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+    /// Where the entry is, e.g. `keybindings.Code.<Ctrl-xx>`.
+    pub path: String,
+    pub message: String,
+    /// 用来在文件中找到所在行的文字
+    needle: Option<String>,
+}
+
+impl ConfigIssue {
+    pub fn new(path: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            message: message.into(),
+            ..Self::default()
+        }
+    }
+    pub fn at(self, needle: &str) -> Self {
+        Self {
+            needle: Some(needle.to_string()),
+            ..self
+        }
+    }
+    pub fn in_file(self, file: &Path) -> Self {
+        Self {
+            file: Some(file.to_path_buf()),
+            ..self
+        }
+    }
+
+    /// 在给出的配置文件中找出错条目所在的行，跳过 `//` 注释。
+    /// 按 path 先找上层的键，再在它的范围里找 needle，找不到时不给行号
+    pub fn locate(&mut self, files: &[(PathBuf, String)]) {
+        let Some(needle) = self.needle.as_deref() else {
+            return;
+        };
+        // needle 是 path 的最后一段时它是个键，否则是 path 所指的键的值
+        let (parents, is_key) = match self.path.strip_suffix(needle) {
+            Some("") => ("", true),
+            Some(rest) if rest.ends_with('.') => (&rest[..rest.len() - 1], true),
+            _ => (self.path.as_str(), false),
+        };
+        let found = files.iter().find_map(|(file, text)| {
+            let lines = text
+                .lines()
+                .map(|line| line.split("//").next().unwrap_or(line))
+                .collect::<Vec<_>>();
+            let mut range = 0..lines.len();
+            for parent in parents.split('.').filter(|parent| !parent.is_empty()) {
+                range = key_range(&lines, range, parent)?;
+            }
+            let id = match is_key {
+                true => key_range(&lines, range, needle)?.start,
+                false => range.clone().find(|&id| lines[id].contains(needle))?,
+            };
+            Some((file, id + 1))
+        });
+        if let Some((file, line)) = found {
+            self.file = Some(file.clone());
+            self.line = Some(line);
+        }
+    }
+}
+
+/// 在 range 的第一层中找键 key，返回它和它的值占的行。
+/// 写在同一行里的下一层不去找，找不到就算了
+fn key_range(lines: &[&str], range: Range<usize>, key: &str) -> Option<Range<usize>> {
+    let mut depth = 0;
+    let mut start = None;
+    for id in range.clone() {
+        if start.is_none() && depth == 1 && is_key(lines[id], key) {
+            start = Some(id);
+        }
+        depth += depth_change(lines[id]);
+        if let Some(start) = start.filter(|_| depth <= 1) {
+            return Some(start..id + 1);
+        }
+    }
+    start.map(|start| start..range.end)
+}
+
+/// 这一行的第一层里有没有写键 key，键可以带引号也可以不带
+fn is_key(line: &str, key: &str) -> bool {
+    line.match_indices(key).any(|(id, _)| {
+        let before = line[..id].trim_end_matches(['"', '\'']).trim_end();
+        let after = line[id + key.len()..].trim_start_matches(['"', '\'']);
+        matches!(before.chars().last(), None | Some(','))
+            && depth_change(before) == 0
+            && after.trim_start().starts_with(':')
+    })
+}
+
+/// 这一行让括号的层数变了多少，不算字符串里的括号
+fn depth_change(line: &str) -> i32 {
+    let mut change = 0;
+    let mut quote = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(_), '\\') => {
+                chars.next();
+            }
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{' | '[') => change += 1,
+            (None, '}' | ']') => change -= 1,
+            (None, _) => {}
+        }
+    }
+    change
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: ", file.display(), line)?,
+            (Some(file), None) => write!(f, "{}: ", file.display())?,
+            _ => {}
+        }
+        match self.path.is_empty() {
+            true => write!(f, "{}", self.message),
+            false => write!(f, "{}: {}", self.path, self.message),
+        }
+    }
+}
+
+/// serde 的错误信息后面会列出所有可选值，太长了只保留前面的部分
+fn short(e: serde_json::Error) -> String {
+    let msg = e.to_string();
+    match msg.find(", expected one of") {
+        Some(id) => msg[..id].to_string(),
+        None => msg,
+    }
+}
+
+/// 检查用户配置，去掉出错的条目，返回找到的问题
+pub fn sanitize(root: &mut Value) -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+    let Some(map) = root.as_object_mut() else {
+        issues.push(ConfigIssue::new("", "the config must be an object"));
+        *root = Value::Object(Map::new());
+        return issues;
+    };
+    map.retain(|name, _| match SECTIONS.contains(&name.as_str()) {
+        true => true,
+        false => {
+            issues.push(ConfigIssue::new(name, "unknown section").at(name));
+            false
+        }
+    });
+    if let Some(keybindings) = map.get_mut("keybindings") {
+        check_keybindings(keybindings, &mut issues);
+    }
+    if let Some(styles) = map.get_mut("styles") {
+        migrate_mode_styles(styles, &mut issues);
+    }
+    if let Some(overrides) = map
+        .get_mut("styles")
+        .and_then(|s| s.as_object_mut())
+        .and_then(|s| s.get_mut("overrides"))
+    {
+        check_style_overrides(overrides, &mut issues);
+    }
+    if let Some(presets) = map
+        .get_mut("layout")
+        .and_then(|s| s.as_object_mut())
+        .and_then(|s| s.get_mut("presets"))
+    {
+        check_presets(presets, &mut issues);
+    }
+    if let Some(macros) = map.get_mut("macros") {
+        check_macros(macros, &mut issues);
+    }
+    check_fields::<super::Styles>(map, "styles", &mut issues);
+    check_fields::<super::ConsoleConfig>(map, "console", &mut issues);
+    check_fields::<super::InputConfig>(map, "input", &mut issues);
+    check_fields::<super::ClipboardConfig>(map, "clipboard", &mut issues);
+    check_fields::<super::SourceConfig>(map, "source", &mut issues);
+    check_fields::<super::HighlightConfig>(map, "highlight", &mut issues);
+    check_fields::<super::LayoutConfig>(map, "layout", &mut issues);
+    check_fields::<super::HoverConfig>(map, "hover", &mut issues);
+    issues
+}
+
+/// 整个条目不能解析时逐个字段检查，只去掉出错的字段
+fn check_fields<T: DeserializeOwned>(
+    map: &mut Map<String, Value>,
+    section: &str,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let Some(value) = map.get_mut(section) else {
+        return;
+    };
+    if serde_json::from_value::<T>(value.clone()).is_ok() {
+        return;
+    }
+    let Some(fields) = value.as_object_mut() else {
+        issues.push(ConfigIssue::new(section, "must be an object").at(section));
+        map.remove(section);
+        return;
+    };
+    fields.retain(|name, field| {
+        let single = Value::Object(Map::from_iter([(name.clone(), field.clone())]));
+        match serde_json::from_value::<T>(single) {
+            Ok(_) => true,
+            Err(e) => {
+                let path = format!("{}.{}", section, name);
+                issues.push(ConfigIssue::new(path, short(e)).at(name));
+                false
+            }
+        }
+    });
+}
+
+fn check_keybindings(value: &mut Value, issues: &mut Vec<ConfigIssue>) {
+    let Some(modes) = value.as_object_mut() else {
+        issues.push(ConfigIssue::new("keybindings", "must map modes to keys"));
+        *value = Value::Object(Map::new());
+        return;
+    };
+    modes.retain(|mode, keys| {
+        if serde_json::from_value::<Mode>(Value::String(mode.clone())).is_err() {
+            let path = format!("keybindings.{}", mode);
+            issues.push(ConfigIssue::new(path, format!("unknown mode `{}`", mode)).at(mode));
+            return false;
+        }
+        let Some(keys) = keys.as_object_mut() else {
+            let path = format!("keybindings.{}", mode);
+            issues.push(ConfigIssue::new(path, "must map keys to actions").at(mode));
+            return false;
+        };
+        keys.retain(|key, action| {
+            let path = format!("keybindings.{}.{}", mode, key);
+            if let Err(e) = parse_key_sequence(key) {
+                issues.push(ConfigIssue::new(path, e).at(key));
+                return false;
+            }
+            match serde_json::from_value::<Action>(action.clone()) {
+                Ok(_) => true,
+                Err(e) => {
+                    let msg = format!("bad action {}: {}", action, short(e));
+                    issues.push(ConfigIssue::new(path, msg).at(key));
+                    false
+                }
+            }
+        });
+        true
+    });
+}
+
+/// 旧版本的样式按模式分组，例如 `styles: {Code: {title: "..."}}`。
+/// 去掉这些分组，认得的名字移到 `overrides`，并提示一次格式已经改变
+fn migrate_mode_styles(value: &mut Value, issues: &mut Vec<ConfigIssue>) {
+    let Some(styles) = value.as_object_mut() else {
+        return;
+    };
+    let modes = styles
+        .keys()
+        .filter(|key| serde_json::from_value::<Mode>(Value::String(key.to_string())).is_ok())
+        .cloned()
+        .collect::<Vec<_>>();
+    let Some(first) = modes.first().cloned() else {
+        return;
+    };
+    let mut moved = Map::new();
+    for mode in modes.iter() {
+        if let Some(Value::Object(old)) = styles.remove(mode) {
+            old.into_iter()
+                .filter(|(key, _)| {
+                    serde_json::from_value::<StyleKey>(Value::String(key.clone())).is_ok()
+                })
+                .for_each(|(key, style)| {
+                    moved.entry(key).or_insert(style);
+                });
+        }
+    }
+    let count = moved.len();
+    let overrides = styles
+        .entry("overrides")
+        .or_insert_with(|| Value::Object(Map::new()));
+    if let Some(overrides) = overrides.as_object_mut() {
+        moved.into_iter().for_each(|(key, style)| {
+            overrides.entry(key).or_insert(style);
+        });
+    }
+    let msg = format!(
+        "styles per mode ({}) are no longer supported, set them in `styles.overrides`; \
+         {} known entries were moved there",
+        modes.join(", "),
+        count
+    );
+    issues.push(ConfigIssue::new(format!("styles.{}", first), msg).at(&first));
+}
+
+fn check_style_overrides(value: &mut Value, issues: &mut Vec<ConfigIssue>) {
+    let Some(overrides) = value.as_object_mut() else {
+        issues.push(ConfigIssue::new(
+            "styles.overrides",
+            "must map names to styles",
+        ));
+        *value = Value::Object(Map::new());
+        return;
+    };
+    overrides.retain(|key, style| {
+        let path = format!("styles.overrides.{}", key);
+        if let Err(e) = serde_json::from_value::<StyleKey>(Value::String(key.clone())) {
+            issues.push(ConfigIssue::new(path, short(e)).at(key));
+            return false;
+        }
+        let error = match style.as_str() {
+            Some(style) => style_error(style),
+            None => Some("a style must be a string".to_string()),
+        };
+        match error {
+            Some(msg) => {
+                issues.push(ConfigIssue::new(path, msg).at(key));
+                false
+            }
+            None => true,
+        }
+    });
+}
+
+fn check_presets(value: &mut Value, issues: &mut Vec<ConfigIssue>) {
+    let Some(presets) = value.as_object_mut() else {
+        issues.push(ConfigIssue::new(
+            "layout.presets",
+            "must map names to layouts",
+        ));
+        *value = Value::Object(Map::new());
+        return;
+    };
+    presets.retain(
+        |name, node| match serde_json::from_value::<LayoutNode>(node.clone()) {
+            Ok(_) => true,
+            Err(e) => {
+                let path = format!("layout.presets.{}", name);
+                issues.push(ConfigIssue::new(path, short(e)).at(name));
+                false
+            }
+        },
+    );
+}
+
+fn check_macros(value: &mut Value, issues: &mut Vec<ConfigIssue>) {
+    let Some(all) = value.as_object_mut() else {
+        issues.push(ConfigIssue::new("macros", "must map names to macros"));
+        *value = Value::Object(Map::new());
+        return;
+    };
+    all.retain(|name, m| {
+        let path = format!("macros.{}", name);
+        let error = match serde_json::from_value::<MacroConfig>(m.clone()) {
+            Ok(m) => m
+                .commands
+                .iter()
+                .map(|command| match command {
+                    MacroCommand::Console(cmd) | MacroCommand::Mi { mi: cmd } => cmd,
+                })
+                .find_map(|cmd| macros::check_template(cmd).err()),
+            Err(e) => Some(short(e)),
+        };
+        match error {
+            Some(msg) => {
+                issues.push(ConfigIssue::new(path, msg).at(name));
+                false
+            }
+            None => true,
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_sanitize() {
+        let mut root: Value = json5::from_str(
+            r#"{
+                "keybindings": {
+                    "Code": {
+                        "<Ctrl-q>": "Quit",
+                        "<Ctrl-xx>": "Quit",
+                        "<z>": "NoSuchAction",
+                    },
+                    "Nope": {"<q>": "Quit"},
+                },
+                "styles": {
+                    "preset": "dim",
+                    "overrides": {
+                        "hint": "bold red on blue",
+                        "mode": "purple",
+                        "nope": "red",
+                        "text": "rgb999",
+                        "title": "gray30",
+                    },
+                },
+                "console": {"scrollback_lines": "many", "history_bytes": 10},
+                "layout": {"presets": {"bad": {"pane": "stack"}}},
+                "macros": {
+                    "this": {"commands": ["p *{word_under_cursor}", {"mi": "-stack-info-frame"}]},
+                    "typo": {"commands": ["p {word}"]},
+                    "empty": {"description": "no commands"},
+                },
+                "colour": {},
+            }"#,
+        )
+        .unwrap();
+        let issues = sanitize(&mut root);
+        let paths = issues.iter().map(|i| i.path.as_str()).collect::<Vec<_>>();
+        let mut sorted = paths.clone();
+        sorted.sort();
+        assert_eq!(
+            sorted,
+            vec![
+                "colour",
+                "console.scrollback_lines",
+                "keybindings.Code.<Ctrl-xx>",
+                "keybindings.Code.<z>",
+                "keybindings.Nope",
+                "layout.presets.bad",
+                "macros.empty",
+                "macros.typo",
+                "styles.overrides.mode",
+                "styles.overrides.nope",
+                "styles.overrides.text",
+                "styles.overrides.title",
+                "styles.preset",
+            ]
+        );
+        assert_eq!(
+            root,
+            serde_json::json!({
+                "keybindings": {"Code": {"<Ctrl-q>": "Quit"}},
+                "styles": {"overrides": {"hint": "bold red on blue"}},
+                "console": {"history_bytes": 10},
+                "layout": {"presets": {}},
+                "macros": {
+                    "this": {"commands": ["p *{word_under_cursor}", {"mi": "-stack-info-frame"}]},
+                },
+            })
+        );
+        // 剩下的部分一定能解析
+        assert!(serde_json::from_value::<super::super::Config>(root).is_ok());
+    }
+
+    #[test]
+    fn f_migrate_mode_styles() {
+        let mut root: Value = json5::from_str(
+            r#"{
+                "styles": {
+                    "Code": {"title": "bold blue", "no_such_style": "red"},
+                    "Gdb": {"title": "red", "hint": "green"},
+                    "overrides": {"hint": "yellow"},
+                },
+            }"#,
+        )
+        .unwrap();
+        let issues = sanitize(&mut root);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "styles.Code");
+        assert!(issues[0].message.contains("styles.overrides"));
+        assert_eq!(
+            root,
+            serde_json::json!({
+                "styles": {"overrides": {"title": "bold blue", "hint": "yellow"}},
+            })
+        );
+    }
+
+    #[test]
+    fn f_locate() {
+        let files = vec![(
+            PathBuf::from("config.json5"),
+            "{\n keybindings: {\n  Code: {\n   // \"<z>\": \"Quit\",\n   \"<z>\": \"NoSuchAction\",\n  },\n },\n}"
+                .to_string(),
+        )];
+        let mut issue = ConfigIssue::new("keybindings.Code.<z>", "bad action").at("<z>");
+        issue.locate(&files);
+        assert_eq!(issue.line, Some(5));
+        assert_eq!(
+            issue.to_string(),
+            "config.json5:5: keybindings.Code.<z>: bad action"
+        );
+        let mut issue = ConfigIssue::new("colour", "unknown section").at("colour");
+        issue.locate(&files);
+        assert_eq!(issue.to_string(), "colour: unknown section");
+    }
+
+    #[test]
+    fn f_locate_in_section() {
+        let text = r#"{
+  keybindings: {
+    Gdb: {
+      "<Esc>": "Quit",
+    },
+    "Hover": {
+      "<Esc>": "NoSuchAction",
+      "<x>": "Quit",
+    },
+  },
+  layout: { preset: "tabs" },
+  "styles": {
+    "overrides": { "preset": "bold" },
+    "preset": "nope",
+  },
+}"#;
+        let files = vec![(PathBuf::from("config.json5"), text.to_string())];
+        let line = |path: &str, needle: &str| {
+            let mut issue = ConfigIssue::new(path, "bad").at(needle);
+            issue.locate(&files);
+            issue.line
+        };
+        assert_eq!(line("keybindings.Hover.<Esc>", "<Esc>"), Some(7));
+        assert_eq!(line("keybindings.Gdb.<Esc>", "<Esc>"), Some(4));
+        assert_eq!(line("keybindings.Code.<Esc>", "<Esc>"), None);
+        assert_eq!(line("styles.preset", "preset"), Some(14));
+        assert_eq!(line("layout.presets.x", "x"), None);
+        // 写在同一行里的下一层不给行号
+        assert_eq!(line("layout.preset", "tabs"), None);
+        assert_eq!(line("styles.overrides.preset", "preset"), None);
+        assert_eq!(line("keybindings", "keybindings"), Some(2));
+    }
+}
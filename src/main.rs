@@ -26,12 +26,23 @@ async fn main() -> Result<()> {
 
     let args = Cli::parse();
     debug!("rgdb args are {:?}", &args);
+    if let Some(cli::Command::List { kind }) = args.command {
+        cli::list(kind);
+        return Ok(());
+    }
+    if args.check_config {
+        std::process::exit(cli::check_config());
+    }
+    let gdb = args.gdb_path().unwrap_or_else(|e| e.exit());
     let mut app = App::new(
         args.tick_rate,
         args.frame_rate,
-        args.gdb,
+        gdb,
         args.args,
         args.gdb_args,
+        args.transcript,
+        args.transcript_timestamp,
+        args.color,
     )?;
     let local = task::LocalSet::new();
     local
@@ -1,4 +1,5 @@
 // use bytes;
+use crate::mi::stream::unescape;
 use crate::mi::token::*;
 use serde::{Deserialize, Serialize};
 
@@ -64,7 +65,7 @@ fn get_from_signal_point(v: &ValueType) -> Option<BreakPointSignalAction> {
         rs.iter().for_each(|r| match r.variable.as_str() {
             "fullname" => {
                 if let ValueType::Const(f) = &r.value {
-                    file = Some(f.clone())
+                    file = Some(unescape(f))
                 }
             }
             "line" => {
@@ -132,7 +133,7 @@ fn get_from_bkpt(r: &ResultType) -> Option<BreakPointAction> {
             rs.iter().for_each(|r| match r.variable.as_str() {
                 "fullname" => {
                     if let ValueType::Const(f) = &r.value {
-                        file = Some(f.clone())
+                        file = Some(unescape(f))
                     }
                 }
                 "line" => {
@@ -0,0 +1,155 @@
+use crate::mi::stream::unescape;
+use crate::mi::token::*;
+use serde::{Deserialize, Serialize};
+
+/// gdb 的变量对象，`-var-create` 和 `-var-list-children` 返回
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VarObj {
+    /// gdb 中的名字，展开和删除时用它
+    pub name: String,
+    /// 显示的表达式，子成员是成员名或下标
+    pub exp: String,
+    pub value: Option<String>,
+    pub type_name: Option<String>,
+    pub numchild: u64,
+}
+
+/// 带编号的求值命令的结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EvalResult {
+    /// `-data-evaluate-expression` 的值
+    Value(String),
+    /// `-var-create` 创建的变量对象
+    Var(VarObj),
+    /// `-var-list-children` 列出的子成员
+    Children(Vec<VarObj>),
+    Error(String),
+}
+
+fn get_const<'a>(rs: &'a [ResultType], name: &str) -> Option<&'a String> {
+    rs.iter()
+        .find(|r| r.variable == name)
+        .and_then(|r| match &r.value {
+            ValueType::Const(s) => Some(s),
+            _ => None,
+        })
+}
+
+fn get_var(rs: &[ResultType]) -> Option<VarObj> {
+    Some(VarObj {
+        name: get_const(rs, "name")?.clone(),
+        exp: get_const(rs, "exp")
+            .map(|s| unescape(s))
+            .unwrap_or_default(),
+        value: get_const(rs, "value").map(|s| unescape(s)),
+        type_name: get_const(rs, "type").map(|s| unescape(s)),
+        numchild: get_const(rs, "numchild")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(0),
+    })
+}
+
+fn get_children(rs: &[ResultType]) -> Option<Vec<VarObj>> {
+    let children = rs.iter().find(|r| r.variable == "children");
+    match children.map(|r| &r.value) {
+        // 没有子成员时 gdb 不输出 children
+        None => get_const(rs, "numchild").map(|_| vec![]),
+        Some(ValueType::List(List::None)) => Some(vec![]),
+        Some(ValueType::List(List::Results(l))) => l
+            .iter()
+            .map(|r| match &r.value {
+                ValueType::Tuple(Tuple::Results(child)) => get_var(child),
+                _ => None,
+            })
+            .collect(),
+        _ => None,
+    }
+}
+
+/// 按结果的内容判断是哪种求值命令的结果
+pub fn get_eval_result(r: &ResultRecordType) -> Option<EvalResult> {
+    match r.result_class {
+        ResultClassType::Error => {
+            let msg = get_const(&r.results, "msg").map_or(String::new(), |s| unescape(s));
+            Some(EvalResult::Error(msg))
+        }
+        ResultClassType::Done => {
+            let rs = &r.results;
+            if get_const(rs, "name").is_some() {
+                get_var(rs).map(EvalResult::Var)
+            } else if get_const(rs, "numchild").is_some() {
+                get_children(rs).map(EvalResult::Children)
+            } else {
+                get_const(rs, "value").map(|v| EvalResult::Value(unescape(v)))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// 把表达式写成 mi 命令的 c-string 参数
+pub fn quote(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mi::miout;
+
+    fn parse(line: &str) -> ResultRecordType {
+        match miout::TokOutputOnelineParser::new().parse(line).unwrap() {
+            OutputOneline::ResultRecord(r) => r,
+            a => panic!("not result record {:?}", a),
+        }
+    }
+
+    #[test]
+    fn f_get_eval_result() {
+        let r = parse("12^done,value=\"{a = 1, s = \\\"hi\\\"}\"\n");
+        assert_eq!(r.token, Some(12));
+        assert_eq!(
+            get_eval_result(&r),
+            Some(EvalResult::Value("{a = 1, s = \"hi\"}".to_string()))
+        );
+
+        let r = parse("13^done,name=\"var1\",numchild=\"2\",value=\"{...}\",type=\"struct node *\",thread-id=\"1\",has_more=\"0\"\n");
+        assert_eq!(
+            get_eval_result(&r),
+            Some(EvalResult::Var(VarObj {
+                name: "var1".to_string(),
+                exp: String::new(),
+                value: Some("{...}".to_string()),
+                type_name: Some("struct node *".to_string()),
+                numchild: 2,
+            }))
+        );
+
+        let r = parse("14^done,numchild=\"2\",children=[child={name=\"var1.a\",exp=\"a\",numchild=\"0\",value=\"1\",type=\"int\",thread-id=\"1\"},child={name=\"var1.next\",exp=\"next\",numchild=\"2\",value=\"0x0\",type=\"struct node *\",thread-id=\"1\"}],has_more=\"0\"\n");
+        let Some(EvalResult::Children(children)) = get_eval_result(&r) else {
+            panic!("not children");
+        };
+        assert_eq!(children.len(), 2);
+        assert_eq!(children[1].name, "var1.next");
+        assert_eq!(children[1].exp, "next");
+        assert_eq!(children[1].numchild, 2);
+
+        let r = parse("15^done,numchild=\"0\",has_more=\"0\"\n");
+        assert_eq!(get_eval_result(&r), Some(EvalResult::Children(vec![])));
+
+        let r = parse("16^error,msg=\"No symbol \\\"x\\\" in current context.\"\n");
+        assert_eq!(
+            get_eval_result(&r),
+            Some(EvalResult::Error(
+                "No symbol \"x\" in current context.".to_string()
+            ))
+        );
+        assert_eq!(parse("^done\n").token, None);
+    }
+
+    #[test]
+    fn f_quote() {
+        assert_eq!(quote("p->next"), "\"p->next\"");
+        assert_eq!(quote("s == \"a\\n\""), "\"s == \\\"a\\\\n\\\"\"");
+    }
+}
@@ -1,4 +1,5 @@
 // use bytes;
+use crate::mi::stream::unescape;
 use crate::mi::token::*;
 use serde::{Deserialize, Serialize};
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -24,7 +25,7 @@ impl TryFrom<&ResultType> for Frame {
                 rs.iter().for_each(|r| match r.variable.as_str() {
                     "fullname" => {
                         if let ValueType::Const(f) = &r.value {
-                            ans.fullname = Some(f.clone())
+                            ans.fullname = Some(unescape(f))
                         }
                     }
                     "line" => {
@@ -0,0 +1,85 @@
+/// Splits the raw byte stream read from the gdb mi pty into complete lines.
+///
+/// Bytes are buffered until a `\n` arrives, so a multi-byte UTF-8 character
+/// split across two reads is decoded correctly. `\r` is dropped, every
+/// returned line keeps its trailing `\n` as the mi grammar expects.
+#[derive(Debug, Default)]
+pub struct LineFramer {
+    pending: Vec<u8>,
+}
+
+impl LineFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        let mut lines = vec![];
+        let mut rest = bytes;
+        while let Some(pos) = rest.iter().position(|c| *c == b'\n') {
+            let (line, tail) = rest.split_at(pos.saturating_add(1));
+            self.pending.extend_from_slice(line);
+            lines.push(Self::decode(&self.pending));
+            self.pending.clear();
+            rest = tail;
+        }
+        self.pending.extend_from_slice(rest);
+        lines
+    }
+
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn decode(line: &[u8]) -> String {
+        match line.contains(&b'\r') {
+            true => {
+                let line = line
+                    .iter()
+                    .copied()
+                    .filter(|c| *c != b'\r')
+                    .collect::<Vec<_>>();
+                String::from_utf8_lossy(&line).into_owned()
+            }
+            false => String::from_utf8_lossy(line).into_owned(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_framer_split_lines() {
+        let mut framer = LineFramer::new();
+        let lines = framer.push(b"^done\n*running,thread-id=\"all\"\n(gdb) \n");
+        assert_eq!(
+            lines,
+            vec!["^done\n", "*running,thread-id=\"all\"\n", "(gdb) \n"]
+        );
+        assert_eq!(framer.pending_len(), 0);
+    }
+
+    #[test]
+    fn f_framer_line_across_reads() {
+        let mut framer = LineFramer::new();
+        assert!(framer.push(b"=thread-sel").is_empty());
+        assert!(framer.push(b"ected,id=\"1\"").is_empty());
+        let lines = framer.push(b"\r\n^do");
+        assert_eq!(lines, vec!["=thread-selected,id=\"1\"\n"]);
+        assert_eq!(framer.pending_len(), 3);
+        let lines = framer.push(b"ne\r\n");
+        assert_eq!(lines, vec!["^done\n"]);
+    }
+
+    #[test]
+    fn f_framer_utf8_across_reads() {
+        let mut framer = LineFramer::new();
+        let s = "~\"中文😀\"\n".as_bytes();
+        let (a, b) = s.split_at(4);
+        assert!(framer.push(a).is_empty());
+        let lines = framer.push(b);
+        assert_eq!(lines, vec!["~\"中文😀\"\n"]);
+    }
+}
@@ -1,6 +1,10 @@
 pub mod breakpointmi;
 pub mod disassemble;
+pub mod evaluate;
 pub mod frame;
+pub mod framer;
+pub mod stream;
+pub mod threadgroup;
 pub mod token;
 use lalrpop_util::lalrpop_mod;
 lalrpop_mod!(
@@ -0,0 +1,126 @@
+/// A gdb mi stream record: `~` console, `@` target and `&` log output.
+///
+/// The text is the decoded c-string, escapes such as `\n` and `\"` are
+/// already turned into the characters they stand for.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum StreamRecord {
+    Console(String),
+    Target(String),
+    Log(String),
+}
+
+pub fn parse_stream_record(line: &str) -> Option<StreamRecord> {
+    let line = line.trim_end_matches(['\r', '\n']);
+    let mut chars = line.chars();
+    let kind = chars.next()?;
+    let text = unescape_c_string(chars.as_str())?;
+    match kind {
+        '~' => Some(StreamRecord::Console(text)),
+        '@' => Some(StreamRecord::Target(text)),
+        '&' => Some(StreamRecord::Log(text)),
+        _ => None,
+    }
+}
+
+/// 解码 mi 结果中的字符串值，解析后的值仍然带着 `\"` 这样的转义
+pub fn unescape(s: &str) -> String {
+    unescape_c_string(&format!("\"{}\"", s)).unwrap_or_else(|| s.to_string())
+}
+
+/// 解码带引号的 c-string，例如 `"a\tb\n"`
+///
+/// gdb 把非 ASCII 的字节写成 `\NNN` 八进制转义，先还原成字节再按 UTF-8 解码。
+fn unescape_c_string(s: &str) -> Option<String> {
+    let s = s.strip_prefix('"')?.strip_suffix('"')?;
+    let mut ans = Vec::with_capacity(s.len());
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0_u8; 4];
+            ans.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next()? {
+            'n' => ans.push(b'\n'),
+            't' => ans.push(b'\t'),
+            'r' => ans.push(b'\r'),
+            'e' => ans.push(0x1b),
+            'a' => ans.push(0x07),
+            c @ '0'..='7' => {
+                let mut byte = c.to_digit(8)?;
+                for _ in 0..2 {
+                    match chars.peek().and_then(|c| c.to_digit(8)) {
+                        Some(d) => {
+                            byte = byte * 8 + d;
+                            chars.next();
+                        }
+                        None => break,
+                    }
+                }
+                ans.push(u8::try_from(byte).ok()?);
+            }
+            c => {
+                let mut buf = [0_u8; 4];
+                ans.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    Some(String::from_utf8_lossy(&ans).into_owned())
+}
+
+/// `show substitute-path` 输出的第一行，后面跟着全部规则
+pub fn is_substitute_path_header(text: &str) -> bool {
+    text.starts_with("List of all source path substitution rules")
+}
+
+/// 解析 `show substitute-path` 输出中的一条规则，如 `` `/build' -> `/home/src'. ``
+pub fn substitute_path_rule(text: &str) -> Option<(String, String)> {
+    let text = text.trim().strip_suffix('.')?;
+    let (from, to) = text.split_once("' -> `")?;
+    let from = from.strip_prefix('`')?;
+    let to = to.strip_suffix('\'')?;
+    Some((from.to_string(), to.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn f_parse_stream_record() {
+        assert_eq!(
+            parse_stream_record("~\"Breakpoint 1 at \\\"a.c\\\"\\n\"\n"),
+            Some(StreamRecord::Console(
+                "Breakpoint 1 at \"a.c\"\n".to_string()
+            ))
+        );
+        assert_eq!(
+            parse_stream_record("&\"warning\\n\"\r\n"),
+            Some(StreamRecord::Log("warning\n".to_string()))
+        );
+        assert_eq!(parse_stream_record("^done\n"), None);
+        assert_eq!(parse_stream_record("~\"bad\\\n"), None);
+    }
+
+    #[test]
+    fn f_unescape_octal() {
+        assert_eq!(
+            parse_stream_record("~\"/home/\\344\\275\\240/a.c:3\\n\"\n"),
+            Some(StreamRecord::Console("/home/你/a.c:3\n".to_string()))
+        );
+        assert_eq!(unescape("\\0\\101\\1012"), "\0AA2");
+        assert_eq!(unescape("\\377"), "\u{fffd}");
+    }
+
+    #[test]
+    fn f_substitute_path_rule() {
+        assert_eq!(
+            substitute_path_rule("  `/build/worker-17' -> `/home/me/src'.\n"),
+            Some(("/build/worker-17".to_string(), "/home/me/src".to_string()))
+        );
+        assert_eq!(
+            substitute_path_rule("List of all source path substitution rules:\n"),
+            None
+        );
+    }
+}
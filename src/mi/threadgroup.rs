@@ -0,0 +1,48 @@
+use crate::mi::stream::unescape;
+use crate::mi::token::*;
+
+/// 从 `-list-thread-groups` 的结果中取出第一个 inferior 的可执行文件路径
+pub fn get_executable(r: &ResultRecordType) -> Option<String> {
+    if r.result_class != ResultClassType::Done {
+        return None;
+    }
+    let groups = r.results.iter().find(|r| r.variable == "groups")?;
+    match &groups.value {
+        ValueType::List(List::Values(l)) => l.iter().find_map(|v| match v {
+            ValueType::Tuple(Tuple::Results(r)) => r
+                .iter()
+                .find(|r| r.variable == "executable")
+                .and_then(|r| match &r.value {
+                    ValueType::Const(s) => Some(unescape(s)),
+                    _ => None,
+                }),
+            _ => None,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mi::miout;
+
+    #[test]
+    fn f_get_executable() {
+        let a = miout::TokOutputOnelineParser::new()
+            .parse("^done,groups=[{id=\"i1\",type=\"process\",pid=\"42\",executable=\"/tmp/a.out\",cores=[\"3\"]}]\n")
+            .unwrap();
+        let OutputOneline::ResultRecord(r) = a else {
+            panic!("not result record");
+        };
+        assert_eq!(get_executable(&r), Some("/tmp/a.out".to_string()));
+
+        let a = miout::TokOutputOnelineParser::new()
+            .parse("^done,groups=[{id=\"i1\",type=\"process\"}]\n")
+            .unwrap();
+        let OutputOneline::ResultRecord(r) = a else {
+            panic!("not result record");
+        };
+        assert_eq!(get_executable(&r), None);
+    }
+}
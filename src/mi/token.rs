@@ -93,6 +93,8 @@ pub enum ResultClassType {
 
 #[derive(Debug, PartialEq, Clone, Eq)]
 pub struct ResultRecordType {
+    /// 发出命令时加在前面的数字，用来找到结果对应的命令
+    pub token: Option<u64>,
     pub result_class: ResultClassType,
     pub results: Vec<ResultType>,
 }
@@ -649,6 +651,7 @@ mod tests {
         assert!(
             a.unwrap()
                 == OutputOneline::ResultRecord(ResultRecordType {
+                    token: None,
                     result_class: ResultClassType::Done,
                     results: vec![ResultType {
                         variable: "asm_insns".to_string(),
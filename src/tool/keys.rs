@@ -0,0 +1,246 @@
+use crate::action::Action;
+use crate::config::key_sequence_to_string;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// 一个模式下的按键绑定
+pub type KeyMap = HashMap<Vec<KeyEvent>, Action>;
+
+/// 已经输入的次数和按键，以及接着可以按的键和它们的说明
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PendingHint {
+    pub typed: String,
+    pub next: Vec<(String, String)>,
+}
+
+impl PendingHint {
+    pub fn is_empty(&self) -> bool {
+        self.typed.is_empty()
+    }
+}
+
+/// 把按键组合成绑定的序列，如 `<g><g>`、`<space><b>`，前面可以带次数，如 `10<j>`。
+/// 一个序列同时是更长序列的前缀时，等到超时或下一个键接不上时才执行它
+#[derive(Debug, Clone, Default)]
+pub struct KeySequence {
+    count: Option<usize>,
+    keys: Vec<KeyEvent>,
+    since: Option<Instant>,
+}
+
+impl KeySequence {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count.is_none() && self.keys.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 处理一个按键，返回要执行的 action，序列还没输完时返回空。
+    /// `counts` 为 false 时数字键不当作次数，用于会把按键当作文字输入的模式
+    pub fn push(&mut self, keymap: &KeyMap, key: KeyEvent, counts: bool) -> Vec<Action> {
+        if counts && self.keys.is_empty() {
+            if let Some(digit) = self.count_digit(keymap, key) {
+                let count = self.count.unwrap_or(0).saturating_mul(10);
+                self.count = Some(count.saturating_add(digit));
+                self.since = Some(Instant::now());
+                return vec![];
+            }
+        }
+        self.keys.push(key);
+        let exact = keymap.get(&self.keys);
+        let longer = keymap
+            .keys()
+            .any(|k| k.len() > self.keys.len() && k.starts_with(&self.keys));
+        match (exact, longer) {
+            (Some(action), false) => {
+                let action = self.counted(action.clone());
+                self.clear();
+                vec![action]
+            }
+            (_, true) => {
+                self.since = Some(Instant::now());
+                vec![]
+            }
+            (None, false) => {
+                // 接不上已有的按键：先结束前面的序列，再单独处理这个键
+                self.keys.pop();
+                let pending = !self.keys.is_empty();
+                let mut actions = self.flush(keymap);
+                if pending {
+                    actions.extend(self.push(keymap, key, counts));
+                }
+                actions
+            }
+        }
+    }
+
+    /// 超时后执行已经完整的序列，丢掉其余的输入
+    pub fn expire(&mut self, keymap: &KeyMap, timeout: Duration) -> Vec<Action> {
+        match self.since {
+            Some(since) if since.elapsed() >= timeout => self.flush(keymap),
+            _ => vec![],
+        }
+    }
+
+    /// 状态栏上显示的提示，只输入了次数时不列出后续按键
+    pub fn hint(&self, keymap: &KeyMap) -> PendingHint {
+        let mut typed = self.count.map(|c| c.to_string()).unwrap_or_default();
+        typed.push_str(&key_sequence_to_string(&self.keys));
+        let mut next = match self.keys.is_empty() {
+            true => vec![],
+            false => keymap
+                .iter()
+                .filter(|(k, _)| k.len() > self.keys.len() && k.starts_with(&self.keys))
+                .map(|(k, action)| {
+                    (
+                        key_sequence_to_string(&k[self.keys.len()..]),
+                        action.description(),
+                    )
+                })
+                .collect::<Vec<_>>(),
+        };
+        next.sort();
+        PendingHint { typed, next }
+    }
+
+    fn flush(&mut self, keymap: &KeyMap) -> Vec<Action> {
+        let action = keymap.get(&self.keys).cloned().map(|a| self.counted(a));
+        self.clear();
+        action.into_iter().collect()
+    }
+
+    fn counted(&self, action: Action) -> Action {
+        match self.count {
+            Some(count) => action.with_count(count),
+            None => action,
+        }
+    }
+
+    /// 数字键是否是次数的一部分。`0` 只能接在其它数字后面，
+    /// 作为按键绑定开头的数字不当作次数
+    fn count_digit(&self, keymap: &KeyMap, key: KeyEvent) -> Option<usize> {
+        let KeyCode::Char(c) = key.code else {
+            return None;
+        };
+        let digit = c.to_digit(10)? as usize;
+        if !(key.modifiers - KeyModifiers::SHIFT).is_empty() {
+            return None;
+        }
+        let bound = keymap.keys().any(|k| k.first() == Some(&key));
+        match self.count {
+            Some(_) => Some(digit),
+            None if digit != 0 && !bound => Some(digit),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::code;
+    use crate::config::parse_key_sequence;
+
+    fn keymap() -> KeyMap {
+        [
+            ("<j>", Action::Code(code::Action::Down(1))),
+            ("<g><g>", Action::Code(code::Action::Top)),
+            ("<space><l>", Action::NextLayout),
+            ("<space><z>", Action::ToggleZoom),
+            ("<z>", Action::ToggleZoom),
+            ("<z><z>", Action::SwapHV),
+            ("<0>", Action::Code(code::Action::Left(100))),
+        ]
+        .into_iter()
+        .map(|(k, a)| (parse_key_sequence(k).unwrap(), a))
+        .collect()
+    }
+
+    fn push(seq: &mut KeySequence, keymap: &KeyMap, keys: &str) -> Vec<Action> {
+        parse_key_sequence(keys)
+            .unwrap()
+            .into_iter()
+            .flat_map(|k| seq.push(keymap, k, true))
+            .collect()
+    }
+
+    #[test]
+    fn f_sequences_and_counts() {
+        let keymap = keymap();
+        let mut seq = KeySequence::new();
+        assert_eq!(
+            push(&mut seq, &keymap, "<g><g>"),
+            vec![Action::Code(code::Action::Top)]
+        );
+        assert!(seq.is_empty());
+        assert_eq!(
+            push(&mut seq, &keymap, "<1><0><j>"),
+            vec![Action::Code(code::Action::Down(10))]
+        );
+        assert_eq!(
+            push(&mut seq, &keymap, "<0>"),
+            vec![Action::Code(code::Action::Left(100))]
+        );
+        // 接不上的键让前面的序列作废，它自己照常处理
+        assert_eq!(
+            push(&mut seq, &keymap, "<g><j>"),
+            vec![Action::Code(code::Action::Down(1))]
+        );
+        assert!(push(&mut seq, &keymap, "<x>").is_empty());
+        assert!(seq.is_empty());
+    }
+
+    #[test]
+    fn f_prefix_and_timeout() {
+        let keymap = keymap();
+        let mut seq = KeySequence::new();
+        assert!(push(&mut seq, &keymap, "<z>").is_empty());
+        assert_eq!(
+            push(&mut seq, &keymap, "<j>"),
+            vec![Action::ToggleZoom, Action::Code(code::Action::Down(1))]
+        );
+        assert!(push(&mut seq, &keymap, "<z>").is_empty());
+        assert!(seq.expire(&keymap, Duration::from_secs(60)).is_empty());
+        assert_eq!(
+            seq.expire(&keymap, Duration::ZERO),
+            vec![Action::ToggleZoom]
+        );
+        assert!(seq.is_empty());
+        assert!(push(&mut seq, &keymap, "<space>").is_empty());
+        assert!(seq.expire(&keymap, Duration::ZERO).is_empty());
+        assert!(seq.is_empty());
+    }
+
+    #[test]
+    fn f_hint() {
+        let keymap = keymap();
+        let mut seq = KeySequence::new();
+        assert!(seq.hint(&keymap).is_empty());
+        push(&mut seq, &keymap, "<3>");
+        assert_eq!(
+            seq.hint(&keymap),
+            PendingHint {
+                typed: "3".to_string(),
+                next: vec![],
+            }
+        );
+        push(&mut seq, &keymap, "<space>");
+        let hint = seq.hint(&keymap);
+        assert_eq!(hint.typed, "3<space>");
+        assert_eq!(
+            hint.next,
+            vec![
+                ("<l>".to_string(), "Next layout".to_string()),
+                ("<z>".to_string(), "Zoom focused pane".to_string()),
+            ]
+        );
+    }
+}
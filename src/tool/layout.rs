@@ -0,0 +1,547 @@
+use crate::app::Mode;
+use crate::config::{
+    self,
+    layout::{LayoutNode, NodeKind, PaneKind, PaneSize, SplitDirection, DEFAULT_PRESET},
+    LayoutConfig,
+};
+use ratatui::layout::{Constraint, Direction, Layout, Position, Rect};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use tracing::error;
+
+/// 源码窗口占的百分比范围，避免把某个窗口拖到看不见
+pub const MIN_SPLIT: u16 = 10;
+pub const MAX_SPLIT: u16 = 90;
+
+pub fn clamp_split(split: i32) -> u16 {
+    split.clamp(MIN_SPLIT.into(), MAX_SPLIT.into()) as u16
+}
+
+/// 各组件共用的布局，由 App 在收到改变布局的 action 时更新，组件只读取
+pub type SharedLayout = Rc<RefCell<LayoutService>>;
+
+/// 源码和 gdb 窗口各自的内容区域和最下面的状态行，不在当前布局里的窗口是空区域
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Layouts {
+    pub src: Rect,
+    pub src_status: Rect,
+    pub gdb: Rect,
+    pub status: Rect,
+}
+
+/// 一个标签页节点的标签栏
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabBar {
+    pub area: Rect,
+    pub titles: Vec<String>,
+    pub active: usize,
+}
+
+/// 大小为 `"ratio"` 的子节点，拖动它和后一个兄弟节点的分界改变比例
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Divider {
+    parent: Rect,
+    child: Rect,
+    direction: SplitDirection,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LayoutService {
+    area: Rect,
+    presets: HashMap<String, LayoutNode>,
+    preset: String,
+    split: u16,
+    /// 交换所有分割的方向
+    flipped: bool,
+    focus: Option<PaneKind>,
+    /// 焦点窗口占满整个区域，其它窗口和标签栏都不显示
+    zoomed: bool,
+    /// 标签页节点（按在树中的先序编号）当前显示的页
+    active_tabs: HashMap<usize, usize>,
+    panes: HashMap<PaneKind, Rect>,
+    tab_bars: Vec<TabBar>,
+    divider: Option<Divider>,
+}
+
+impl LayoutService {
+    pub fn new(config: &LayoutConfig) -> Self {
+        let mut service = Self {
+            presets: config.presets.clone(),
+            split: clamp_split(config.split.into()),
+            ..Self::default()
+        };
+        if !service.set_preset(&config.preset) {
+            error!("layout preset {} not found", &config.preset);
+            service.preset = DEFAULT_PRESET.to_string();
+        }
+        service
+    }
+    pub fn shared(self) -> SharedLayout {
+        Rc::new(RefCell::new(self))
+    }
+
+    pub fn set_area(&mut self, area: Rect) {
+        if self.area != area {
+            self.area = area;
+            self.compute();
+        }
+    }
+    pub fn split(&self) -> u16 {
+        self.split
+    }
+    pub fn set_split(&mut self, split: u16) {
+        self.split = split;
+        self.compute();
+    }
+    pub fn swap(&mut self) {
+        self.flipped = !self.flipped;
+        self.compute();
+    }
+    pub fn preset(&self) -> &str {
+        &self.preset
+    }
+    /// 按名字排序的所有预设
+    pub fn preset_names(&self) -> Vec<&String> {
+        let mut names = self.presets.keys().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+    pub fn set_preset(&mut self, name: &str) -> bool {
+        if !self.presets.contains_key(name) {
+            return false;
+        }
+        self.preset = name.to_string();
+        self.active_tabs.clear();
+        if let Some(focus) = self.focus {
+            self.reveal(focus);
+        }
+        self.compute();
+        true
+    }
+    /// 重新加载配置后换上新的预设，当前预设还在时继续用它，
+    /// 分割比例、焦点和放大状态都保留
+    pub fn set_presets(&mut self, config: &LayoutConfig) {
+        self.presets = config.presets.clone();
+        let current = self.preset.clone();
+        if !self.set_preset(&current) && !self.set_preset(&config.preset) {
+            error!("layout preset {} not found", &config.preset);
+            self.preset = DEFAULT_PRESET.to_string();
+            self.compute();
+        }
+    }
+    /// 切换到按名字排序的下一个预设，返回它的名字
+    pub fn next_preset(&mut self) -> String {
+        let names = self.preset_names();
+        let next = names
+            .iter()
+            .position(|n| **n == self.preset)
+            .map(|i| names[(i + 1) % names.len()].clone())
+            .or_else(|| names.first().map(|n| n.to_string()))
+            .unwrap_or_else(|| self.preset.clone());
+        self.set_preset(&next);
+        next
+    }
+
+    /// 设置焦点窗口，它在没显示的标签页里时切换过去，返回窗口区域是否改变
+    pub fn set_focus(&mut self, pane: PaneKind) -> bool {
+        self.focus = Some(pane);
+        let old = self.panes.clone();
+        self.reveal(pane);
+        self.compute();
+        old != self.panes
+    }
+    pub fn zoomed(&self) -> bool {
+        self.zoomed
+    }
+    /// 放大或还原焦点窗口，没有焦点窗口时不放大
+    pub fn toggle_zoom(&mut self) -> bool {
+        self.zoomed = !self.zoomed && self.focus.is_some();
+        self.compute();
+        self.zoomed
+    }
+    /// 焦点移到当前布局中的下一个窗口
+    pub fn focus_next(&mut self) -> Option<PaneKind> {
+        let panes = self.root()?.panes();
+        let next = match self.focus.and_then(|f| panes.iter().position(|p| *p == f)) {
+            Some(i) => panes[(i + 1) % panes.len()],
+            None => *panes.first()?,
+        };
+        self.set_focus(next);
+        Some(next)
+    }
+
+    pub fn pane(&self, pane: PaneKind) -> Option<Rect> {
+        self.panes.get(&pane).copied()
+    }
+    pub fn layouts(&self) -> Layouts {
+        let split_status = |pane| {
+            let area = self.pane(pane).unwrap_or_default();
+            let [main, status] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(area);
+            (main, status)
+        };
+        let (src, src_status) = split_status(PaneKind::Source);
+        let (gdb, status) = split_status(PaneKind::Console);
+        Layouts {
+            src,
+            src_status,
+            gdb,
+            status,
+        }
+    }
+    pub fn tab_bars(&self) -> &Vec<TabBar> {
+        &self.tab_bars
+    }
+
+    /// 鼠标是否在可调比例的分界上：上下分割是那个窗口的最后一行（状态行），左右分割是分界两侧的两列
+    pub fn is_divider(&self, (row, column): (u16, u16)) -> bool {
+        let Some(Divider {
+            parent,
+            child,
+            direction,
+        }) = self.divider
+        else {
+            return false;
+        };
+        if !parent.contains(Position::new(column, row)) {
+            return false;
+        }
+        match direction {
+            SplitDirection::Vertical => row == child.bottom().saturating_sub(1),
+            SplitDirection::Horizontal => {
+                column == child.right().saturating_sub(1) || column == child.right()
+            }
+        }
+    }
+    /// 把分界拖到鼠标位置时的比例
+    pub fn split_at(&self, (row, column): (u16, u16)) -> Option<u16> {
+        let divider = self.divider?;
+        let (pos, start, len) = match divider.direction {
+            SplitDirection::Vertical => (
+                row.saturating_add(1),
+                divider.parent.y,
+                divider.parent.height,
+            ),
+            SplitDirection::Horizontal => (
+                column.saturating_add(1),
+                divider.parent.x,
+                divider.parent.width,
+            ),
+        };
+        let len = i32::from(len.max(1));
+        let offset = i32::from(pos.saturating_sub(start));
+        Some(clamp_split((offset * 100 + len / 2) / len))
+    }
+
+    fn root(&self) -> Option<&LayoutNode> {
+        self.presets.get(&self.preset)
+    }
+
+    /// 让包含 `pane` 的标签页显示出来
+    fn reveal(&mut self, pane: PaneKind) {
+        fn walk(
+            node: &LayoutNode,
+            pane: PaneKind,
+            id: &mut usize,
+            active: &mut HashMap<usize, usize>,
+        ) {
+            let my_id = *id;
+            *id = id.saturating_add(1);
+            match &node.kind {
+                NodeKind::Pane { .. } => {}
+                NodeKind::Split { children, .. } => {
+                    children.iter().for_each(|c| walk(c, pane, id, active))
+                }
+                NodeKind::Tabs { tabs } => tabs.iter().enumerate().for_each(|(i, c)| {
+                    if c.panes().contains(&pane) {
+                        active.insert(my_id, i);
+                    }
+                    walk(c, pane, id, active)
+                }),
+            }
+        }
+        let Some(root) = self.presets.get(&self.preset) else {
+            return;
+        };
+        walk(root, pane, &mut 0, &mut self.active_tabs);
+    }
+
+    fn compute(&mut self) {
+        self.panes.clear();
+        self.tab_bars.clear();
+        self.divider = None;
+        if let (true, Some(focus)) = (self.zoomed, self.focus) {
+            self.panes.insert(focus, self.area);
+            return;
+        }
+        if let Some(root) = self.presets.get(&self.preset).cloned() {
+            self.place(&root, self.area, true, &mut 0);
+        }
+    }
+
+    fn place(&mut self, node: &LayoutNode, area: Rect, visible: bool, id: &mut usize) {
+        let my_id = *id;
+        *id = id.saturating_add(1);
+        match &node.kind {
+            NodeKind::Pane { pane } => {
+                if visible {
+                    self.panes.entry(*pane).or_insert(area);
+                }
+            }
+            NodeKind::Split { split, children } => {
+                let direction = match self.flipped {
+                    true => split.flip(),
+                    false => *split,
+                };
+                let constraints = children.iter().map(|c| match c.size {
+                    PaneSize::Fill => Constraint::Fill(1),
+                    PaneSize::Ratio => Constraint::Percentage(self.split),
+                    PaneSize::Percent(p) => Constraint::Percentage(p),
+                    PaneSize::Length(n) => Constraint::Length(n),
+                });
+                let layout = match direction {
+                    SplitDirection::Vertical => Layout::new(Direction::Vertical, constraints),
+                    SplitDirection::Horizontal => Layout::new(Direction::Horizontal, constraints),
+                };
+                let rects = layout.split(area);
+                children.iter().zip(rects.iter()).for_each(|(child, rect)| {
+                    if visible && child.size == PaneSize::Ratio && self.divider.is_none() {
+                        self.divider = Some(Divider {
+                            parent: area,
+                            child: *rect,
+                            direction,
+                        });
+                    }
+                    self.place(child, *rect, visible, id);
+                });
+            }
+            NodeKind::Tabs { tabs } => {
+                let active = self
+                    .active_tabs
+                    .get(&my_id)
+                    .copied()
+                    .unwrap_or(0)
+                    .min(tabs.len().saturating_sub(1));
+                let [bar, rest] =
+                    Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+                if visible {
+                    self.tab_bars.push(TabBar {
+                        area: bar,
+                        titles: tabs
+                            .iter()
+                            .map(|t| {
+                                t.panes()
+                                    .iter()
+                                    .map(|p| p.name())
+                                    .collect::<Vec<_>>()
+                                    .join("+")
+                            })
+                            .collect(),
+                        active,
+                    });
+                }
+                tabs.iter().enumerate().for_each(|(i, child)| {
+                    self.place(child, rest, visible && i == active, id);
+                });
+            }
+        }
+    }
+}
+
+/// 模式对应的焦点窗口，可视模式可以在两个窗口中选择，没有对应的窗口
+pub fn pane_of_mode(mode: Mode) -> Option<PaneKind> {
+    match mode {
+        Mode::Gdb | Mode::Search => Some(PaneKind::Console),
+        Mode::Code | Mode::Locate | Mode::Hover => Some(PaneKind::Source),
+        Mode::Visual | Mode::Palette | Mode::Help => None,
+    }
+}
+
+impl From<PaneKind> for Mode {
+    fn from(pane: PaneKind) -> Self {
+        match pane {
+            PaneKind::Source => Mode::Code,
+            PaneKind::Console => Mode::Gdb,
+        }
+    }
+}
+
+/// 上次调整的比例和使用的预设，优先于配置文件中的默认值
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedLayout {
+    #[serde(default)]
+    pub split: Option<u16>,
+    #[serde(default)]
+    pub preset: Option<String>,
+}
+
+impl SavedLayout {
+    pub fn path() -> PathBuf {
+        config::get_data_dir().join("layout.json")
+    }
+
+    pub fn load(path: &Path) -> Self {
+        let Ok(s) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<SavedLayout>(&s) {
+            Ok(saved) => Self {
+                split: saved.split.map(|s| clamp_split(s.into())),
+                ..saved
+            },
+            Err(e) => {
+                error!("read {:?} fail: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> color_eyre::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(preset: &str, area: Rect) -> LayoutService {
+        let mut layout = LayoutService::new(&LayoutConfig {
+            split: 25,
+            preset: preset.to_string(),
+            ..LayoutConfig::default()
+        });
+        layout.set_area(area);
+        layout
+    }
+
+    #[test]
+    fn f_layouts_split() {
+        let area = Rect::new(0, 0, 100, 40);
+        let layouts = service("stacked", area).layouts();
+        assert_eq!(layouts.src, Rect::new(0, 0, 100, 9));
+        assert_eq!(layouts.src_status, Rect::new(0, 9, 100, 1));
+        assert_eq!(layouts.gdb, Rect::new(0, 10, 100, 29));
+        assert_eq!(layouts.status, Rect::new(0, 39, 100, 1));
+        let mut layout = service("side_by_side", area);
+        assert_eq!(layout.layouts().src.width, 25);
+        assert_eq!(layout.layouts().gdb.x, 25);
+        layout.swap();
+        assert_eq!(layout.layouts().src.width, 100);
+        assert_eq!(layout.layouts().src_status.y, 9);
+    }
+
+    #[test]
+    fn f_tabs_and_focus() {
+        let area = Rect::new(0, 0, 80, 24);
+        let mut layout = service("tabs", area);
+        assert_eq!(layout.layouts().src, Rect::new(0, 1, 80, 22));
+        assert!(layout.layouts().gdb.is_empty());
+        assert_eq!(layout.tab_bars()[0].titles, vec!["source", "console"]);
+        assert_eq!(layout.focus_next(), Some(PaneKind::Source));
+        assert_eq!(layout.focus_next(), Some(PaneKind::Console));
+        assert!(layout.layouts().src.is_empty());
+        assert_eq!(layout.layouts().status, Rect::new(0, 23, 80, 1));
+        assert_eq!(layout.tab_bars()[0].active, 1);
+        assert!(!layout.set_focus(PaneKind::Console));
+        assert!(layout.set_focus(PaneKind::Source));
+    }
+
+    #[test]
+    fn f_zoom() {
+        let area = Rect::new(0, 0, 80, 24);
+        let mut layout = service("stacked", area);
+        assert!(!layout.toggle_zoom());
+        layout.set_focus(PaneKind::Console);
+        let normal = layout.layouts();
+        assert!(layout.toggle_zoom());
+        assert!(layout.layouts().src.is_empty());
+        assert_eq!(layout.layouts().gdb, Rect::new(0, 0, 80, 23));
+        assert!(!layout.is_divider((5, 0)));
+        // 放大时切换焦点，放大的窗口跟着换
+        assert!(layout.set_focus(PaneKind::Source));
+        assert_eq!(layout.layouts().src, Rect::new(0, 0, 80, 23));
+        assert!(layout.layouts().gdb.is_empty());
+        layout.set_focus(PaneKind::Console);
+        assert!(!layout.toggle_zoom());
+        assert_eq!(layout.layouts(), normal);
+
+        let mut layout = service("tabs", area);
+        layout.set_focus(PaneKind::Source);
+        layout.toggle_zoom();
+        assert!(layout.tab_bars().is_empty());
+        layout.toggle_zoom();
+        assert_eq!(layout.tab_bars().len(), 1);
+    }
+
+    #[test]
+    fn f_presets() {
+        let mut layout = service("no such", Rect::new(0, 0, 80, 24));
+        assert_eq!(layout.preset(), DEFAULT_PRESET);
+        assert_eq!(
+            layout.preset_names(),
+            vec!["side_by_side", "stacked", "tabs"]
+        );
+        assert_eq!(layout.next_preset(), "tabs");
+        assert_eq!(layout.next_preset(), "side_by_side");
+        assert!(!layout.set_preset("no such"));
+        assert_eq!(layout.preset(), "side_by_side");
+    }
+
+    #[test]
+    fn f_set_presets() {
+        let mut layout = service("side_by_side", Rect::new(0, 0, 80, 24));
+        layout.set_split(60);
+        layout.set_focus(PaneKind::Console);
+        layout.toggle_zoom();
+        let mut config = LayoutConfig::default();
+        config.presets.remove("tabs");
+        layout.set_presets(&config);
+        assert_eq!(layout.preset(), "side_by_side");
+        assert_eq!(layout.split(), 60);
+        assert!(layout.zoomed());
+        assert_eq!(layout.preset_names(), vec!["side_by_side", "stacked"]);
+        config.presets.remove("side_by_side");
+        layout.set_presets(&config);
+        assert_eq!(layout.preset(), DEFAULT_PRESET);
+    }
+
+    #[test]
+    fn f_divider_drag() {
+        let area = Rect::new(0, 0, 100, 40);
+        let layout = service("stacked", area);
+        assert!(layout.is_divider((9, 7)));
+        assert!(!layout.is_divider((10, 7)));
+        assert_eq!(layout.split_at((29, 0)), Some(75));
+        assert_eq!(layout.split_at((0, 0)), Some(MIN_SPLIT));
+        assert_eq!(layout.split_at((39, 0)), Some(MAX_SPLIT));
+        let layout = service("side_by_side", area);
+        assert!(layout.is_divider((3, 24)));
+        assert!(layout.is_divider((3, 25)));
+        assert!(!layout.is_divider((3, 23)));
+        assert_eq!(layout.split_at((0, 59)), Some(60));
+        assert!(!service("tabs", area).is_divider((0, 0)));
+    }
+
+    #[test]
+    fn f_save_load() {
+        let path = std::env::temp_dir().join(format!("rgdb-layout-{}.json", std::process::id()));
+        assert_eq!(SavedLayout::load(&path), SavedLayout::default());
+        let saved = SavedLayout {
+            split: Some(65),
+            preset: Some("tabs".to_string()),
+        };
+        saved.save(&path).unwrap();
+        assert_eq!(SavedLayout::load(&path), saved);
+        std::fs::write(&path, r#"{"split": 300}"#).unwrap();
+        assert_eq!(SavedLayout::load(&path).split, Some(MAX_SPLIT));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
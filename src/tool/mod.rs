@@ -1,76 +1,34 @@
 use crate::action;
 use crate::components::code::breakpoint::BreakPointData;
+use crate::config::{get_config_dir, HighlightConfig};
 use color_eyre::{eyre::Ok, Result};
+use lazy_static::lazy_static;
 use libc::ptsname;
-use ratatui::layout::{Constraint, Layout, Rect};
 use std::collections::HashMap;
-use std::ffi::CStr;
+use std::ffi::{CStr, OsStr};
 use std::hash::Hash;
+use std::path::Path;
 use std::rc::Rc;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
 use tokio::sync::mpsc::UnboundedSender;
 use tracing::error;
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 const NORD_THEME: &str = include_str!("../themes/Nord.tmTheme");
+pub const DEFAULT_THEME: &str = "Nord";
 const ASSEMBLY_X86_64: &str = include_str!("../syntaxes/assembly_x86_64.sublime-syntax");
 
+pub mod keys;
+pub mod layout;
+pub use layout::Layouts;
+
 pub fn get_pty_name(fd: i32) -> Result<String> {
     let name = unsafe { ptsname(fd) };
     let c_str = unsafe { CStr::from_ptr(name) }.to_str()?;
     Ok(c_str.to_string())
 }
 
-fn get_layout_vertical(area: Rect) -> [Rect; 4] {
-    Layout::vertical([
-        Constraint::Percentage(50),
-        Constraint::Length(1),
-        Constraint::Fill(1),
-        Constraint::Length(1),
-    ])
-    .areas(area)
-}
-fn get_layout_horizontal(area: Rect) -> [Rect; 4] {
-    let [src, gdb] =
-        Layout::horizontal([Constraint::Percentage(50), Constraint::Fill(1)]).areas(area);
-    let [src, src_status] =
-        Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(src);
-    let [gdb, status] = Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(gdb);
-    [src, src_status, gdb, status]
-}
-
-pub struct Layouts {
-    pub src: Rect,
-    pub src_status: Rect,
-    pub gdb: Rect,
-    pub status: Rect,
-}
-
-impl From<(ratatui::layout::Rect, bool)> for Layouts {
-    fn from(area: (ratatui::layout::Rect, bool)) -> Self {
-        let (area, is_horizontal) = area;
-        match is_horizontal {
-            false => {
-                let [src, src_status, gdb, status] = get_layout_vertical(area);
-                Layouts {
-                    src,
-                    src_status,
-                    gdb,
-                    status,
-                }
-            }
-            true => {
-                let [src, src_status, gdb, status] = get_layout_horizontal(area);
-                Layouts {
-                    src,
-                    src_status,
-                    gdb,
-                    status,
-                }
-            }
-        }
-    }
-}
-
 pub trait HashSelf<T: Hash> {
     fn get_key(&self) -> Rc<T>;
 }
@@ -100,12 +58,12 @@ pub trait HighlightFileData
 where
     Self: TextFileData,
 {
-    fn get_highlight_done(&self) -> bool;
-    fn set_highlight_done(&mut self);
+    /// 还没高亮的行用 `text` 颜色显示
     fn get_highlight_lines_range(
         &self,
         start: usize,
         end: usize,
+        text: ratatui::style::Color,
     ) -> (Vec<Vec<(ratatui::style::Color, String)>>, usize, usize);
 }
 pub trait FileData:
@@ -120,36 +78,225 @@ pub fn addr_to_u64(value: &str) -> Option<u64> {
     }
 }
 
-pub fn get_theme() -> syntect::highlighting::Theme {
+lazy_static! {
+    static ref THEME_SET: ThemeSet = load_theme_set(&get_config_dir().join("themes"));
+    static ref SYNTAX_SET: SyntaxSet = load_syntax_set(&get_config_dir().join("syntaxes"));
+    static ref ASM_SYNTAX_SET: SyntaxSet = load_asm_syntax_set();
+}
+
+/// 自带的主题、syntect 的默认主题，再加上配置目录 `themes` 下的 `.tmTheme`
+fn load_theme_set(dir: &Path) -> ThemeSet {
+    let mut themes = ThemeSet::load_defaults();
     let mut nord_theme = std::io::Cursor::new(NORD_THEME.as_bytes());
-    match syntect::highlighting::ThemeSet::load_from_reader(&mut nord_theme) {
-        std::result::Result::Ok(theme) => theme,
-        std::result::Result::Err(_) => syntect::highlighting::Theme::default(),
+    match ThemeSet::load_from_reader(&mut nord_theme) {
+        std::result::Result::Ok(theme) => {
+            themes.themes.insert(DEFAULT_THEME.to_string(), theme);
+        }
+        std::result::Result::Err(e) => error!("load theme {} fail: {}", DEFAULT_THEME, e),
+    }
+    if dir.is_dir() {
+        if let Err(e) = themes.add_from_folder(dir) {
+            error!("load themes from {:?} fail: {}", dir, e);
+        }
+    }
+    themes
+}
+
+/// syntect 的默认语法，再加上配置目录 `syntaxes` 下的 `.sublime-syntax`
+fn load_syntax_set(dir: &Path) -> SyntaxSet {
+    let defaults = SyntaxSet::load_defaults_newlines();
+    if !dir.is_dir() {
+        return defaults;
+    }
+    let mut builder = defaults.into_builder();
+    match builder.add_from_folder(dir, true) {
+        std::result::Result::Ok(_) => builder.build(),
+        std::result::Result::Err(e) => {
+            error!("load syntaxes from {:?} fail: {}", dir, e);
+            SyntaxSet::load_defaults_newlines()
+        }
+    }
+}
+
+fn load_asm_syntax_set() -> SyntaxSet {
+    let mut builder = syntect::parsing::SyntaxSetBuilder::new();
+    match syntect::parsing::syntax_definition::SyntaxDefinition::load_from_str(
+        ASSEMBLY_X86_64,
+        true,
+        None,
+    ) {
+        std::result::Result::Ok(a) => {
+            builder.add(a);
+        }
+        std::result::Result::Err(_) => {
+            error!("Failed to load syntaxes from asm");
+        }
+    };
+    builder.build()
+}
+
+/// 按名字取主题，找不到时用自带的 Nord
+pub fn get_theme(name: &str) -> Theme {
+    match THEME_SET
+        .themes
+        .get(name)
+        .or_else(|| THEME_SET.themes.get(DEFAULT_THEME))
+    {
+        Some(theme) => {
+            if !THEME_SET.themes.contains_key(name) {
+                error!("theme {} not found, use {}", name, DEFAULT_THEME);
+            }
+            theme.clone()
+        }
+        None => Theme::default(),
+    }
+}
+
+pub fn get_syntax_set(ext: &str) -> &'static SyntaxSet {
+    match ext {
+        "asm" => &ASM_SYNTAX_SET,
+        _ => &SYNTAX_SET,
     }
 }
 
-pub fn get_syntax_set(ext: &str) -> syntect::parsing::SyntaxSet {
-    let syntax_set = match ext {
-        "asm" => {
-            let mut builder = syntect::parsing::SyntaxSetBuilder::new();
-
-            match syntect::parsing::syntax_definition::SyntaxDefinition::load_from_str(
-                ASSEMBLY_X86_64,
-                true,
-                None,
-            ) {
-                std::result::Result::Ok(a) => {
-                    builder.add(a);
-                }
-                std::result::Result::Err(_) => {
-                    error!("Failed to load syntaxes from asm");
-                }
-            };
-            builder.build()
+pub fn theme_names() -> Vec<&'static str> {
+    THEME_SET.themes.keys().map(String::as_str).collect()
+}
+
+pub fn syntaxes() -> &'static [SyntaxReference] {
+    SYNTAX_SET.syntaxes()
+}
+
+/// 在文件开头和结尾的这么多行中查找 modeline
+const MODELINE_LINES: usize = 5;
+
+/// 找源码文件的语法
+///
+/// 依次尝试配置的 glob 规则、扩展名、modeline、第一行（如 `#!/bin/sh`），
+/// 最后把 C++ 头文件目录下没有扩展名的文件（如 `vector`）当作 C++。
+pub fn find_syntax(
+    file_name: &str,
+    lines: &[String],
+    config: &HighlightConfig,
+) -> Option<&'static SyntaxReference> {
+    let by_rule = config
+        .syntax_map
+        .iter()
+        .filter(|rule| glob_match(&rule.glob, file_name))
+        .find_map(|rule| {
+            let syntax = find_syntax_by_name(&rule.syntax);
+            if syntax.is_none() {
+                error!("syntax {} for {} not found", &rule.syntax, &rule.glob);
+            }
+            syntax
+        });
+    let modeline = || {
+        let tail = lines
+            .len()
+            .saturating_sub(MODELINE_LINES)
+            .max(MODELINE_LINES);
+        lines
+            .iter()
+            .take(MODELINE_LINES)
+            .chain(lines.iter().skip(tail))
+            .filter_map(|line| modeline_syntax(line))
+            .find_map(|name| find_syntax_by_name(&name))
+    };
+    let in_include_dir = || {
+        let no_ext = Path::new(file_name).extension().is_none();
+        match no_ext
+            && config
+                .cpp_include_dirs
+                .iter()
+                .any(|dir| glob_match(&format!("{}/**", dir.trim_end_matches('/')), file_name))
+        {
+            true => SYNTAX_SET.find_syntax_by_name("C++"),
+            false => None,
         }
-        _ => syntect::parsing::SyntaxSet::load_defaults_newlines(),
     };
-    syntax_set
+    by_rule
+        .or_else(|| {
+            Path::new(file_name)
+                .extension()
+                .and_then(OsStr::to_str)
+                .and_then(|ext| SYNTAX_SET.find_syntax_by_extension(ext))
+        })
+        .or_else(modeline)
+        .or_else(|| {
+            lines
+                .first()
+                .and_then(|line| SYNTAX_SET.find_syntax_by_first_line(line))
+        })
+        .or_else(in_include_dir)
+}
+
+/// 从 modeline 中取出语法名
+///
+/// 支持 Emacs 的 `-*- C++ -*-`、`-*- mode: c++; -*-` 和 Vim 的 `vim: set ft=cpp:`。
+pub fn modeline_syntax(line: &str) -> Option<String> {
+    lazy_static! {
+        static ref EMACS: regex::Regex = regex::Regex::new(r"-\*-\s*(.*?)\s*-\*-").unwrap();
+        static ref EMACS_MODE: regex::Regex =
+            regex::Regex::new(r"(?i)(?:^|;)\s*mode\s*:\s*([^;\s]+)").unwrap();
+        static ref VIM: regex::Regex = regex::Regex::new(
+            r"(?:^|\s)(?:vi|vim|ex):.*?\b(?:ft|filetype|syntax)=([A-Za-z0-9_+.-]+)"
+        )
+        .unwrap();
+    }
+    if let Some(c) = EMACS.captures(line) {
+        let vars = c.get(1)?.as_str();
+        return match vars.contains(':') {
+            true => EMACS_MODE
+                .captures(vars)
+                .and_then(|c| c.get(1))
+                .map(|m| m.as_str().to_string()),
+            false => Some(vars.trim_end_matches(';').trim().to_string()),
+        };
+    }
+    VIM.captures(line)
+        .and_then(|c| c.get(1))
+        .map(|m| m.as_str().to_string())
+}
+
+/// 按语法名字查找，也接受 `cpp` 这样的扩展名
+pub fn find_syntax_by_name(name: &str) -> Option<&'static SyntaxReference> {
+    SYNTAX_SET
+        .find_syntax_by_name(name)
+        .or_else(|| SYNTAX_SET.find_syntax_by_token(name))
+}
+
+/// 简单的 glob 匹配：`**` 匹配任意路径，`*` 和 `?` 不跨过 `/`
+///
+/// 不含 `/` 的模式只和文件名匹配，例如 `*.inl`；含 `/` 的和整个路径匹配。
+pub fn glob_match(glob: &str, path: &str) -> bool {
+    let target = match glob.contains('/') {
+        true => path,
+        false => Path::new(path)
+            .file_name()
+            .and_then(OsStr::to_str)
+            .unwrap_or(path),
+    };
+    let mut re = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                re.push_str(".*");
+            }
+            '*' => re.push_str("[^/]*"),
+            '?' => re.push_str("[^/]"),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    match regex::Regex::new(&re) {
+        std::result::Result::Ok(re) => re.is_match(target),
+        std::result::Result::Err(e) => {
+            error!("bad glob {}: {}", glob, e);
+            false
+        }
+    }
 }
 
 pub fn send_action(send: &UnboundedSender<action::Action>, action: action::Action) {
@@ -205,6 +352,62 @@ pub fn get_str_by_display_range(
     ret
 }
 
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// 显示列 `column` 处标识符的字节范围
+fn word_range_at(s: &str, column: usize) -> Option<(usize, usize)> {
+    let mut display = 0_usize;
+    let (id, c) = s.char_indices().find(|(_, c)| {
+        display += c.to_string().width();
+        display > column
+    })?;
+    if !is_word_char(c) {
+        return None;
+    }
+    let start = word_start(s, id);
+    let end = s[id..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(s.len(), |(i, _)| id + i);
+    Some((start, end))
+}
+
+/// `end` 前面连着的标识符的开头
+fn word_start(s: &str, end: usize) -> usize {
+    s[..end]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(end, |(i, _)| i)
+}
+
+/// 显示列 `column` 处的标识符，不在标识符上时返回 None
+pub fn word_at(s: &str, column: usize) -> Option<&str> {
+    word_range_at(s, column).map(|(start, end)| &s[start..end])
+}
+
+/// 显示列 `column` 处的标识符连同它前面的成员访问，如 `p->next` 中的 `next`
+/// 得到 `p->next`，用来在当前栈帧中求值
+pub fn expression_at(s: &str, column: usize) -> Option<&str> {
+    let (mut start, end) = word_range_at(s, column)?;
+    loop {
+        let before = s[..start].trim_end();
+        let Some(op) = [".", "->", "::"].iter().find(|op| before.ends_with(*op)) else {
+            break;
+        };
+        let before = before[..before.len() - op.len()].trim_end();
+        let word = word_start(before, before.len());
+        if word == before.len() {
+            break;
+        }
+        start = word;
+    }
+    Some(&s[start..end])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,7 +421,78 @@ mod tests {
 
     #[test]
     fn test_theme() {
-        get_theme();
+        get_theme(DEFAULT_THEME);
+        assert!(theme_names().contains(&DEFAULT_THEME));
+        assert_eq!(
+            get_theme("no such theme").name,
+            get_theme(DEFAULT_THEME).name
+        );
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.inl", "/usr/include/a.inl"));
+        assert!(!glob_match("*.inl", "/usr/include/a.inlx"));
+        assert!(glob_match(
+            "/usr/include/c++/**",
+            "/usr/include/c++/13/vector"
+        ));
+        assert!(!glob_match("/usr/include/*", "/usr/include/c++/13/vector"));
+        assert!(glob_match("a?.c", "ab.c"));
+    }
+
+    #[test]
+    fn test_find_syntax() {
+        let config = HighlightConfig::default();
+        assert_eq!(find_syntax("/a/b.cu", &[], &config).unwrap().name, "C++");
+        assert_eq!(find_syntax("/a/b.rs", &[], &config).unwrap().name, "Rust");
+        assert!(find_syntax("/a/b.nothing", &[], &config).is_none());
+    }
+
+    #[test]
+    fn test_find_syntax_no_extension() {
+        let config = HighlightConfig::default();
+        let lines = |s: &[&str]| s.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            find_syntax(
+                "/opt/x/vector",
+                &lines(&["// <vector> -*- C++ -*-\n"]),
+                &config
+            )
+            .unwrap()
+            .name,
+            "C++"
+        );
+        assert_eq!(
+            find_syntax("/opt/x/run", &lines(&["#!/bin/bash\n"]), &config)
+                .unwrap()
+                .name,
+            "Bourne Again Shell (bash)"
+        );
+        assert_eq!(
+            find_syntax("/usr/include/c++/13/memory", &lines(&["\n"]), &config)
+                .unwrap()
+                .name,
+            "C++"
+        );
+        assert!(find_syntax("/opt/x/README", &lines(&["hello\n"]), &config).is_none());
+    }
+
+    #[test]
+    fn test_modeline_syntax() {
+        assert_eq!(
+            modeline_syntax("// <memory> -*- C++ -*-"),
+            Some("C++".to_string())
+        );
+        assert_eq!(
+            modeline_syntax("/* -*- mode: c++; tab-width: 4 -*- */"),
+            Some("c++".to_string())
+        );
+        assert_eq!(
+            modeline_syntax("// vim: set ft=cpp ts=4:"),
+            Some("cpp".to_string())
+        );
+        assert_eq!(modeline_syntax("int vim = 1;"), None);
     }
 
     #[test]
@@ -327,4 +601,28 @@ mod tests {
         assert_eq!(get_str_by_display_range(&s, 10, 15), None);
         assert_eq!(get_str_by_display_range(&s, 0, 10), Some(("12345", 0, 5)));
     }
+
+    #[test]
+    fn test_word_at() {
+        let s = "  p->next = 中文_var;\n";
+        assert_eq!(word_at(s, 2), Some("p"));
+        assert_eq!(word_at(s, 3), None);
+        assert_eq!(word_at(s, 5), Some("next"));
+        assert_eq!(word_at(s, 8), Some("next"));
+        assert_eq!(word_at(s, 12), Some("中文_var"));
+        assert_eq!(word_at(s, 15), Some("中文_var"));
+        assert_eq!(word_at(s, 100), None);
+    }
+
+    #[test]
+    fn test_expression_at() {
+        let s = "  a.b->c = std::max(x, p -> next);";
+        assert_eq!(expression_at(s, 2), Some("a"));
+        assert_eq!(expression_at(s, 4), Some("a.b"));
+        assert_eq!(expression_at(s, 7), Some("a.b->c"));
+        assert_eq!(expression_at(s, 16), Some("std::max"));
+        assert_eq!(expression_at(s, 20), Some("x"));
+        assert_eq!(expression_at(s, 29), Some("p -> next"));
+        assert_eq!(expression_at(s, 9), None);
+    }
 }